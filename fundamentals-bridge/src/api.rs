@@ -0,0 +1,417 @@
+use std::convert::Infallible;
+
+use fundamentals_core::{downsample::DownsampleAlgorithm, recording::Recording, viz::Viz};
+use log::warn;
+use percent_encoding::percent_decode_str;
+use tokio_util::io::ReaderStream;
+use warp::{
+    http::{header, StatusCode},
+    hyper::Body,
+    reply::Response,
+    Filter, Reply,
+};
+
+use crate::{
+    requests,
+    state::{self, StateHandle},
+    with_state,
+};
+
+/// Query parameters accepted when fetching a viz.
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct VizQuery {
+    pub t0: Option<f64>,
+    pub t1: Option<f64>,
+    /// Downsample scalar plots to roughly this many points.
+    pub points: Option<usize>,
+    #[serde(default)]
+    pub algorithm: DownsampleAlgorithm,
+}
+
+/// Shape of a viz without its data.
+#[derive(serde::Serialize, Debug)]
+pub struct VizSummary {
+    pub name: String,
+    pub source: Option<String>,
+    pub widgets: Vec<&'static str>,
+    pub samples: usize,
+    pub time_bounds: Option<(f64, f64)>,
+    pub metadata: std::collections::BTreeMap<String, serde_json::Value>,
+}
+
+impl From<&Viz> for VizSummary {
+    fn from(viz: &Viz) -> Self {
+        Self {
+            name: viz.name.clone(),
+            source: viz.source.clone(),
+            widgets: viz.widgets.iter().map(|w| w.kind()).collect(),
+            samples: viz.widgets.iter().map(|w| w.len()).sum(),
+            time_bounds: viz.time_bounds(),
+            metadata: viz.metadata.clone(),
+        }
+    }
+}
+
+/// JSON endpoints under `/api`:
+///
+/// - `GET /api/recordings`
+/// - `GET /api/recordings/{session_id}`
+/// - `GET /api/recordings/{session_id}/vizs`
+/// - `GET /api/recordings/{session_id}/vizs/{name}?t0=&t1=&points=&algorithm=`
+/// - `GET /api/recordings/{session_id}/assets/{asset_id}`
+/// - `GET /api/recordings/{session_id}/raw`
+pub fn routes(
+    state: StateHandle,
+) -> impl Filter<Extract = (Response,), Error = warp::Rejection> + Clone {
+    let recordings = warp::path!("api" / "recordings")
+        .and(warp::get())
+        .and(with_state(state.clone()))
+        .and_then(list_recordings);
+
+    let recording = warp::path!("api" / "recordings" / String)
+        .and(warp::get())
+        .and(with_state(state.clone()))
+        .and_then(get_recording);
+
+    let vizs = warp::path!("api" / "recordings" / String / "vizs")
+        .and(warp::get())
+        .and(with_state(state.clone()))
+        .and_then(list_vizs);
+
+    let viz = warp::path!("api" / "recordings" / String / "vizs" / String)
+        .and(warp::get())
+        .and(warp::query::<VizQuery>())
+        .and(with_state(state.clone()))
+        .and_then(get_viz);
+
+    let asset = warp::path!("api" / "recordings" / String / "assets" / String)
+        .and(warp::get())
+        .and(with_state(state.clone()))
+        .and_then(get_asset);
+
+    let raw = warp::path!("api" / "recordings" / String / "raw")
+        .and(warp::get())
+        .and(with_state(state))
+        .and_then(get_raw);
+
+    recordings
+        .or(recording)
+        .unify()
+        .or(vizs)
+        .unify()
+        .or(viz)
+        .unify()
+        .or(asset)
+        .unify()
+        .or(raw)
+        .unify()
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({ "error": message })),
+        status,
+    )
+    .into_response()
+}
+
+fn decode(segment: &str) -> String {
+    percent_decode_str(segment).decode_utf8_lossy().to_string()
+}
+
+/// Runs `f` on a recording. Recordings that are not open are read with
+/// `state::read_unopened`, without holding the state lock, so browsing the API does
+/// not leave every recording it touched in memory.
+async fn with_recording<R>(
+    state: &StateHandle,
+    session_id: &str,
+    f: impl FnOnce(&Recording) -> R,
+) -> Result<R, Response> {
+    let path = {
+        let state = state.lock().await;
+        if let Some(recording) = state.get_recording(session_id) {
+            return Ok(f(recording));
+        }
+        state
+            .catalog_path(session_id)
+            .map_err(|e| error(StatusCode::NOT_FOUND, e.to_string()))?
+    };
+    match state::read_unopened(state, session_id, path).await {
+        Ok(recording) => Ok(f(&recording)),
+        Err(e) => Err(error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+async fn list_recordings(state: StateHandle) -> Result<Response, Infallible> {
+    let state = state.lock().await;
+    Ok(warp::reply::json(&state.listing()).into_response())
+}
+
+async fn get_recording(session_id: String, state: StateHandle) -> Result<Response, Infallible> {
+    let session_id = decode(&session_id);
+    let state = state.lock().await;
+    Ok(
+        match state
+            .listing()
+            .into_iter()
+            .find(|info| info.session_id == session_id)
+        {
+            Some(info) => warp::reply::json(&info).into_response(),
+            None => error(
+                StatusCode::NOT_FOUND,
+                format!("No recording {}", session_id),
+            ),
+        },
+    )
+}
+
+async fn list_vizs(session_id: String, state: StateHandle) -> Result<Response, Infallible> {
+    let session_id = decode(&session_id);
+    let summaries = with_recording(&state, &session_id, |recording| {
+        recording
+            .vizs
+            .iter()
+            .map(VizSummary::from)
+            .collect::<Vec<_>>()
+    })
+    .await;
+    Ok(match summaries {
+        Ok(summaries) => warp::reply::json(&summaries).into_response(),
+        Err(response) => response,
+    })
+}
+
+async fn get_viz(
+    session_id: String,
+    name: String,
+    query: VizQuery,
+    state: StateHandle,
+) -> Result<Response, Infallible> {
+    let (session_id, name) = (decode(&session_id), decode(&name));
+    let viz = with_recording(&state, &session_id, |recording| {
+        recording
+            .get_viz(&name)
+            .map(|viz| match (query.t0, query.t1) {
+                (None, None) => viz.clone(),
+                (t0, t1) => viz.slice(t0.unwrap_or(f64::NEG_INFINITY), t1.unwrap_or(f64::INFINITY)),
+            })
+    })
+    .await;
+    let mut viz = match viz {
+        Ok(Some(viz)) => viz,
+        Ok(None) => {
+            return Ok(error(
+                StatusCode::NOT_FOUND,
+                format!("No viz named {}", name),
+            ))
+        }
+        Err(response) => return Ok(response),
+    };
+    if let Some(points) = query.points {
+        viz = requests::downsample_viz(&viz, points, query.algorithm);
+    }
+    requests::bin_histograms(&mut viz);
+    Ok(warp::reply::json(&viz).into_response())
+}
+
+async fn get_asset(
+    session_id: String,
+    asset_id: String,
+    state: StateHandle,
+) -> Result<Response, Infallible> {
+    let (session_id, asset_id) = (decode(&session_id), decode(&asset_id));
+    let asset = with_recording(&state, &session_id, |recording| {
+        recording
+            .get_asset(&asset_id)
+            .map(|asset| warp::reply::json(asset).into_response())
+    })
+    .await;
+    Ok(match asset {
+        Ok(Some(response)) => response,
+        Ok(None) => error(StatusCode::NOT_FOUND, format!("No asset {}", asset_id)),
+        Err(response) => response,
+    })
+}
+
+async fn get_raw(session_id: String, state: StateHandle) -> Result<Response, Infallible> {
+    let session_id = decode(&session_id);
+    let path = {
+        let state = state.lock().await;
+        match state.catalog.path_of(&session_id) {
+            Some(path) => path.clone(),
+            // Recordings handed to the bridge in memory have no file to send
+            None => {
+                return Ok(match state.get_recording(&session_id) {
+                    Some(recording) => warp::reply::json(recording).into_response(),
+                    None => error(
+                        StatusCode::NOT_FOUND,
+                        format!("No recording {}", session_id),
+                    ),
+                })
+            }
+        }
+    };
+
+    // Streamed, as recordings can be much larger than we want to hold in memory
+    match tokio::fs::File::open(&path).await {
+        Ok(file) => {
+            let file_name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| format!("{}.json", session_id));
+            let length = file.metadata().await.map(|m| m.len()).ok();
+            let mut response = Response::new(Body::wrap_stream(ReaderStream::new(file)));
+            let headers = response.headers_mut();
+            headers.insert(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_static("application/octet-stream"),
+            );
+            if let Ok(value) = format!("attachment; filename=\"{}\"", file_name).parse() {
+                headers.insert(header::CONTENT_DISPOSITION, value);
+            }
+            if let Some(length) = length {
+                headers.insert(header::CONTENT_LENGTH, length.into());
+            }
+            Ok(response)
+        }
+        Err(e) => {
+            warn!("Failed to read {}: {}", path.display(), e);
+            Ok(error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    use fundamentals_core::widgets::{plot_scalar::PlotScalarData, Widget};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::state::WSBridgeState;
+
+    fn plot(name: &str, samples: usize) -> Viz {
+        let data = (0..samples).map(|i| (i as f64, i as f64)).collect();
+        Viz::new(name.to_string()).with_widget(Widget::PlotScalar(PlotScalarData::new(data)))
+    }
+
+    /// A catalog with one recording file, `run-7`, and an in-memory recording, `live`.
+    fn catalog_and_live(dir: &PathBuf) -> StateHandle {
+        std::fs::create_dir_all(dir).unwrap();
+        let mut recording = Recording::new("drive".to_string(), "run-7".to_string());
+        recording.add_viz(plot("speed", 100));
+        recording.save_to_file(&dir.join("drive.json")).unwrap();
+
+        let mut state = WSBridgeState::new();
+        state.catalog.add_input(dir.clone()).unwrap();
+        let mut live = Recording::new("live".to_string(), "live".to_string());
+        live.add_viz(plot("speed", 3));
+        state.add_recording(live);
+        state.as_handle()
+    }
+
+    async fn get(state: &StateHandle, path: &str) -> (StatusCode, Value) {
+        let response = warp::test::request()
+            .path(path)
+            .reply(&routes(state.clone()))
+            .await;
+        let body = serde_json::from_slice(response.body()).unwrap_or(Value::Null);
+        (response.status(), body)
+    }
+
+    #[tokio::test]
+    async fn recordings_are_listed_and_browsed_without_opening_them() {
+        let dir = std::env::temp_dir().join(format!("fundamentals-api-{}", std::process::id()));
+        let state = catalog_and_live(&dir);
+
+        let (status, listing) = get(&state, "/api/recordings").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(listing.as_array().unwrap().len(), 2);
+        let (_, info) = get(&state, "/api/recordings/run-7").await;
+        assert_eq!(info["name"], "drive");
+        assert_eq!(info["loaded"], false);
+        let (status, _) = get(&state, "/api/recordings/missing").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (_, vizs) = get(&state, "/api/recordings/run-7/vizs").await;
+        assert_eq!(vizs[0]["name"], "speed");
+        assert_eq!(vizs[0]["samples"], 100);
+        assert_eq!(vizs[0]["time_bounds"], serde_json::json!([0.0, 99.0]));
+
+        let (_, viz) = get(&state, "/api/recordings/run-7/vizs/speed?t0=10&t1=12").await;
+        let samples = &viz["widgets"][0]["plot_scalar"]["data_x"];
+        assert_eq!(samples.as_array().unwrap().len(), 3);
+        let (_, viz) = get(&state, "/api/recordings/run-7/vizs/speed?points=10").await;
+        assert_eq!(viz["metadata"][requests::DOWNSAMPLED_FROM_KEY], 100);
+        let (status, _) = get(&state, "/api/recordings/run-7/vizs/heading").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = get(&state, "/api/recordings/run-7/assets/mesh").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Open recordings are answered from memory
+        let (_, vizs) = get(&state, "/api/recordings/live/vizs").await;
+        assert_eq!(vizs[0]["samples"], 3);
+        assert!(!state.lock().await.is_open("run-7"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn unopened_recordings_are_read_once_until_their_file_changes() {
+        let dir =
+            std::env::temp_dir().join(format!("fundamentals-api-cache-{}", std::process::id()));
+        let state = catalog_and_live(&dir);
+        get(&state, "/api/recordings/run-7/vizs").await;
+
+        // Later requests use the cached copy rather than the file
+        {
+            let mut state = state.lock().await;
+            let cached = state.last_read.as_mut().unwrap();
+            let mut recording = (*cached.recording).clone();
+            recording.add_viz(plot("cached", 1));
+            cached.recording = Arc::new(recording);
+        }
+        let (status, _) = get(&state, "/api/recordings/run-7/vizs/cached").await;
+        assert_eq!(status, StatusCode::OK);
+
+        // A rewritten file is read again
+        let path = state.lock().await.catalog_path("run-7").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        let (status, _) = get(&state, "/api/recordings/run-7/vizs/cached").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Opening the recording drops the cached copy
+        state::open_recording(&state, "run-7").await.unwrap();
+        assert!(state.lock().await.last_read.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn raw_sends_the_file_or_the_recording_in_memory() {
+        let dir = std::env::temp_dir().join(format!("fundamentals-api-raw-{}", std::process::id()));
+        let state = catalog_and_live(&dir);
+
+        let response = warp::test::request()
+            .path("/api/recordings/run-7/raw")
+            .reply(&routes(state.clone()))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"drive.json\""
+        );
+        let path = state.lock().await.catalog_path("run-7").unwrap();
+        assert_eq!(response.body().as_ref(), std::fs::read(&path).unwrap());
+
+        let (status, live) = get(&state, "/api/recordings/live/raw").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(live["session_id"], "live");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use fundamentals_core::{
+    annotation,
+    compression::Compression,
+    recording::{Recording, RecordingHeader, RecordingMetadata},
+};
+use log::{info, warn};
+
+/// File extensions picked up when scanning a directory for recordings. Compressed
+/// recordings keep the extension of their format, e.g. `.json.zst`.
+pub const RECORDING_EXTENSIONS: &[&str] = &["json", "msgpack", "mpk", "cbor", "mcap"];
+
+/// Listing entry for a recording, loaded or not.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct RecordingInfo {
+    pub name: String,
+    pub session_id: String,
+    pub metadata: RecordingMetadata,
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// File size in bytes.
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// File modification time in seconds since the unix epoch.
+    #[serde(default)]
+    pub modified: Option<f64>,
+    /// Whether the recording is currently open in the bridge.
+    #[serde(default)]
+    pub loaded: bool,
+}
+
+impl From<&Recording> for RecordingInfo {
+    fn from(recording: &Recording) -> Self {
+        Self {
+            name: recording.name.clone(),
+            session_id: recording.session_id.clone(),
+            metadata: recording.metadata.clone(),
+            path: None,
+            size: None,
+            modified: None,
+            loaded: true,
+        }
+    }
+}
+
+impl RecordingInfo {
+    fn from_header(header: RecordingHeader, path: &Path) -> Self {
+        let file_metadata = std::fs::metadata(path).ok();
+        Self {
+            name: header.name,
+            session_id: header.session_id,
+            metadata: header.metadata,
+            path: Some(path.to_path_buf()),
+            size: file_metadata.as_ref().map(|m| m.len()),
+            modified: file_metadata
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs_f64()),
+            loaded: false,
+        }
+    }
+}
+
+/// Recording files the bridge knows about, indexed by canonical path. Only headers
+/// are read when indexing; recordings are loaded when a client opens them.
+///
+/// Clients refer to recordings by session id, which files do not always make
+/// unique: merged files and copies share ids, and MCAP files from other tools are
+/// named after the file. An entry whose id is already taken by another file is
+/// listed, and opened, as `{session_id}~{n}`.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+pub struct RecordingCatalog {
+    /// Files, directories or glob patterns passed on the command line.
+    pub inputs: Vec<PathBuf>,
+    pub entries: BTreeMap<PathBuf, RecordingInfo>,
+}
+
+impl RecordingCatalog {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a file, directory or glob pattern and indexes every recording it matches.
+    pub fn add_input(&mut self, input: PathBuf) -> Result<usize, anyhow::Error> {
+        let paths = discover(&input)?;
+        self.inputs.push(input);
+        let count = paths.len();
+        for path in paths {
+            if let Err(e) = self.index_file(&path) {
+                warn!("Skipping {}: {}", path.display(), e);
+            }
+        }
+        Ok(count)
+    }
+
+    /// Registers a recording that was already loaded from `path`, giving it the
+    /// session id it is listed under.
+    pub fn add_loaded(&mut self, path: PathBuf, recording: &mut Recording) {
+        let header = RecordingHeader {
+            name: recording.name.clone(),
+            session_id: recording.session_id.clone(),
+            metadata: recording.metadata.clone(),
+        };
+        let key = canonical(&path);
+        if let Some(info) = self.entries.get_mut(&key) {
+            info.loaded = true;
+            recording.session_id = info.session_id.clone();
+            return;
+        }
+        let mut info = self.unique(RecordingInfo::from_header(header, &key));
+        info.loaded = true;
+        recording.session_id = info.session_id.clone();
+        self.entries.insert(key, info);
+        self.inputs.push(path);
+    }
+
+    /// Re-scans every input, picking up new files and dropping deleted ones.
+    pub fn refresh(&mut self) -> Result<(), anyhow::Error> {
+        let mut found = BTreeSet::new();
+        for input in self.inputs.iter() {
+            found.extend(discover(input)?.iter().map(|path| canonical(path)));
+        }
+        self.entries
+            .retain(|path, info| info.loaded || found.contains(path));
+        for path in found {
+            if !self.entries.contains_key(&path) {
+                if let Err(e) = self.index_file(&path) {
+                    warn!("Skipping {}: {}", path.display(), e);
+                }
+            }
+        }
+        info!("Catalog holds {} recordings", self.entries.len());
+        Ok(())
+    }
+
+    pub fn index_file(&mut self, path: &Path) -> Result<&RecordingInfo, anyhow::Error> {
+        let path = canonical(path);
+        if !self.entries.contains_key(&path) {
+            let header = Recording::load_header(&path)?;
+            let info = self.unique(RecordingInfo::from_header(header, &path));
+            self.entries.insert(path.clone(), info);
+        }
+        Ok(&self.entries[&path])
+    }
+
+    /// `info` with a session id no other entry uses.
+    fn unique(&self, mut info: RecordingInfo) -> RecordingInfo {
+        let taken = |id: &str| self.entries.values().any(|other| other.session_id == id);
+        if taken(&info.session_id) {
+            let id = (2..)
+                .map(|n| format!("{}~{}", info.session_id, n))
+                .find(|id| !taken(id))
+                .unwrap_or_default();
+            info.session_id = id;
+        }
+        info
+    }
+
+    pub fn path_of(&self, session_id: &str) -> Option<&PathBuf> {
+        self.entries
+            .iter()
+            .find(|(_, info)| info.session_id == session_id)
+            .map(|(path, _)| path)
+    }
+
+    pub fn set_loaded(&mut self, session_id: &str, loaded: bool) {
+        if let Some(info) = self
+            .entries
+            .values_mut()
+            .find(|info| info.session_id == session_id)
+        {
+            info.loaded = loaded;
+        }
+    }
+
+    pub fn listing(&self) -> Vec<RecordingInfo> {
+        self.entries.values().cloned().collect()
+    }
+}
+
+/// `path` made absolute with symlinks resolved, so one file has one entry.
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+pub fn is_recording_file(path: &Path) -> bool {
+    path.is_file()
+        && !annotation::is_sidecar(path)
+        && Compression::strip_extension(path)
+            .extension()
+            .is_some_and(|ext| RECORDING_EXTENSIONS.iter().any(|e| ext == *e))
+}
+
+/// Expands a file, directory or glob pattern into recording file paths.
+pub fn discover(input: &Path) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let pattern = input.to_string_lossy();
+    let mut paths: Vec<PathBuf> = if input.is_dir() {
+        std::fs::read_dir(input)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| is_recording_file(path))
+            .collect()
+    } else if pattern.contains(['*', '?', '[']) {
+        glob::glob(&pattern)?
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file() && !annotation::is_sidecar(path))
+            .collect()
+    } else if input.is_file() {
+        vec![input.to_path_buf()]
+    } else {
+        return Err(anyhow::anyhow!("{} does not exist", input.display()));
+    };
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_get_distinct_session_ids() {
+        let dir = std::env::temp_dir().join(format!("fundamentals-catalog-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let recording = Recording::new("drive".to_string(), "run-7".to_string());
+        for name in ["a.json", "b.json"] {
+            recording.save_to_file(&dir.join(name)).unwrap();
+        }
+
+        let mut catalog = RecordingCatalog::new();
+        assert_eq!(catalog.add_input(dir.clone()).unwrap(), 2);
+        let a = catalog.path_of("run-7").unwrap().clone();
+        let b = catalog.path_of("run-7~2").unwrap().clone();
+        assert_ne!(a, b);
+
+        // Re-indexing or refreshing keeps the ids stable
+        catalog.index_file(&dir.join("b.json")).unwrap();
+        catalog.refresh().unwrap();
+        assert_eq!(catalog.listing().len(), 2);
+        catalog.set_loaded("run-7~2", true);
+        let loaded: Vec<_> = catalog.listing().into_iter().filter(|i| i.loaded).collect();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].path.as_ref(), Some(&b));
+
+        let mut opened = Recording::load_from_file(&b).unwrap();
+        catalog.add_loaded(b.clone(), &mut opened);
+        assert_eq!(opened.session_id, "run-7~2");
+        assert_eq!(catalog.listing().len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,177 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use clap::Subcommand;
+use fundamentals_core::{
+    compare::Comparison,
+    recording::{MergePolicy, Recording},
+};
+use log::info;
+
+/// Offline operations on recording files
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Merge several recording files into one
+    Merge {
+        /// Recording files to merge, in order
+        #[clap(required = true, num_args = 1..)]
+        inputs: Vec<PathBuf>,
+
+        /// Path of the merged recording
+        #[clap(short, long)]
+        output: PathBuf,
+
+        /// What to do with vizs that share a name: combine, keep-existing, replace or rename
+        #[clap(long, default_value = "combine")]
+        policy: MergePolicy,
+
+        /// Append each recording after the end of the previous one instead of
+        /// overlaying them. Name clashes are still resolved with `--policy`
+        #[clap(long)]
+        concat: bool,
+
+        /// Time gap inserted between concatenated recordings
+        #[clap(long, default_value_t = 0.0)]
+        gap: f64,
+    },
+    /// Compare a run against a baseline and report per-viz error statistics
+    Compare {
+        /// Baseline recording file
+        baseline: PathBuf,
+
+        /// Recording file compared against the baseline
+        candidate: PathBuf,
+
+        /// Write a recording of the differences, to overlay in the viewer
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+
+        /// Fail if any series differs from the baseline by more than this, or if
+        /// the two recordings do not have the same vizs
+        #[clap(long)]
+        tolerance: Option<f64>,
+    },
+}
+
+pub fn run(command: Command) -> Result<(), anyhow::Error> {
+    match command {
+        Command::Merge {
+            inputs,
+            output,
+            policy,
+            concat,
+            gap,
+        } => merge(&inputs, &output, policy, concat.then_some(gap)),
+        Command::Compare {
+            baseline,
+            candidate,
+            output,
+            tolerance,
+        } => compare(&baseline, &candidate, output.as_ref(), tolerance),
+    }
+}
+
+fn merge(
+    inputs: &[PathBuf],
+    output: &PathBuf,
+    policy: MergePolicy,
+    concat_gap: Option<f64>,
+) -> Result<(), anyhow::Error> {
+    let mut merged: Option<Recording> = None;
+    for input in inputs {
+        let mut recording = Recording::load_from_file(input)
+            .with_context(|| format!("Failed to load {}", input.display()))?;
+        info!(
+            "Loaded {} ({} vizs) from {}",
+            recording.session_id,
+            recording.vizs.len(),
+            input.display()
+        );
+
+        let Some(merged) = merged.as_mut() else {
+            merged = Some(recording);
+            continue;
+        };
+        if let Some(gap) = concat_gap {
+            let end = merged.time_bounds().map(|(_, t1)| t1).unwrap_or_default();
+            let start = recording
+                .time_bounds()
+                .map(|(t0, _)| t0)
+                .unwrap_or_default();
+            recording.shift_time(end + gap - start);
+        }
+        merged
+            .merge(recording, policy)
+            .with_context(|| format!("Failed to merge {}", input.display()))?;
+    }
+
+    let mut merged = merged.context("No input recordings")?;
+    if inputs.len() > 1 {
+        // The merged file is a recording of its own, not the first input
+        merged.session_id = uuid::Uuid::new_v4().to_string();
+    }
+    merged.save_to_file(output)?;
+    println!(
+        "Wrote {} vizs from {} recordings to {}",
+        merged.vizs.len(),
+        inputs.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn compare(
+    baseline: &PathBuf,
+    candidate: &PathBuf,
+    output: Option<&PathBuf>,
+    tolerance: Option<f64>,
+) -> Result<(), anyhow::Error> {
+    let load = |path: &PathBuf| {
+        Recording::load_from_file(path)
+            .with_context(|| format!("Failed to load {}", path.display()))
+    };
+    let comparison = Comparison::new(&load(baseline)?, &load(candidate)?);
+
+    println!(
+        "{:<32} {:>8} {:>14} {:>14} {:>12}",
+        "viz", "samples", "max abs", "rms", "worst at"
+    );
+    for viz in comparison.vizs.iter() {
+        for series in viz.series.iter() {
+            println!(
+                "{:<32} {:>8} {:>14.6e} {:>14.6e} {:>12}",
+                viz.label(series),
+                series.samples,
+                series.max_abs,
+                series.rms,
+                series
+                    .worst_time
+                    .map(|t| format!("{:.3}", t))
+                    .unwrap_or_else(|| "-".to_string())
+            );
+        }
+    }
+    for name in comparison.only_in_baseline.iter() {
+        println!("{:<32} only in baseline", name);
+    }
+    for name in comparison.only_in_candidate.iter() {
+        println!("{:<32} only in candidate", name);
+    }
+
+    if let Some(output) = output {
+        comparison.diff_recording().save_to_file(output)?;
+        println!("Wrote differences to {}", output.display());
+    }
+
+    let failures = tolerance
+        .map(|tolerance| comparison.failures(tolerance))
+        .unwrap_or_default();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Recordings differ:\n  {}",
+            failures.join("\n  ")
+        ))
+    }
+}
@@ -1,10 +1,14 @@
 use clap::Parser;
-use fundamentals_core::recording::Recording;
-use log::info;
+use fundamentals_core::{format::Format, recording::Recording};
+use log::{info, warn};
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use warp::Filter;
 
+pub mod api;
+pub mod catalog;
+pub mod cli;
+pub mod requests;
 pub mod state;
 pub mod ws_handler;
 
@@ -20,7 +24,8 @@ pub struct WSBridgeArgs {
     #[clap(short, long, default_value_t = 3031)]
     pub port: u16,
 
-    /// Path to the input recording file
+    /// Recording file, directory of recordings or quoted glob pattern. Directories
+    /// and globs are listed to clients and loaded on demand
     #[clap(short, long)]
     pub input: PathBuf,
 
@@ -28,9 +33,15 @@ pub struct WSBridgeArgs {
     #[clap(long)]
     pub exit_after_serve: bool,
 
-    /// Open the browser after serving the first request
-    #[clap(long)]
+    /// Open the browser once the server is up. Pass `--open-browser false` on
+    /// headless hosts
+    #[clap(long, default_value_t = true, action = clap::ArgAction::Set)]
     pub open_browser: bool,
+
+    /// Downsample scalar plots to this many points in the initial update;
+    /// clients request finer ranges as they zoom in
+    #[clap(long)]
+    pub max_points: Option<usize>,
 }
 
 /// Starts the WebSocket bridge server with the given arguments
@@ -40,10 +51,20 @@ pub async fn start_server(args: WSBridgeArgs) {
 
     // Initialize state with command line arguments
     let mut state = state::WSBridgeState::new();
-    let recording = Recording::load_from_file(&args.input).unwrap();
-    state.add_recording(recording);
-
-    info!("Loaded recording from {}", args.input.display());
+    state.max_points = args.max_points;
+    if args.input.is_file() {
+        let format = Format::detect(&args.input).unwrap();
+        let recording = Recording::load_from_file_as(&args.input, format).unwrap();
+        state.add_recording_from_file(recording, args.input.clone());
+        info!("Loaded {} recording from {}", format, args.input.display());
+    } else {
+        let count = state.catalog.add_input(args.input.clone()).unwrap();
+        info!(
+            "Found {} recordings in {}, loading on demand",
+            count,
+            args.input.display()
+        );
+    }
 
     if args.exit_after_serve {
         info!(
@@ -57,9 +78,12 @@ pub async fn start_server(args: WSBridgeArgs) {
     let ws_route = warp::path("ws")
         // The `ws()` filter will prepare the Websocket handshake.
         .and(warp::ws())
-        .and(with_state(state))
+        .and(with_state(state.clone()))
         .and_then(ws_handler::ws_handler);
 
+    // JSON API for scripts and notebooks
+    let api_route = api::routes(state);
+
     // Static files route - serve files from the static directory
     let cargo_root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
     let static_dir = Path::new(&cargo_root)
@@ -77,9 +101,10 @@ pub async fn start_server(args: WSBridgeArgs) {
 
     // Combine all routes with proper precedence:
     // 1. WebSocket route first
-    // 2. Static files for exact matches
-    // 3. Fallback to index.html for everything else (SPA routing)
-    let routes = ws_route.or(static_route).or(spa_fallback);
+    // 2. JSON API under /api
+    // 3. Static files for exact matches
+    // 4. Fallback to index.html for everything else (SPA routing)
+    let routes = ws_route.or(api_route).or(static_route).or(spa_fallback);
 
     let socket_addr: SocketAddr = format!("0.0.0.0:{}", args.port).parse().unwrap();
     println!("Server started at http://0.0.0.0:{}", args.port);
@@ -87,7 +112,9 @@ pub async fn start_server(args: WSBridgeArgs) {
     // Auto open static path + /plot/0
     if args.open_browser {
         let url = format!("http://localhost:{}", args.port);
-        open::that(url).unwrap();
+        if let Err(e) = open::that(&url) {
+            warn!("Could not open a browser at {}: {}", url, e);
+        }
     }
 
     warp::serve(routes).run(socket_addr).await;
@@ -106,6 +133,7 @@ pub async fn start_server_with_recording(
         input: recording_path,
         exit_after_serve,
         open_browser,
+        max_points: None,
     };
 
     start_server(args).await;
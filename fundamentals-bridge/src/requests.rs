@@ -0,0 +1,643 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::PathBuf,
+};
+
+use fundamentals_core::{
+    annotation::{self, Annotation},
+    assets::AssetTable,
+    compare::Comparison,
+    downsample::{downsample, DownsampleAlgorithm},
+    recording::{LatestAt, Recording},
+    transforms::{Summary, Transform},
+    viz::Viz,
+    widgets::{
+        plot_scalar::PlotScalarData,
+        tensor::{DimSlice, Tensor},
+        LatestValue, Widget,
+    },
+};
+
+use crate::{state::WSBridgeState, ws_handler::WSMessage};
+use log::warn;
+
+pub const DOWNSAMPLED_FROM_KEY: &str = "downsampled_from";
+/// Viz metadata naming the recording a viz was sent from, so clients can address
+/// it in requests when several open recordings share viz names.
+pub const SESSION_ID_KEY: &str = "session_id";
+
+/// Requests a client can send over the WebSocket.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub enum WSRequest {
+    /// Every recording the bridge can serve, loaded or not.
+    ListRecordings,
+    /// Re-scans the input directories for new recordings, then lists them.
+    RefreshRecordings,
+    /// Loads a recording and sends its vizs.
+    OpenRecording {
+        session_id: String,
+    },
+    /// Drops a recording from memory.
+    CloseRecording {
+        session_id: String,
+    },
+    PlotRange(PlotRangeRequest),
+    /// Full-resolution slice of any viz between `t0` and `t1`.
+    VizRange {
+        #[serde(default)]
+        session_id: Option<String>,
+        viz: String,
+        t0: f64,
+        t1: f64,
+    },
+    /// Most recent value of every viz at time `t`, in one recording or in all of
+    /// them.
+    LatestAt {
+        #[serde(default)]
+        session_id: Option<String>,
+        t: f64,
+    },
+    /// Adds an annotation to a recording (the first one if no session id is given)
+    /// and saves it in the annotation sidecar of the recording file.
+    AddAnnotation {
+        #[serde(default)]
+        session_id: Option<String>,
+        annotation: Annotation,
+    },
+    /// Compares two recordings by session id. The reply carries the error
+    /// statistics, followed by a diff recording the viewer can overlay.
+    Compare {
+        baseline: String,
+        candidate: String,
+    },
+    /// A series derived from a scalar plot (derivative, FFT, ...), computed on the
+    /// samples between `t0` and `t1` or on the whole plot.
+    Derive {
+        #[serde(default)]
+        session_id: Option<String>,
+        viz: String,
+        transform: Transform,
+        #[serde(default)]
+        t0: Option<f64>,
+        #[serde(default)]
+        t1: Option<f64>,
+    },
+    /// Part of a tensor, selected by one `DimSlice` per dimension, from the frame
+    /// at time `t` (the latest frame if not given).
+    TensorSlice {
+        #[serde(default)]
+        session_id: Option<String>,
+        viz: String,
+        #[serde(default)]
+        t: Option<f64>,
+        slices: Vec<DimSlice>,
+    },
+}
+
+impl WSRequest {
+    /// Recordings the request loads from the catalog, see `state::open_recording`.
+    pub fn recordings_to_open(&self) -> Vec<&str> {
+        match self {
+            WSRequest::OpenRecording { session_id } => vec![session_id],
+            WSRequest::Compare {
+                baseline,
+                candidate,
+            } => vec![baseline, candidate],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// "Give me viz `viz` between `t0` and `t1` at roughly `points` points".
+///
+/// Requests that name a viz look it up in the recording `session_id`, or in the
+/// first open recording that has it if no session id is given.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct PlotRangeRequest {
+    #[serde(default)]
+    pub session_id: Option<String>,
+    pub viz: String,
+    pub t0: f64,
+    pub t1: f64,
+    pub points: usize,
+    #[serde(default)]
+    pub algorithm: DownsampleAlgorithm,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct PlotRangeResponse {
+    /// Recording the viz was found in.
+    pub session_id: String,
+    pub viz: String,
+    pub t0: f64,
+    pub t1: f64,
+    /// Number of samples in the range before downsampling.
+    pub total_points: usize,
+    pub data: PlotScalarData,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct DerivedSeries {
+    pub session_id: String,
+    pub viz: String,
+    pub transform: Transform,
+    pub data: PlotScalarData,
+    /// Statistics of the source samples the series was derived from.
+    pub summary: Summary,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct TensorSliceResponse {
+    pub session_id: String,
+    pub viz: String,
+    /// Time of the frame the slice was taken from.
+    pub time: f64,
+    pub slices: Vec<DimSlice>,
+    pub tensor: Tensor,
+}
+
+/// Messages that bring a client up to date with a recording: its info,
+/// annotations and every viz.
+pub fn recording_messages(state: &WSBridgeState, recording: &Recording) -> Vec<WSMessage> {
+    let mut messages = vec![WSMessage::RecordingInfo(state.info_of(recording))];
+    if !recording.annotations.is_empty() {
+        messages.push(WSMessage::Annotations {
+            session_id: recording.session_id.clone(),
+            annotations: recording.annotations.clone(),
+        });
+    }
+    messages.extend(recording.vizs.iter().map(|viz| {
+        let mut viz = match state.max_points {
+            Some(max_points) => preview(viz, max_points),
+            None => viz.clone(),
+        };
+        bin_histograms(&mut viz);
+        viz.set_metadata(SESSION_ID_KEY, recording.session_id.clone());
+        WSMessage::VizUpdate(viz)
+    }));
+    messages
+}
+
+/// Puts an `Assets` message before each message that refers to assets the client
+/// has not received yet, so every asset crosses the connection once. `sent` holds
+/// the ids already sent on this connection.
+pub fn with_assets(
+    state: &WSBridgeState,
+    messages: Vec<WSMessage>,
+    sent: &mut HashSet<String>,
+) -> Vec<WSMessage> {
+    let mut with_assets = Vec::with_capacity(messages.len());
+    for message in messages {
+        let referenced: BTreeSet<&str> = match &message {
+            WSMessage::VizUpdate(viz) | WSMessage::VizRange(viz) => viz.asset_ids(),
+            WSMessage::LatestAt(latest) => latest
+                .iter()
+                .flat_map(|l| l.values.iter())
+                .flat_map(|value| match value {
+                    LatestValue::ThreeDView(_, primative) => primative.asset_ids(),
+                    _ => Vec::new(),
+                })
+                .collect(),
+            _ => BTreeSet::new(),
+        };
+        let missing: Vec<&str> = referenced
+            .into_iter()
+            .filter(|id| !sent.contains(*id))
+            .collect();
+        if !missing.is_empty() {
+            let mut assets = AssetTable::new();
+            for recording in state.recordings.iter() {
+                assets.extend(recording.assets.subset(missing.iter().copied()));
+            }
+            sent.extend(assets.iter().map(|(id, _)| id.to_string()));
+            if !assets.is_empty() {
+                with_assets.push(WSMessage::Assets(assets));
+            }
+        }
+        with_assets.push(message);
+    }
+    with_assets
+}
+
+/// Handles one client request. Most requests produce a single reply; opening a
+/// recording streams all of its vizs.
+pub fn handle_request(state: &mut WSBridgeState, request: WSRequest) -> Vec<WSMessage> {
+    let reply = match request {
+        WSRequest::ListRecordings => WSMessage::Recordings(state.listing()),
+        WSRequest::RefreshRecordings => {
+            state.refresh_catalog();
+            WSMessage::Recordings(state.listing())
+        }
+        WSRequest::OpenRecording { session_id } => {
+            if let Err(e) = state.open_recording(&session_id) {
+                return vec![WSMessage::Error(e.to_string())];
+            }
+            return match state.get_recording(&session_id) {
+                Some(recording) => recording_messages(state, recording),
+                None => vec![WSMessage::Error(format!(
+                    "Recording {} has a different session id on disk",
+                    session_id
+                ))],
+            };
+        }
+        WSRequest::CloseRecording { session_id } => {
+            if state.close_recording(&session_id) {
+                WSMessage::RecordingClosed(session_id)
+            } else {
+                WSMessage::Error(format!("Recording {} is not open", session_id))
+            }
+        }
+        WSRequest::PlotRange(request) => match plot_range(state, &request) {
+            Some(response) => WSMessage::PlotRange(response),
+            None => WSMessage::Error(format!("No scalar plot named {}", request.viz)),
+        },
+        WSRequest::VizRange {
+            session_id,
+            viz,
+            t0,
+            t1,
+        } => match find_viz(state, session_id.as_deref(), &viz) {
+            Some((recording, found)) => {
+                let mut slice = found.slice(t0, t1);
+                bin_histograms(&mut slice);
+                slice.set_metadata(SESSION_ID_KEY, recording.session_id.clone());
+                WSMessage::VizRange(slice)
+            }
+            None => WSMessage::Error(format!("No viz named {}", viz)),
+        },
+        WSRequest::LatestAt { session_id, t } => {
+            WSMessage::LatestAt(latest_at(state, session_id.as_deref(), t))
+        }
+        WSRequest::AddAnnotation {
+            session_id,
+            annotation,
+        } => add_annotation(state, session_id.as_deref(), annotation),
+        WSRequest::Compare {
+            baseline,
+            candidate,
+        } => return compare(state, &baseline, &candidate),
+        WSRequest::Derive {
+            session_id,
+            viz,
+            transform,
+            t0,
+            t1,
+        } => match scalar_data(state, session_id.as_deref(), &viz) {
+            Some((session_id, data)) => {
+                let source = PlotScalarData::new(
+                    data.range(t0.unwrap_or(f64::NEG_INFINITY), t1.unwrap_or(f64::INFINITY))
+                        .to_vec(),
+                );
+                match transform.validate(&source) {
+                    Ok(()) => WSMessage::Derived(DerivedSeries {
+                        session_id: session_id.to_string(),
+                        viz,
+                        transform,
+                        data: transform.apply(&source),
+                        summary: Summary::of(&source.data_x),
+                    }),
+                    Err(e) => WSMessage::Error(format!("Cannot derive {}: {}", viz, e)),
+                }
+            }
+            None => WSMessage::Error(format!("No scalar plot named {}", viz)),
+        },
+        WSRequest::TensorSlice {
+            session_id,
+            viz,
+            t,
+            slices,
+        } => tensor_slice(state, session_id.as_deref(), viz, t, slices),
+    };
+    vec![reply]
+}
+
+fn add_annotation(
+    state: &mut WSBridgeState,
+    session_id: Option<&str>,
+    annotation: Annotation,
+) -> WSMessage {
+    let Some(recording) = state.get_recording_mut(session_id) else {
+        return WSMessage::Error(format!("No recording {}", session_id.unwrap_or_default()));
+    };
+    recording.add_annotation(annotation.clone());
+    let session_id = recording.session_id.clone();
+
+    if let Some(path) = state.catalog.path_of(&session_id) {
+        save_annotation(path.clone(), annotation.clone());
+    }
+    WSMessage::AnnotationAdded {
+        session_id,
+        annotation,
+    }
+}
+
+/// Appends `annotation` to the sidecar of the recording at `path`, off the async
+/// runtime so clients are not held up by the disk.
+fn save_annotation(path: PathBuf, annotation: Annotation) {
+    let save = move || {
+        if let Err(e) = annotation::append_to_sidecar(&path, &[annotation]) {
+            warn!("Annotation for {} was not saved: {}", path.display(), e);
+        }
+    };
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => drop(handle.spawn_blocking(save)),
+        Err(_) => save(),
+    }
+}
+
+fn compare(state: &mut WSBridgeState, baseline: &str, candidate: &str) -> Vec<WSMessage> {
+    for session_id in [baseline, candidate] {
+        if let Err(e) = state.open_recording(session_id) {
+            return vec![WSMessage::Error(e.to_string())];
+        }
+    }
+    let (Some(baseline), Some(candidate)) = (
+        state.get_recording(baseline),
+        state.get_recording(candidate),
+    ) else {
+        return vec![WSMessage::Error("Recordings are not open".to_string())];
+    };
+
+    let comparison = Comparison::new(baseline, candidate);
+    let diff = comparison.diff_recording();
+    // Comparing the same pair again replaces the previous diff
+    state.close_recording(&diff.session_id);
+    let mut messages = vec![WSMessage::Comparison(comparison)];
+    messages.extend(recording_messages(state, &diff));
+    state.add_recording(diff);
+    messages
+}
+
+fn tensor_slice(
+    state: &WSBridgeState,
+    session_id: Option<&str>,
+    viz: String,
+    t: Option<f64>,
+    slices: Vec<DimSlice>,
+) -> WSMessage {
+    let Some((recording, data)) =
+        find_viz(state, session_id, &viz).and_then(|(recording, found)| {
+            found.widgets.iter().find_map(|widget| match widget {
+                Widget::Tensor(data) => Some((recording, data)),
+                _ => None,
+            })
+        })
+    else {
+        return WSMessage::Error(format!("No tensor named {}", viz));
+    };
+    let frame = match t {
+        Some(t) => data.latest_at(t),
+        None => data.frames.last(),
+    };
+    let Some((time, tensor)) = frame else {
+        return WSMessage::Error(format!("Tensor {} has no frame at that time", viz));
+    };
+    match tensor.slice(&slices) {
+        Ok(tensor) => WSMessage::TensorSlice(TensorSliceResponse {
+            session_id: recording.session_id.clone(),
+            viz,
+            time: *time,
+            slices,
+            tensor,
+        }),
+        Err(e) => WSMessage::Error(format!("Cannot slice {}: {}", viz, e)),
+    }
+}
+
+/// The named viz in recording `session_id`, or in the first open recording that
+/// has one if no session id is given, along with its recording.
+fn find_viz<'a>(
+    state: &'a WSBridgeState,
+    session_id: Option<&str>,
+    name: &str,
+) -> Option<(&'a Recording, &'a Viz)> {
+    match session_id {
+        Some(id) => {
+            let recording = state.get_recording(id)?;
+            Some((recording, recording.get_viz(name)?))
+        }
+        None => state
+            .recordings
+            .iter()
+            .find_map(|recording| Some((recording, recording.get_viz(name)?))),
+    }
+}
+
+pub fn latest_at(state: &WSBridgeState, session_id: Option<&str>, t: f64) -> Vec<LatestAt> {
+    state
+        .recordings
+        .iter()
+        .filter(|recording| session_id.is_none_or(|id| recording.session_id == id))
+        .flat_map(|recording| recording.latest_at(t))
+        .collect()
+}
+
+/// The first scalar plot of the named viz, with the session id of its recording.
+fn scalar_data<'a>(
+    state: &'a WSBridgeState,
+    session_id: Option<&str>,
+    viz: &str,
+) -> Option<(&'a str, &'a PlotScalarData)> {
+    let (recording, found) = find_viz(state, session_id, viz)?;
+    found.widgets.iter().find_map(|widget| match widget {
+        Widget::PlotScalar(data) => Some((recording.session_id.as_str(), data)),
+        _ => None,
+    })
+}
+
+pub fn plot_range(state: &WSBridgeState, request: &PlotRangeRequest) -> Option<PlotRangeResponse> {
+    let (session_id, data) = scalar_data(state, request.session_id.as_deref(), &request.viz)?;
+
+    let range = data.range(request.t0, request.t1);
+    Some(PlotRangeResponse {
+        session_id: session_id.to_string(),
+        viz: request.viz.clone(),
+        t0: request.t0,
+        t1: request.t1,
+        total_points: range.len(),
+        data: PlotScalarData::new(downsample(range, request.points, request.algorithm))
+            .with_precision(data.precision),
+    })
+}
+
+/// Copy of `viz` with every scalar plot reduced to at most `max_points` samples,
+/// so the first paint of a long recording stays cheap. Clients re-query with
+/// `PlotRange` for detail.
+pub fn preview(viz: &Viz, max_points: usize) -> Viz {
+    downsample_viz(viz, max_points, DownsampleAlgorithm::Lttb)
+}
+
+/// Copy of `viz` with every scalar plot longer than `points` downsampled and every
+/// tensor strided down to about `points` elements. The original length is recorded
+/// in the viz metadata; clients fetch detail with `PlotRange` or `TensorSlice`.
+pub fn downsample_viz(viz: &Viz, points: usize, algorithm: DownsampleAlgorithm) -> Viz {
+    let mut reduced = viz.clone();
+    for widget in reduced.widgets.iter_mut() {
+        match widget {
+            Widget::PlotScalar(data) if data.data_x.len() > points => {
+                reduced
+                    .metadata
+                    .insert(DOWNSAMPLED_FROM_KEY.to_string(), data.data_x.len().into());
+                data.data_x = downsample(&data.data_x, points, algorithm);
+            }
+            Widget::Tensor(data) => {
+                for (_, tensor) in data.frames.iter_mut() {
+                    if tensor.len() > points {
+                        reduced.metadata.insert(
+                            DOWNSAMPLED_FROM_KEY.to_string(),
+                            tensor.shape.clone().into(),
+                        );
+                        *tensor = tensor.downsample(points);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    reduced
+}
+
+/// Bins the raw samples of every histogram so clients only receive counts.
+pub fn bin_histograms(viz: &mut Viz) {
+    for widget in viz.widgets.iter_mut() {
+        if let Widget::Histogram(data) = widget {
+            if !data.is_binned() {
+                if let Err(e) = data.bins.validate() {
+                    warn!("Histogram {} has invalid bins: {}", viz.name, e);
+                }
+                *data = data.binned();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fundamentals_core::widgets::tensor::TensorData;
+
+    /// Two runs with the same vizs, every value offset by `offset`.
+    fn run(session_id: &str, offset: f64) -> Recording {
+        let mut recording = Recording::new("drive".to_string(), session_id.to_string());
+        let samples = (0..10).map(|i| (i as f64, offset + i as f64)).collect();
+        recording.add_viz(
+            Viz::new("speed".to_string())
+                .with_widget(Widget::PlotScalar(PlotScalarData::new(samples))),
+        );
+        let frame = Tensor::from_vec(vec![2], vec![offset, offset + 1.0]).unwrap();
+        recording.add_viz(
+            Viz::new("image".to_string()).with_widget(Widget::Tensor(TensorData {
+                frames: vec![(0.0, frame)],
+            })),
+        );
+        recording
+    }
+
+    fn two_runs() -> WSBridgeState {
+        let mut state = WSBridgeState::new();
+        state.add_recording(run("a", 0.0));
+        state.add_recording(run("b", 100.0));
+        state
+    }
+
+    fn reply(state: &mut WSBridgeState, request: WSRequest) -> WSMessage {
+        let mut replies = handle_request(state, request);
+        assert_eq!(replies.len(), 1);
+        replies.remove(0)
+    }
+
+    #[test]
+    fn requests_address_vizs_by_recording() {
+        let mut state = two_runs();
+        let session = |id: &str| Some(id.to_string());
+
+        let range = |session_id| PlotRangeRequest {
+            session_id,
+            viz: "speed".to_string(),
+            t0: 2.0,
+            t1: 4.0,
+            points: 100,
+            algorithm: DownsampleAlgorithm::Lttb,
+        };
+        let response = plot_range(&state, &range(session("b"))).unwrap();
+        assert_eq!(response.session_id, "b");
+        assert_eq!(
+            response.data.data_x,
+            vec![(2.0, 102.0), (3.0, 103.0), (4.0, 104.0)]
+        );
+        // Without a session id the first recording that has the viz answers
+        let response = plot_range(&state, &range(None)).unwrap();
+        assert_eq!(response.session_id, "a");
+        assert_eq!(response.data.data_x[0], (2.0, 2.0));
+        assert!(plot_range(&state, &range(session("c"))).is_none());
+
+        let request = WSRequest::VizRange {
+            session_id: session("b"),
+            viz: "speed".to_string(),
+            t0: 0.0,
+            t1: 0.0,
+        };
+        let WSMessage::VizRange(slice) = reply(&mut state, request) else {
+            panic!("expected a viz range");
+        };
+        assert_eq!(slice.get_metadata(SESSION_ID_KEY), Some(&"b".into()));
+        assert!(matches!(
+            &slice.widgets[0],
+            Widget::PlotScalar(data) if data.data_x == vec![(0.0, 100.0)]
+        ));
+
+        let request = WSRequest::Derive {
+            session_id: session("b"),
+            viz: "speed".to_string(),
+            transform: Transform::Derivative,
+            t0: None,
+            t1: None,
+        };
+        let WSMessage::Derived(derived) = reply(&mut state, request) else {
+            panic!("expected a derived series");
+        };
+        assert_eq!(derived.session_id, "b");
+        assert_eq!(derived.summary.min, 100.0);
+
+        let request = WSRequest::TensorSlice {
+            session_id: session("b"),
+            viz: "image".to_string(),
+            t: None,
+            slices: vec![DimSlice::Index(1)],
+        };
+        let WSMessage::TensorSlice(slice) = reply(&mut state, request) else {
+            panic!("expected a tensor slice");
+        };
+        assert_eq!(slice.session_id, "b");
+        assert_eq!(slice.tensor.get(&[]), Some(101.0));
+
+        let request = WSRequest::LatestAt {
+            session_id: session("b"),
+            t: 5.0,
+        };
+        let WSMessage::LatestAt(latest) = reply(&mut state, request) else {
+            panic!("expected latest values");
+        };
+        assert_eq!(latest.len(), 2);
+        assert!(matches!(
+            latest[0].values[0],
+            LatestValue::PlotScalar(5.0, value) if value == 105.0
+        ));
+        assert_eq!(latest_at(&state, None, 5.0).len(), 4);
+    }
+
+    #[test]
+    fn viz_updates_name_their_recording() {
+        let state = two_runs();
+        for recording in state.recordings.iter() {
+            for message in recording_messages(&state, recording) {
+                if let WSMessage::VizUpdate(viz) = message {
+                    assert_eq!(
+                        viz.get_metadata(SESSION_ID_KEY),
+                        Some(&recording.session_id.clone().into())
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,10 +1,33 @@
-use std::collections::BTreeMap;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
 
-use fundamentals_core::recording::Recording;
+use fundamentals_core::{annotation, recording::Recording};
+use log::{info, warn};
+
+use crate::catalog::{RecordingCatalog, RecordingInfo};
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct WSBridgeState {
+    /// Recordings currently open.
     pub recordings: Vec<Recording>,
+    /// Scalar plots longer than this are downsampled in the initial `VizUpdate`.
+    pub max_points: Option<usize>,
+    /// Every recording file the bridge can open.
+    pub catalog: RecordingCatalog,
+    /// The last recording read for an API request without opening it.
+    #[serde(skip)]
+    pub last_read: Option<ReadCache>,
+}
+
+/// A recording read from `path` as it was at `modified`.
+#[derive(Debug, Clone)]
+pub struct ReadCache {
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+    pub recording: Arc<Recording>,
 }
 
 pub type StateHandle = std::sync::Arc<tokio::sync::Mutex<WSBridgeState>>;
@@ -13,6 +36,9 @@ impl Default for WSBridgeState {
     fn default() -> Self {
         Self {
             recordings: Vec::new(),
+            max_points: None,
+            catalog: RecordingCatalog::new(),
+            last_read: None,
         }
     }
 }
@@ -30,7 +56,177 @@ impl WSBridgeState {
         self.recordings.push(recording);
     }
 
+    pub fn add_recording_from_file(&mut self, mut recording: Recording, path: PathBuf) {
+        add_sidecar_annotations(&mut recording, &path);
+        self.catalog.add_loaded(path, &mut recording);
+        self.add_recording(recording);
+    }
+
+    pub fn is_open(&self, session_id: &str) -> bool {
+        self.recordings.iter().any(|r| r.session_id == session_id)
+    }
+
+    /// Loads a recording from the catalog. Does nothing if it is already open.
+    /// From async code, prefer `open_recording`, which reads without the lock.
+    pub fn open_recording(&mut self, session_id: &str) -> Result<(), anyhow::Error> {
+        if self.is_open(session_id) {
+            return Ok(());
+        }
+        let path = self.catalog_path(session_id)?;
+        let recording = load_catalog_file(&path, session_id)?;
+        self.insert_opened(recording);
+        Ok(())
+    }
+
+    pub fn catalog_path(&self, session_id: &str) -> Result<PathBuf, anyhow::Error> {
+        self.catalog
+            .path_of(session_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Unknown recording {}", session_id))
+    }
+
+    /// Adds a recording read with `load_catalog_file`, unless another request
+    /// opened it in the meantime.
+    pub fn insert_opened(&mut self, recording: Recording) {
+        if self.is_open(&recording.session_id) {
+            return;
+        }
+        self.catalog.set_loaded(&recording.session_id, true);
+        // Served from memory from now on, so the copy read for the API can go
+        let path = self.catalog.path_of(&recording.session_id);
+        if self
+            .last_read
+            .as_ref()
+            .is_some_and(|c| Some(&c.path) == path)
+        {
+            self.last_read = None;
+        }
+        self.add_recording(recording);
+    }
+
+    /// Drops a recording from memory; it stays in the catalog and can be reopened.
+    pub fn close_recording(&mut self, session_id: &str) -> bool {
+        let open = self.recordings.len();
+        self.recordings.retain(|r| r.session_id != session_id);
+        self.catalog.set_loaded(session_id, false);
+        self.recordings.len() != open
+    }
+
+    /// Catalog entries plus any open recording that did not come from a file.
+    pub fn listing(&self) -> Vec<RecordingInfo> {
+        let mut listing = self.catalog.listing();
+        for recording in self.recordings.iter() {
+            if self.catalog.path_of(&recording.session_id).is_none() {
+                listing.push(recording.into());
+            }
+        }
+        listing
+    }
+
+    pub fn refresh_catalog(&mut self) {
+        if let Err(e) = self.catalog.refresh() {
+            warn!("Failed to refresh recordings: {}", e);
+        }
+    }
+
     pub fn get_recordings(&self) -> &Vec<Recording> {
         &self.recordings
     }
+
+    pub fn get_recording(&self, session_id: &str) -> Option<&Recording> {
+        self.recordings.iter().find(|r| r.session_id == session_id)
+    }
+
+    /// Catalog entry for an open recording, or a bare entry if it has no file.
+    pub fn info_of(&self, recording: &Recording) -> RecordingInfo {
+        self.catalog
+            .path_of(&recording.session_id)
+            .and_then(|path| self.catalog.entries.get(path))
+            .cloned()
+            .unwrap_or_else(|| recording.into())
+    }
+
+    /// The recording with `session_id`, or the first one if no id is given.
+    pub fn get_recording_mut(&mut self, session_id: Option<&str>) -> Option<&mut Recording> {
+        match session_id {
+            Some(id) => self.recordings.iter_mut().find(|r| r.session_id == id),
+            None => self.recordings.first_mut(),
+        }
+    }
+}
+
+/// Reads a catalog file as the recording listed under `session_id`, along with
+/// the annotations viewers stored next to it.
+pub fn load_catalog_file(path: &Path, session_id: &str) -> Result<Recording, anyhow::Error> {
+    let mut recording = Recording::load_from_file(&path.to_path_buf())?;
+    add_sidecar_annotations(&mut recording, path);
+    recording.session_id = session_id.to_string();
+    info!("Opened {} from {}", session_id, path.display());
+    Ok(recording)
+}
+
+/// `load_catalog_file` on the blocking thread pool.
+pub async fn read_catalog_file(
+    path: PathBuf,
+    session_id: &str,
+) -> Result<Recording, anyhow::Error> {
+    let session_id = session_id.to_string();
+    tokio::task::spawn_blocking(move || load_catalog_file(&path, &session_id)).await?
+}
+
+/// Reads a catalog recording without opening it. The last one read is kept until
+/// its file changes, so a client browsing one recording through the API does not
+/// re-read the file on every request, while at most one unopened recording stays
+/// in memory.
+pub async fn read_unopened(
+    state: &StateHandle,
+    session_id: &str,
+    path: PathBuf,
+) -> Result<Arc<Recording>, anyhow::Error> {
+    let modified = modified_time(&path);
+    if let Some(cached) = state.lock().await.last_read.as_ref() {
+        if cached.path == path && cached.modified == modified && modified.is_some() {
+            return Ok(cached.recording.clone());
+        }
+    }
+    let recording = Arc::new(read_catalog_file(path.clone(), session_id).await?);
+    state.lock().await.last_read = Some(ReadCache {
+        path,
+        modified,
+        recording: recording.clone(),
+    });
+    Ok(recording)
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// `WSBridgeState::open_recording` that reads the file without holding the state
+/// lock, so other clients are not held up.
+pub async fn open_recording(state: &StateHandle, session_id: &str) -> Result<(), anyhow::Error> {
+    let path = {
+        let state = state.lock().await;
+        if state.is_open(session_id) {
+            return Ok(());
+        }
+        state.catalog_path(session_id)?
+    };
+    let recording = read_catalog_file(path, session_id).await?;
+    state.lock().await.insert_opened(recording);
+    Ok(())
+}
+
+/// Adds the annotations viewers stored next to the recording file.
+fn add_sidecar_annotations(recording: &mut Recording, path: &Path) {
+    match annotation::load_sidecar(path) {
+        Ok(annotations) => {
+            for annotation in annotations {
+                if !recording.annotations.contains(&annotation) {
+                    recording.add_annotation(annotation);
+                }
+            }
+        }
+        Err(e) => warn!("Ignoring annotations stored for {}: {}", path.display(), e),
+    }
 }
@@ -1,7 +1,14 @@
-use crate::state::StateHandle;
+use crate::catalog::RecordingInfo;
+use crate::requests::{self, DerivedSeries, PlotRangeResponse, TensorSliceResponse, WSRequest};
+use crate::state::{self, StateHandle};
+use fundamentals_core::annotation::Annotation;
+use fundamentals_core::assets::AssetTable;
+use fundamentals_core::compare::Comparison;
+use fundamentals_core::recording::LatestAt;
 use fundamentals_core::viz::Viz;
 use log::{debug, error, info};
 use std::collections::BTreeMap;
+use std::collections::HashSet;
 use warp::ws::{Message, WebSocket};
 use warp::{Rejection, Reply};
 type Result<T> = std::result::Result<T, Rejection>;
@@ -13,7 +20,28 @@ use std::sync::Arc;
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
 pub enum WSMessage {
+    RecordingInfo(RecordingInfo),
+    Recordings(Vec<RecordingInfo>),
+    RecordingClosed(String),
+    /// Assets referenced by the messages that follow, keyed by id. Each asset is
+    /// sent once per connection.
+    Assets(AssetTable),
     VizUpdate(Viz),
+    PlotRange(PlotRangeResponse),
+    VizRange(Viz),
+    LatestAt(Vec<LatestAt>),
+    Annotations {
+        session_id: String,
+        annotations: Vec<Annotation>,
+    },
+    AnnotationAdded {
+        session_id: String,
+        annotation: Annotation,
+    },
+    Comparison(Comparison),
+    Derived(DerivedSeries),
+    TensorSlice(TensorSliceResponse),
+    Error(String),
 }
 
 // Flag to track if the application should exit after serving
@@ -40,23 +68,85 @@ pub async fn ws_handler(ws: warp::ws::Ws, state: StateHandle) -> Result<impl Rep
     }))
 }
 
+/// Loads the recordings `request` needs before it is handled under the lock.
+async fn open_recordings(state: &StateHandle, request: &WSRequest) -> anyhow::Result<()> {
+    for session_id in request.recordings_to_open() {
+        state::open_recording(state, session_id).await?;
+    }
+    Ok(())
+}
+
 pub async fn ws_connect(ws: &mut WebSocket, state: StateHandle) {
     info!("New WebSocket connection");
 
-    let (mut client_ws_sender, _client_ws_rcv) = ws.split();
+    let (mut client_ws_sender, mut client_ws_rcv) = ws.split();
+    let mut sent_assets = HashSet::new();
+
+    let messages: Vec<WSMessage> = {
+        let state = state.lock().await;
+        let mut messages = Vec::new();
+        if state.recordings.is_empty() {
+            // Nothing is open yet (directory mode): let the client pick
+            messages.push(WSMessage::Recordings(state.listing()));
+        }
+        for recording in state.recordings.iter() {
+            messages.extend(requests::recording_messages(&state, recording));
+        }
+        requests::with_assets(&state, messages, &mut sent_assets)
+    };
+
+    for message in messages {
+        let msg_json = serde_json::to_string(&message).unwrap();
+        client_ws_sender
+            .send(Message::text(msg_json))
+            .await
+            .unwrap();
+        info!("Sent initial message");
+        // Wait 50ms
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+
+    // The connection is closed right after the initial burst in exit-after-serve mode
+    if EXIT_AFTER_SERVE.load(Ordering::SeqCst) {
+        return;
+    }
+
+    while let Some(msg) = client_ws_rcv.next().await {
+        let msg = match msg {
+            Ok(msg) => msg,
+            Err(e) => {
+                error!("WebSocket error: {}", e);
+                break;
+            }
+        };
+        if msg.is_close() {
+            break;
+        }
+        let Ok(text) = msg.to_str() else {
+            continue;
+        };
 
-    let recordings = state.lock().await;
+        let responses = match serde_json::from_str::<WSRequest>(text) {
+            Ok(request) => {
+                debug!("Received request {:?}", request);
+                match open_recordings(&state, &request).await {
+                    Ok(()) => {
+                        let mut state = state.lock().await;
+                        let responses = requests::handle_request(&mut state, request);
+                        requests::with_assets(&state, responses, &mut sent_assets)
+                    }
+                    Err(e) => vec![WSMessage::Error(e.to_string())],
+                }
+            }
+            Err(e) => vec![WSMessage::Error(format!("Invalid request: {}", e))],
+        };
 
-    for recording in recordings.recordings.iter() {
-        for viz in recording.vizs.iter() {
-            let msg_json = serde_json::to_string(&WSMessage::VizUpdate(viz.clone())).unwrap();
-            client_ws_sender
-                .send(Message::text(msg_json))
-                .await
-                .unwrap();
-            info!("Sent VizUpdate message");
-            // Wait 50ms
-            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        for response in responses {
+            let msg_json = serde_json::to_string(&response).unwrap();
+            if let Err(e) = client_ws_sender.send(Message::text(msg_json)).await {
+                error!("Failed to send response: {}", e);
+                return;
+            }
         }
     }
 }
@@ -0,0 +1,159 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Suffix of the file next to a recording that holds annotations added after it
+/// was written, e.g. `run.mcap.annotations.json`. Recording files themselves are
+/// never rewritten to add annotations.
+pub const SIDECAR_SUFFIX: &str = ".annotations.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationTime {
+    /// A single moment, e.g. "collision".
+    Instant(f64),
+    /// A span of time, e.g. "test phase 2".
+    Interval(f64, f64),
+}
+
+/// A labelled event marker shown on every viz it applies to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub label: String,
+    pub time: AnnotationTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Names of the vizs this annotation is scoped to. Empty means every viz.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vizs: Vec<String>,
+}
+
+impl Annotation {
+    pub fn instant(time: f64, label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            time: AnnotationTime::Instant(time),
+            color: None,
+            vizs: Vec::new(),
+        }
+    }
+
+    pub fn interval(start: f64, end: f64, label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            time: AnnotationTime::Interval(start.min(end), start.max(end)),
+            color: None,
+            vizs: Vec::new(),
+        }
+    }
+
+    pub fn with_color(mut self, color: &str) -> Self {
+        self.color = Some(color.to_string());
+        self
+    }
+
+    pub fn with_viz(mut self, viz: &str) -> Self {
+        self.vizs.push(viz.to_string());
+        self
+    }
+
+    pub fn start(&self) -> f64 {
+        match self.time {
+            AnnotationTime::Instant(t) | AnnotationTime::Interval(t, _) => t,
+        }
+    }
+
+    pub fn end(&self) -> f64 {
+        match self.time {
+            AnnotationTime::Instant(t) | AnnotationTime::Interval(_, t) => t,
+        }
+    }
+
+    pub fn applies_to(&self, viz: &str) -> bool {
+        self.vizs.is_empty() || self.vizs.iter().any(|v| v == viz)
+    }
+
+    pub fn overlaps(&self, t0: f64, t1: f64) -> bool {
+        self.start() <= t1 && self.end() >= t0
+    }
+
+    pub fn shift_time(&mut self, offset: f64) {
+        self.time = match self.time {
+            AnnotationTime::Instant(t) => AnnotationTime::Instant(t + offset),
+            AnnotationTime::Interval(t0, t1) => AnnotationTime::Interval(t0 + offset, t1 + offset),
+        };
+    }
+}
+
+pub fn sidecar_path(recording: &Path) -> PathBuf {
+    let mut path = recording.as_os_str().to_owned();
+    path.push(SIDECAR_SUFFIX);
+    PathBuf::from(path)
+}
+
+pub fn is_sidecar(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(SIDECAR_SUFFIX)
+}
+
+/// Annotations stored next to `recording`, or none if it has no sidecar.
+pub fn load_sidecar(recording: &Path) -> Result<Vec<Annotation>, anyhow::Error> {
+    let path = sidecar_path(recording);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+}
+
+/// Adds `annotations` to the sidecar of `recording`. The sidecar is replaced
+/// atomically, so a crash leaves either the old or the new list.
+pub fn append_to_sidecar(
+    recording: &Path,
+    annotations: &[Annotation],
+) -> Result<(), anyhow::Error> {
+    // Read-modify-write, one writer at a time
+    static LOCK: Mutex<()> = Mutex::new(());
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let mut stored = load_sidecar(recording)?;
+    for annotation in annotations {
+        if !stored.contains(annotation) {
+            stored.push(annotation.clone());
+        }
+    }
+    let path = sidecar_path(recording);
+    let mut temp = path.clone().into_os_string();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    let mut writer = BufWriter::new(File::create(&temp)?);
+    serde_json::to_writer(&mut writer, &stored)?;
+    writer.into_inner()?.sync_all()?;
+    std::fs::rename(&temp, &path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sidecar_appends_without_duplicates() {
+        let dir = std::env::temp_dir().join(format!("fundamentals-sidecar-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let recording = dir.join("run.mcap");
+        assert!(load_sidecar(&recording).unwrap().is_empty());
+
+        let stall = Annotation::instant(1.0, "stall");
+        let phase = Annotation::interval(3.0, 2.0, "phase").with_viz("speed");
+        append_to_sidecar(&recording, std::slice::from_ref(&stall)).unwrap();
+        append_to_sidecar(&recording, &[stall.clone(), phase.clone()]).unwrap();
+        assert_eq!(load_sidecar(&recording).unwrap(), vec![stall, phase]);
+        assert!(is_sidecar(&sidecar_path(&recording)));
+        assert!(!recording.exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+
+use crate::widgets::mesh::Mesh;
+
+/// Geometry or image data that primitives refer to by id instead of embedding it,
+/// so data that recurs every frame is stored and sent once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Asset {
+    Mesh(Mesh),
+    /// An encoded image (PNG, JPEG, ...), decoded by the viewer.
+    Texture {
+        mime_type: String,
+        /// Base64 in every format, so JSON files do not spell images out as
+        /// arrays of numbers.
+        #[serde(with = "base64_bytes")]
+        data: Vec<u8>,
+    },
+    /// A point cloud that does not change over time, e.g. a prior map.
+    PointCloud(Vec<(f64, f64, f64)>),
+}
+
+impl Asset {
+    /// Id derived from the asset's content: equal assets get equal ids, whichever
+    /// recording or process logged them. The first 128 bits of a SHA-256, in hex,
+    /// so different assets getting the same id is not a practical concern.
+    pub fn content_id(&self) -> String {
+        let bytes = serde_json::to_vec(self).expect("assets always serialize");
+        Sha256::digest(&bytes)[..16]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+/// Bytes as a base64 string. Arrays of numbers, as older recordings store them,
+/// are still read.
+mod base64_bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Encoded {
+        Base64(String),
+        Raw(Vec<u8>),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        match Encoded::deserialize(deserializer)? {
+            Encoded::Base64(text) => base64::engine::general_purpose::STANDARD
+                .decode(text)
+                .map_err(serde::de::Error::custom),
+            Encoded::Raw(bytes) => Ok(bytes),
+        }
+    }
+}
+
+/// Content-addressed assets of a recording.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AssetTable {
+    assets: BTreeMap<String, Asset>,
+}
+
+impl AssetTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `asset` unless an identical one is already present. Returns its id.
+    pub fn insert(&mut self, asset: Asset) -> String {
+        let id = asset.content_id();
+        self.assets.entry(id.clone()).or_insert(asset);
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Asset> {
+        self.assets.get(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.assets.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Asset)> {
+        self.assets.iter().map(|(id, asset)| (id.as_str(), asset))
+    }
+
+    /// Adds every asset of `other`. Ids are content hashes, so an id found in both
+    /// tables stands for the same asset and the existing entry is kept.
+    pub fn extend(&mut self, other: AssetTable) {
+        for (id, asset) in other.assets {
+            self.assets.entry(id).or_insert(asset);
+        }
+    }
+
+    /// The assets with the given ids; unknown ids are skipped.
+    pub fn subset<'a>(&self, ids: impl IntoIterator<Item = &'a str>) -> AssetTable {
+        Self {
+            assets: ids
+                .into_iter()
+                .filter_map(|id| Some((id.to_string(), self.assets.get(id)?.clone())))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texture(data: Vec<u8>) -> Asset {
+        Asset::Texture {
+            mime_type: "image/png".to_string(),
+            data,
+        }
+    }
+
+    #[test]
+    fn ids_follow_content() {
+        let cloud = Asset::PointCloud(vec![(1.0, 2.0, 3.0)]);
+        let id = cloud.content_id();
+        assert_eq!(id.len(), 32);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(id, Asset::PointCloud(vec![(1.0, 2.0, 3.0)]).content_id());
+        assert_ne!(id, Asset::PointCloud(vec![(1.0, 2.0, 3.5)]).content_id());
+
+        let mut table = AssetTable::new();
+        assert_eq!(table.insert(cloud.clone()), id);
+        assert_eq!(table.insert(cloud), id);
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn texture_data_is_base64() {
+        let asset = texture(vec![0, 1, 2, 255]);
+        let json = serde_json::to_value(&asset).unwrap();
+        assert_eq!(json["texture"]["data"], "AAEC/w==");
+        assert_eq!(serde_json::from_value::<Asset>(json).unwrap(), asset);
+
+        let old = r#"{"texture": {"mime_type": "image/png", "data": [0, 1, 2, 255]}}"#;
+        assert_eq!(serde_json::from_str::<Asset>(old).unwrap(), asset);
+
+        let mut packed = Vec::new();
+        ciborium::into_writer(&asset, &mut packed).unwrap();
+        assert_eq!(
+            ciborium::from_reader::<Asset, _>(packed.as_slice()).unwrap(),
+            asset
+        );
+        let packed = rmp_serde::to_vec(&asset).unwrap();
+        assert_eq!(rmp_serde::from_slice::<Asset>(&packed).unwrap(), asset);
+    }
+
+    #[test]
+    fn extend_and_subset_keep_ids() {
+        let mut first = AssetTable::new();
+        let a = first.insert(texture(vec![1]));
+        let mut second = AssetTable::new();
+        let b = second.insert(texture(vec![2]));
+        second.insert(texture(vec![1]));
+        first.extend(second);
+        assert_eq!(first.len(), 2);
+        let subset = first.subset([b.as_str(), "unknown"]);
+        assert_eq!(subset.len(), 1);
+        assert!(subset.contains(&b) && !subset.contains(&a));
+    }
+}
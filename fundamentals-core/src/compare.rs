@@ -0,0 +1,376 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotation::Annotation,
+    recording::Recording,
+    viz::Viz,
+    widgets::{plot_scalar::PlotScalarData, Widget},
+};
+
+/// Error statistics of one scalar widget against its baseline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeriesDiff {
+    /// Index of the widget within its viz.
+    pub widget: usize,
+    /// Candidate samples that fall within the baseline's time range.
+    pub samples: usize,
+    pub baseline_samples: usize,
+    pub candidate_samples: usize,
+    /// First and last sample time of each series, `None` if it is empty.
+    pub baseline_range: Option<(f64, f64)>,
+    pub candidate_range: Option<(f64, f64)>,
+    /// Samples where one run is NaN and the other is not. Each counts as an
+    /// infinite difference.
+    pub nan_mismatches: usize,
+    pub max_abs: f64,
+    pub rms: f64,
+    /// Time of the largest absolute difference, if the series differ at all.
+    pub worst_time: Option<f64>,
+    /// `candidate - baseline` at every compared sample, leaving out NaN mismatches.
+    #[serde(skip)]
+    pub diff: PlotScalarData,
+}
+
+impl SeriesDiff {
+    /// Compares `candidate` against `baseline`, interpolating the baseline at every
+    /// candidate timestamp. Samples outside the baseline's range are left out of
+    /// the statistics; see `aligned` for whether the ranges match.
+    pub fn new(widget: usize, baseline: &PlotScalarData, candidate: &PlotScalarData) -> Self {
+        let diff: Vec<(f64, f64)> = candidate
+            .data_x
+            .iter()
+            .filter_map(|&(t, y)| Some((t, difference(y, baseline.interpolate(t)?))))
+            .collect();
+
+        let worst = diff
+            .iter()
+            .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+            .filter(|(_, d)| *d != 0.0)
+            .copied();
+        let rms = if diff.is_empty() {
+            0.0
+        } else {
+            (diff.iter().map(|(_, d)| d * d).sum::<f64>() / diff.len() as f64).sqrt()
+        };
+        Self {
+            widget,
+            samples: diff.len(),
+            baseline_samples: baseline.data_x.len(),
+            candidate_samples: candidate.data_x.len(),
+            baseline_range: time_range(baseline),
+            candidate_range: time_range(candidate),
+            nan_mismatches: diff.iter().filter(|(_, d)| d.is_infinite()).count(),
+            max_abs: worst.map(|(_, d)| d.abs()).unwrap_or_default(),
+            rms,
+            worst_time: worst.map(|(t, _)| t),
+            diff: PlotScalarData::new(diff.into_iter().filter(|(_, d)| d.is_finite()).collect()),
+        }
+    }
+
+    /// Whether both series have as many samples over the same time range. A
+    /// truncated or empty candidate is not aligned.
+    pub fn aligned(&self) -> bool {
+        let same_time = |a: f64, b: f64| (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0);
+        self.baseline_samples == self.candidate_samples
+            && match (self.baseline_range, self.candidate_range) {
+                (Some((a0, a1)), Some((b0, b1))) => same_time(a0, b0) && same_time(a1, b1),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+    }
+}
+
+/// `candidate - baseline`, zero if both are NaN and infinite if only one is.
+fn difference(candidate: f64, baseline: f64) -> f64 {
+    if candidate == baseline || (candidate.is_nan() && baseline.is_nan()) {
+        0.0
+    } else if candidate.is_nan() || baseline.is_nan() {
+        f64::INFINITY
+    } else {
+        candidate - baseline
+    }
+}
+
+fn time_range(data: &PlotScalarData) -> Option<(f64, f64)> {
+    Some((data.data_x.first()?.0, data.data_x.last()?.0))
+}
+
+/// Comparison of one viz present in both recordings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VizDiff {
+    pub name: String,
+    pub series: Vec<SeriesDiff>,
+    /// Widgets that could not be compared: 3D views, or widgets whose kind differs
+    /// between the two runs.
+    pub skipped: usize,
+    pub baseline_widgets: usize,
+    pub candidate_widgets: usize,
+}
+
+impl VizDiff {
+    /// Pairs widgets by index, as `Viz::merge` does.
+    pub fn new(baseline: &Viz, candidate: &Viz) -> Self {
+        let mut series = Vec::new();
+        let mut skipped = baseline.widgets.len().abs_diff(candidate.widgets.len());
+        for (index, pair) in baseline.widgets.iter().zip(&candidate.widgets).enumerate() {
+            match pair {
+                (Widget::PlotScalar(a), Widget::PlotScalar(b)) => {
+                    series.push(SeriesDiff::new(index, a, b))
+                }
+                _ => skipped += 1,
+            }
+        }
+        Self {
+            name: baseline.name.clone(),
+            series,
+            skipped,
+            baseline_widgets: baseline.widgets.len(),
+            candidate_widgets: candidate.widgets.len(),
+        }
+    }
+
+    /// `name`, or `name[index]` when the viz has several scalar series.
+    pub fn label(&self, series: &SeriesDiff) -> String {
+        match self.series.len() {
+            1 => self.name.clone(),
+            _ => format!("{}[{}]", self.name, series.widget),
+        }
+    }
+
+    pub fn max_abs(&self) -> f64 {
+        self.series.iter().map(|s| s.max_abs).fold(0.0, f64::max)
+    }
+
+    /// The series with the largest absolute difference.
+    pub fn worst(&self) -> Option<&SeriesDiff> {
+        self.series
+            .iter()
+            .max_by(|a, b| a.max_abs.total_cmp(&b.max_abs))
+    }
+}
+
+/// Run-to-run comparison of two recordings, aligned by viz name and time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comparison {
+    /// Session id of the baseline recording.
+    pub baseline: String,
+    /// Session id of the recording compared against the baseline.
+    pub candidate: String,
+    pub vizs: Vec<VizDiff>,
+    pub only_in_baseline: Vec<String>,
+    pub only_in_candidate: Vec<String>,
+}
+
+impl Comparison {
+    pub fn new(baseline: &Recording, candidate: &Recording) -> Self {
+        let mut vizs = Vec::new();
+        let mut only_in_baseline = Vec::new();
+        for viz in baseline.vizs.iter() {
+            match candidate.get_viz(&viz.name) {
+                Some(other) => vizs.push(VizDiff::new(viz, other)),
+                None => only_in_baseline.push(viz.name.clone()),
+            }
+        }
+        let only_in_candidate = candidate
+            .vizs
+            .names()
+            .filter(|name| !baseline.vizs.contains(name))
+            .map(str::to_string)
+            .collect();
+        Self {
+            baseline: baseline.session_id.clone(),
+            candidate: candidate.session_id.clone(),
+            vizs,
+            only_in_baseline,
+            only_in_candidate,
+        }
+    }
+
+    /// Largest absolute difference over every compared series.
+    pub fn max_abs(&self) -> f64 {
+        self.vizs.iter().map(VizDiff::max_abs).fold(0.0, f64::max)
+    }
+
+    /// Whether every compared series stays within `tolerance` of the baseline and
+    /// both recordings have the same vizs, widgets and samples.
+    pub fn within(&self, tolerance: f64) -> bool {
+        self.failures(tolerance).is_empty()
+    }
+
+    /// One line per way the candidate falls short of the baseline, see `within`.
+    pub fn failures(&self, tolerance: f64) -> Vec<String> {
+        let mut failures = self.shape_failures();
+        for viz in self.vizs.iter() {
+            for series in viz.series.iter() {
+                if series.nan_mismatches == 0 && series.max_abs > tolerance {
+                    failures.push(format!(
+                        "{}: max abs difference {:e} at t={} (tolerance {:e})",
+                        viz.label(series),
+                        series.max_abs,
+                        series.worst_time.unwrap_or_default(),
+                        tolerance
+                    ));
+                }
+            }
+        }
+        failures
+    }
+
+    /// Like `failures`, without comparing values: vizs only in one run, differing
+    /// widget counts, series that are not `aligned`, and samples that are NaN in only
+    /// one run.
+    pub fn shape_failures(&self) -> Vec<String> {
+        let mut failures: Vec<String> = self
+            .only_in_baseline
+            .iter()
+            .map(|name| format!("{}: missing from candidate", name))
+            .chain(
+                self.only_in_candidate
+                    .iter()
+                    .map(|name| format!("{}: not in baseline", name)),
+            )
+            .collect();
+        for viz in self.vizs.iter() {
+            if viz.baseline_widgets != viz.candidate_widgets {
+                failures.push(format!(
+                    "{}: {} widgets, baseline has {}",
+                    viz.name, viz.candidate_widgets, viz.baseline_widgets
+                ));
+            }
+            for series in viz.series.iter() {
+                let label = viz.label(series);
+                if !series.aligned() {
+                    failures.push(format!(
+                        "{}: {} samples over {}, baseline has {} over {}",
+                        label,
+                        series.candidate_samples,
+                        format_range(series.candidate_range),
+                        series.baseline_samples,
+                        format_range(series.baseline_range)
+                    ));
+                }
+                if series.nan_mismatches > 0 {
+                    failures.push(format!(
+                        "{}: {} samples are NaN in only one run",
+                        label, series.nan_mismatches
+                    ));
+                }
+            }
+        }
+        failures
+    }
+
+    /// Recording of `candidate - baseline` per scalar series, one `<name> diff` viz
+    /// per compared viz, with an annotation at the worst divergence of each. Open it
+    /// next to either run to overlay the difference.
+    pub fn diff_recording(&self) -> Recording {
+        let mut recording = Recording::new(
+            format!("{} vs {}", self.candidate, self.baseline),
+            format!("diff-{}-{}", self.candidate, self.baseline),
+        );
+        recording.set_tag("compare.baseline", &self.baseline);
+        recording.set_tag("compare.candidate", &self.candidate);
+
+        for viz_diff in self.vizs.iter().filter(|v| !v.series.is_empty()) {
+            let name = format!("{} diff", viz_diff.name);
+            let mut viz = Viz::new(name.clone());
+            for series in viz_diff.series.iter() {
+                viz.add_widget(Widget::PlotScalar(series.diff.clone()));
+            }
+            viz.set_metadata("compare.max_abs", viz_diff.max_abs());
+            viz.set_metadata(
+                "compare.rms",
+                viz_diff.series.iter().map(|s| s.rms).fold(0.0, f64::max),
+            );
+            if let Some(t) = viz_diff.worst().and_then(|s| s.worst_time) {
+                viz.set_metadata("compare.worst_time", t);
+                recording.add_annotation(
+                    Annotation::instant(t, "worst divergence")
+                        .with_viz(&name)
+                        .with_viz(&viz_diff.name),
+                );
+            }
+            recording.add_viz(viz);
+        }
+        recording
+    }
+}
+
+fn format_range(range: Option<(f64, f64)>) -> String {
+    match range {
+        Some((start, end)) => format!("[{}, {}]", start, end),
+        None => "nothing".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording(data: Vec<(f64, f64)>) -> Recording {
+        let mut recording = Recording::new("run".to_string(), "run".to_string());
+        recording.add_viz(
+            Viz::new("speed".to_string())
+                .with_widget(Widget::PlotScalar(PlotScalarData::new(data))),
+        );
+        recording
+    }
+
+    fn ramp(n: usize) -> Vec<(f64, f64)> {
+        (0..n).map(|i| (i as f64, i as f64)).collect()
+    }
+
+    #[test]
+    fn identical_runs_are_within_zero() {
+        let comparison = Comparison::new(&recording(ramp(10)), &recording(ramp(10)));
+        assert!(comparison.within(0.0));
+        assert_eq!(comparison.vizs[0].series[0].worst_time, None);
+    }
+
+    #[test]
+    fn offset_is_measured() {
+        let shifted = ramp(10).into_iter().map(|(t, y)| (t, y + 0.5)).collect();
+        let comparison = Comparison::new(&recording(ramp(10)), &recording(shifted));
+        assert_eq!(comparison.max_abs(), 0.5);
+        assert!(comparison.within(0.5));
+        assert!(!comparison.within(0.1));
+    }
+
+    #[test]
+    fn nan_in_one_run_is_an_infinite_difference() {
+        let mut data = ramp(10);
+        data[4].1 = f64::NAN;
+        let comparison = Comparison::new(&recording(ramp(10)), &recording(data.clone()));
+        let series = &comparison.vizs[0].series[0];
+        assert_eq!(series.nan_mismatches, 1);
+        assert_eq!(series.max_abs, f64::INFINITY);
+        assert_eq!(series.worst_time, Some(4.0));
+        assert_eq!(series.diff.data_x.len(), 9);
+        assert!(!comparison.within(f64::MAX));
+
+        // NaN in both runs at the same time matches
+        let comparison = Comparison::new(&recording(data.clone()), &recording(data));
+        assert!(comparison.within(0.0));
+    }
+
+    #[test]
+    fn truncated_and_empty_candidates_fail() {
+        let truncated = Comparison::new(&recording(ramp(10)), &recording(ramp(5)));
+        assert_eq!(truncated.max_abs(), 0.0);
+        assert!(!truncated.within(1.0));
+        assert_eq!(truncated.failures(1.0).len(), 1);
+
+        let empty = Comparison::new(&recording(ramp(10)), &recording(Vec::new()));
+        assert!(!empty.within(1.0));
+
+        let longer = Comparison::new(&recording(ramp(5)), &recording(ramp(10)));
+        assert!(!longer.within(1.0));
+    }
+
+    #[test]
+    fn missing_vizs_fail() {
+        let comparison =
+            Comparison::new(&recording(ramp(3)), &Recording::new("b".into(), "b".into()));
+        assert_eq!(comparison.only_in_baseline, vec!["speed".to_string()]);
+        assert!(!comparison.within(1.0));
+    }
+}
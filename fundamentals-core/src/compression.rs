@@ -0,0 +1,222 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const LZ4_MAGIC: [u8; 4] = [0x04, 0x22, 0x4d, 0x18];
+
+/// Compression of a recording file, applied on top of whatever format it is
+/// serialized in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Zstd,
+    Lz4,
+}
+
+impl Compression {
+    /// File name suffixes, e.g. `recording.json.zst`.
+    pub const EXTENSIONS: &[(&str, Compression)] = &[
+        ("zst", Compression::Zstd),
+        ("zstd", Compression::Zstd),
+        ("lz4", Compression::Lz4),
+    ];
+
+    pub fn from_path(path: &Path) -> Self {
+        path.extension()
+            .and_then(|ext| {
+                let ext = ext.to_str()?.to_ascii_lowercase();
+                Self::EXTENSIONS
+                    .iter()
+                    .find(|(e, _)| *e == ext)
+                    .map(|(_, compression)| *compression)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Compression of data starting with `bytes`, from the frame magic number.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else if bytes.starts_with(&LZ4_MAGIC) {
+            Compression::Lz4
+        } else {
+            Compression::None
+        }
+    }
+
+    /// `path` without its compression suffix, for picking the inner format.
+    pub fn strip_extension(path: &Path) -> &Path {
+        match Self::from_path(path) {
+            Compression::None => path,
+            _ => path.file_stem().map(Path::new).unwrap_or(path),
+        }
+    }
+}
+
+/// Opens `path` for streaming reads, decompressing it if its first bytes are a
+/// zstd or lz4 frame, whatever its extension.
+pub fn open(path: impl AsRef<Path>) -> Result<Box<dyn BufRead>, anyhow::Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let compression = Compression::detect(reader.fill_buf()?);
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+        Compression::Lz4 => Box::new(BufReader::new(lz4_flex::frame::FrameDecoder::new(reader))),
+    })
+}
+
+/// Streaming writer that compresses as it goes. Call `finish` to write the end
+/// of the compressed frame.
+pub struct CompressedWriter {
+    inner: Inner,
+}
+
+enum Inner {
+    None(BufWriter<File>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+    Lz4(lz4_flex::frame::FrameEncoder<BufWriter<File>>),
+}
+
+impl CompressedWriter {
+    /// Creates `path`, compressed as its extension says.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let compression = Compression::from_path(path.as_ref());
+        Self::create_with(path, compression)
+    }
+
+    pub fn create_with(
+        path: impl AsRef<Path>,
+        compression: Compression,
+    ) -> Result<Self, anyhow::Error> {
+        let file = BufWriter::new(File::create(path)?);
+        let inner = match compression {
+            Compression::None => Inner::None(file),
+            Compression::Zstd => Inner::Zstd(zstd::Encoder::new(file, 0)?),
+            Compression::Lz4 => Inner::Lz4(lz4_flex::frame::FrameEncoder::new(file)),
+        };
+        Ok(Self { inner })
+    }
+
+    pub fn finish(self) -> Result<(), anyhow::Error> {
+        let mut file = match self.inner {
+            Inner::None(file) => file,
+            Inner::Zstd(encoder) => encoder.finish()?,
+            Inner::Lz4(encoder) => encoder.finish()?,
+        };
+        file.flush()?;
+        Ok(())
+    }
+}
+
+impl Write for CompressedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match &mut self.inner {
+            Inner::None(w) => w.write(buf),
+            Inner::Zstd(w) => w.write(buf),
+            Inner::Lz4(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.inner {
+            Inner::None(w) => w.flush(),
+            Inner::Zstd(w) => w.flush(),
+            Inner::Lz4(w) => w.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, path::PathBuf};
+
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("fundamentals-compression-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    fn contents() -> Vec<u8> {
+        (0..10_000)
+            .flat_map(|i: u32| (i % 7).to_le_bytes())
+            .collect()
+    }
+
+    fn write(path: &Path, compression: Compression) {
+        let mut writer = CompressedWriter::create_with(path, compression).unwrap();
+        writer.write_all(&contents()).unwrap();
+        writer.finish().unwrap();
+    }
+
+    fn read(path: &Path) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        open(path).unwrap().read_to_end(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn every_compression_round_trips() {
+        for (name, compression) in [
+            ("plain.bin", Compression::None),
+            ("zstd.bin.zst", Compression::Zstd),
+            ("lz4.bin.lz4", Compression::Lz4),
+        ] {
+            let path = temp_path(name);
+            write(&path, compression);
+            let raw = std::fs::read(&path).unwrap();
+            assert_eq!(Compression::detect(&raw), compression);
+            if compression != Compression::None {
+                assert!(raw.len() < contents().len() / 10);
+            }
+            assert_eq!(read(&path), contents());
+        }
+    }
+
+    #[test]
+    fn compression_is_detected_whatever_the_extension() {
+        let path = temp_path("misnamed.json");
+        write(&path, Compression::Lz4);
+        assert_eq!(Compression::from_path(&path), Compression::None);
+        assert_eq!(read(&path), contents());
+    }
+
+    #[test]
+    fn extension_picks_the_compression() {
+        let path = temp_path("recording.json.ZST");
+        assert_eq!(Compression::from_path(&path), Compression::Zstd);
+        assert_eq!(
+            Compression::strip_extension(&path),
+            Path::new("recording.json")
+        );
+        assert_eq!(
+            Compression::strip_extension(Path::new("recording.json")),
+            Path::new("recording.json")
+        );
+
+        let mut writer = CompressedWriter::create(&path).unwrap();
+        writer.write_all(b"{}").unwrap();
+        writer.finish().unwrap();
+        assert!(std::fs::read(&path).unwrap().starts_with(&ZSTD_MAGIC));
+    }
+
+    #[test]
+    fn empty_and_truncated_files() {
+        let path = temp_path("empty.bin");
+        std::fs::write(&path, b"").unwrap();
+        assert!(read(&path).is_empty());
+
+        let path = temp_path("truncated.bin.zst");
+        write(&path, Compression::Zstd);
+        let raw = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &raw[..raw.len() / 2]).unwrap();
+        let mut bytes = Vec::new();
+        assert!(open(&path).unwrap().read_to_end(&mut bytes).is_err());
+    }
+}
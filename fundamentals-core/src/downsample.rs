@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownsampleAlgorithm {
+    /// Largest-Triangle-Three-Buckets: keeps the visual shape of the line.
+    #[default]
+    Lttb,
+    /// Minimum and maximum of each equally wide x bucket (one bucket per pixel):
+    /// never hides spikes.
+    MinMax,
+}
+
+/// Reduces `data` (sorted by x) to roughly `points` samples.
+pub fn downsample(
+    data: &[(f64, f64)],
+    points: usize,
+    algorithm: DownsampleAlgorithm,
+) -> Vec<(f64, f64)> {
+    match algorithm {
+        DownsampleAlgorithm::Lttb => lttb(data, points),
+        DownsampleAlgorithm::MinMax => min_max(data, points / 2),
+    }
+}
+
+/// Largest-Triangle-Three-Buckets downsampling to `threshold` points.
+///
+/// The first and last samples are always kept. Returns the input unchanged if it
+/// already fits.
+pub fn lttb(data: &[(f64, f64)], threshold: usize) -> Vec<(f64, f64)> {
+    if threshold >= data.len() || threshold < 3 {
+        return data.to_vec();
+    }
+
+    let mut sampled = Vec::with_capacity(threshold);
+    let every = (data.len() - 2) as f64 / (threshold - 2) as f64;
+    let mut a = 0;
+    sampled.push(data[a]);
+
+    for i in 0..threshold - 2 {
+        // Average of the next bucket is the third triangle vertex.
+        let next_start = ((i + 1) as f64 * every) as usize + 1;
+        let next_end = (((i + 2) as f64 * every) as usize + 1).min(data.len());
+        let next = &data[next_start..next_end];
+        let (avg_x, avg_y) = next
+            .iter()
+            .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+        let count = next.len().max(1) as f64;
+        let (avg_x, avg_y) = (avg_x / count, avg_y / count);
+
+        let start = (i as f64 * every) as usize + 1;
+        let end = next_start;
+        let (ax, ay) = data[a];
+        let mut max_area = -1.0;
+        let mut max_index = start;
+        for (j, (x, y)) in data.iter().enumerate().take(end).skip(start) {
+            let area = ((ax - avg_x) * (y - ay) - (ax - x) * (avg_y - ay)).abs();
+            if area > max_area {
+                max_area = area;
+                max_index = j;
+            }
+        }
+
+        sampled.push(data[max_index]);
+        a = max_index;
+    }
+
+    sampled.push(data[data.len() - 1]);
+    sampled
+}
+
+/// Splits the x range into `buckets` equally wide buckets and keeps the minimum and
+/// maximum sample of each, in x order.
+pub fn min_max(data: &[(f64, f64)], buckets: usize) -> Vec<(f64, f64)> {
+    if buckets == 0 || data.len() <= buckets * 2 {
+        return data.to_vec();
+    }
+
+    let x0 = data[0].0;
+    let width = (data[data.len() - 1].0 - x0) / buckets as f64;
+    if width <= 0.0 {
+        return data.to_vec();
+    }
+
+    let mut sampled = Vec::with_capacity(buckets * 2);
+    let mut start = 0;
+    for bucket in 0..buckets {
+        let bound = x0 + width * (bucket + 1) as f64;
+        let end = if bucket + 1 == buckets {
+            data.len()
+        } else {
+            start + data[start..].partition_point(|(x, _)| *x < bound)
+        };
+        let slice = &data[start..end];
+        start = end;
+
+        let Some(min) = slice.iter().min_by(|a, b| a.1.total_cmp(&b.1)) else {
+            continue;
+        };
+        let max = slice
+            .iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or(min);
+        if min.0 == max.0 {
+            sampled.push(*min);
+        } else if min.0 < max.0 {
+            sampled.extend([*min, *max]);
+        } else {
+            sampled.extend([*max, *min]);
+        }
+    }
+    sampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(n: usize) -> Vec<(f64, f64)> {
+        (0..n).map(|i| (i as f64, (i as f64 * 0.1).sin())).collect()
+    }
+
+    fn is_sorted(data: &[(f64, f64)]) -> bool {
+        data.windows(2).all(|w| w[0].0 <= w[1].0)
+    }
+
+    #[test]
+    fn lttb_keeps_ends_and_count() {
+        let data = series(1000);
+        let sampled = lttb(&data, 100);
+        assert_eq!(sampled.len(), 100);
+        assert_eq!(sampled[0], data[0]);
+        assert_eq!(sampled[99], data[999]);
+        assert!(is_sorted(&sampled));
+        assert!(sampled.iter().all(|p| data.contains(p)));
+    }
+
+    #[test]
+    fn lttb_returns_short_input_unchanged() {
+        let data = series(10);
+        assert_eq!(lttb(&data, 10), data);
+        assert_eq!(lttb(&data, 50), data);
+        assert_eq!(lttb(&data, 2), data);
+        assert!(lttb(&[], 100).is_empty());
+    }
+
+    #[test]
+    fn lttb_keeps_spike() {
+        let mut data: Vec<(f64, f64)> = (0..1000).map(|i| (i as f64, 0.0)).collect();
+        data[503].1 = 100.0;
+        assert!(lttb(&data, 20).contains(&(503.0, 100.0)));
+    }
+
+    #[test]
+    fn min_max_keeps_extremes_in_order() {
+        let mut data = series(1000);
+        data[250].1 = -50.0;
+        data[251].1 = 50.0;
+        let sampled = min_max(&data, 10);
+        assert!(sampled.len() <= 20);
+        assert!(is_sorted(&sampled));
+        assert!(sampled.contains(&(250.0, -50.0)));
+        assert!(sampled.contains(&(251.0, 50.0)));
+    }
+
+    #[test]
+    fn min_max_degenerate_input() {
+        let data = series(10);
+        assert_eq!(min_max(&data, 0), data);
+        assert_eq!(min_max(&data, 5), data);
+        let flat = vec![(1.0, 2.0); 100];
+        assert_eq!(min_max(&flat, 10), flat);
+    }
+
+    #[test]
+    fn downsample_min_max_uses_two_points_per_bucket() {
+        let data = series(1000);
+        let sampled = downsample(&data, 100, DownsampleAlgorithm::MinMax);
+        assert!(sampled.len() <= 100);
+        assert_eq!(sampled[0], data[0]);
+    }
+}
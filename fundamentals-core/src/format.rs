@@ -0,0 +1,245 @@
+use std::{
+    io::{BufRead, Read, Write},
+    path::Path,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::compression::{self, Compression};
+
+/// Serialization of a recording file. The binary formats are self-describing like
+/// JSON, so files stay readable as the model gains fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Json,
+    MessagePack,
+    Cbor,
+    /// Robotics log files, see the `mcap` module. Holds whole recordings only.
+    Mcap,
+}
+
+impl Format {
+    pub const EXTENSIONS: &[(&str, Format)] = &[
+        ("json", Format::Json),
+        ("msgpack", Format::MessagePack),
+        ("mpk", Format::MessagePack),
+        ("cbor", Format::Cbor),
+        ("mcap", Format::Mcap),
+    ];
+
+    /// Format named by the extension of `path`, ignoring a compression suffix as in
+    /// `recording.cbor.zst`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = Compression::strip_extension(path)
+            .extension()?
+            .to_str()?
+            .to_ascii_lowercase();
+        Self::EXTENSIONS
+            .iter()
+            .find(|(e, _)| *e == extension)
+            .map(|(_, format)| *format)
+    }
+
+    /// Format of a serialized recording starting with `bytes`. Recordings are maps,
+    /// which start with a distinct byte in each format.
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&crate::mcap::MAGIC[..5]) {
+            return Some(Format::Mcap);
+        }
+        match *bytes.first()? {
+            b'{' | b' ' | b'\t' | b'\r' | b'\n' => Some(Format::Json),
+            0x80..=0x8f | 0xde | 0xdf => Some(Format::MessagePack),
+            // Definite and indefinite length maps, or the self-describe tag
+            0xa0..=0xbf | 0xd9 => Some(Format::Cbor),
+            _ => None,
+        }
+    }
+
+    /// Format of the recording file at `path`, from its content once decompressed,
+    /// or from its extension if the content is not recognized.
+    pub fn detect(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        open(path).map(|(format, _)| format)
+    }
+
+    pub fn write<T: Serialize>(
+        &self,
+        mut writer: impl Write,
+        value: &T,
+    ) -> Result<(), anyhow::Error> {
+        match self {
+            Format::Json => serde_json::to_writer(writer, value)?,
+            // Named fields, so skipped and defaulted fields line up when read back
+            Format::MessagePack => rmp_serde::encode::write_named(&mut writer, value)?,
+            Format::Cbor => ciborium::into_writer(value, writer)?,
+            Format::Mcap => return Err(mcap_only()),
+        }
+        Ok(())
+    }
+
+    pub fn read<T: DeserializeOwned>(&self, reader: impl Read) -> Result<T, anyhow::Error> {
+        Ok(match self {
+            Format::Json => serde_json::from_reader(reader)?,
+            Format::MessagePack => rmp_serde::from_read(reader)?,
+            Format::Cbor => ciborium::from_reader(reader)?,
+            Format::Mcap => return Err(mcap_only()),
+        })
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Format::Json => "JSON",
+            Format::MessagePack => "MessagePack",
+            Format::Cbor => "CBOR",
+            Format::Mcap => "MCAP",
+        })
+    }
+}
+
+fn mcap_only() -> anyhow::Error {
+    anyhow::anyhow!("MCAP holds recordings only, see Recording::save_to_file")
+}
+
+/// Opens a recording file for reading, along with its detected format.
+pub(crate) fn open(path: impl AsRef<Path>) -> Result<(Format, Box<dyn BufRead>), anyhow::Error> {
+    let path = path.as_ref();
+    let mut reader = compression::open(path)?;
+    let format = Format::sniff(reader.fill_buf()?)
+        .or_else(|| Format::from_path(path))
+        .ok_or_else(|| anyhow::anyhow!("Unknown recording format: {}", path.display()))?;
+    Ok((format, reader))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{
+        recording::Recording,
+        viz::Viz,
+        widgets::{
+            plot_scalar::PlotScalarData,
+            precision::Precision,
+            three_d_view::{PointDelta, ThreeDPrimative, ThreeDViewData},
+            Widget,
+        },
+    };
+
+    fn recording() -> Recording {
+        let mut recording = Recording::new("drive".to_string(), "run-7".to_string());
+        recording.set_tag("car", "blue");
+        let speed = PlotScalarData::new((0..50).map(|i| (i as f64 * 0.1, i as f64)).collect());
+        recording.add_viz(Viz::new("speed".to_string()).with_widget(Widget::PlotScalar(speed)));
+        let current = PlotScalarData::new(vec![(0.0, 0.25), (1.0, -3.5)])
+            .with_precision(Precision::i16_range(-4.0, 4.0));
+        recording.add_viz(Viz::new("current".to_string()).with_widget(Widget::PlotScalar(current)));
+        let lidar = ThreeDViewData {
+            primatives: vec![
+                (
+                    0.0,
+                    ThreeDPrimative::points(&[(1.0, 2.0, 3.0)], Precision::F32),
+                ),
+                (
+                    1.0,
+                    ThreeDPrimative::PointDelta(PointDelta {
+                        len: 2,
+                        changed: vec![(1, (4.0, 5.0, 6.0))],
+                    }),
+                ),
+                (2.0, ThreeDPrimative::PointDelta(PointDelta::default())),
+            ],
+        };
+        recording.add_viz(Viz::new("lidar".to_string()).with_widget(Widget::ThreeDView(lidar)));
+        recording
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fundamentals-format-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    fn binary_formats_round_trip() {
+        let expected = serde_json::to_value(recording()).unwrap();
+        for format in [Format::MessagePack, Format::Cbor] {
+            let mut bytes = Vec::new();
+            format.write(&mut bytes, &recording()).unwrap();
+            assert_eq!(Format::sniff(&bytes), Some(format));
+            let read: Recording = format.read(bytes.as_slice()).unwrap();
+            assert_eq!(serde_json::to_value(read).unwrap(), expected, "{}", format);
+        }
+    }
+
+    #[test]
+    fn binary_formats_keep_nan() {
+        let data = PlotScalarData::new(vec![(0.0, f64::NAN), (1.0, 1.0)]);
+        for format in [Format::MessagePack, Format::Cbor] {
+            let mut bytes = Vec::new();
+            format.write(&mut bytes, &data).unwrap();
+            let read: PlotScalarData = format.read(bytes.as_slice()).unwrap();
+            assert!(read.data_x[0].1.is_nan(), "{}", format);
+            assert_eq!(read.data_x[1], (1.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn sniff_recognizes_the_first_byte() {
+        assert_eq!(Format::sniff(b"\n  {\"name\": 1}"), Some(Format::Json));
+        assert_eq!(Format::sniff(&[0x81, 0xa4]), Some(Format::MessagePack));
+        assert_eq!(
+            Format::sniff(&[0xde, 0x00, 0x20]),
+            Some(Format::MessagePack)
+        );
+        assert_eq!(Format::sniff(&[0xa4, 0x64]), Some(Format::Cbor));
+        assert_eq!(Format::sniff(&[0xd9, 0xd9, 0xf7]), Some(Format::Cbor));
+        assert_eq!(Format::sniff(crate::mcap::MAGIC), Some(Format::Mcap));
+        assert_eq!(Format::sniff(b"name,value"), None);
+        assert_eq!(Format::sniff(b""), None);
+    }
+
+    #[test]
+    fn extension_names_the_format() {
+        assert_eq!(
+            Format::from_path(Path::new("a.mpk")),
+            Some(Format::MessagePack)
+        );
+        assert_eq!(
+            Format::from_path(Path::new("a.CBOR.zst")),
+            Some(Format::Cbor)
+        );
+        assert_eq!(
+            Format::from_path(Path::new("a.msgpack.lz4")),
+            Some(Format::MessagePack)
+        );
+        assert_eq!(Format::from_path(Path::new("a.zst")), None);
+        assert_eq!(Format::from_path(Path::new("a.csv")), None);
+    }
+
+    #[test]
+    fn content_wins_over_the_extension() {
+        let path = temp_path("cbor-named.json");
+        let mut bytes = Vec::new();
+        Format::Cbor.write(&mut bytes, &recording()).unwrap();
+        std::fs::write(&path, bytes).unwrap();
+        assert_eq!(Format::detect(&path).unwrap(), Format::Cbor);
+        assert_eq!(Recording::load_from_file(&path).unwrap().name, "drive");
+
+        // Unrecognized content falls back to the extension
+        let path = temp_path("empty.msgpack");
+        std::fs::write(&path, b"").unwrap();
+        assert_eq!(Format::detect(&path).unwrap(), Format::MessagePack);
+        let path = temp_path("empty.bin");
+        std::fs::write(&path, b"").unwrap();
+        assert!(Format::detect(&path).is_err());
+    }
+
+    #[test]
+    fn mcap_is_for_recordings_only() {
+        assert!(Format::Mcap.write(Vec::new(), &recording()).is_err());
+        assert!(Format::Mcap.read::<Recording>(b"".as_slice()).is_err());
+    }
+}
@@ -1,3 +1,12 @@
+pub mod annotation;
+pub mod assets;
+pub mod compare;
+pub mod compression;
+pub mod downsample;
+pub mod format;
+pub mod mcap;
 pub mod recording;
+pub mod store;
+pub mod transforms;
 pub mod viz;
 pub mod widgets;
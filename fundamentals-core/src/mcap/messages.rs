@@ -0,0 +1,723 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use base64::Engine;
+
+use crate::widgets::mesh::Pose;
+
+/// A decoded message, whatever its encoding.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Struct(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn from_json(json: serde_json::Value) -> Option<Self> {
+        Some(match json {
+            serde_json::Value::Null => return None,
+            serde_json::Value::Bool(b) => Value::Number(if b { 1.0 } else { 0.0 }),
+            serde_json::Value::Number(n) => Value::Number(n.as_f64()?),
+            serde_json::Value::String(s) => Value::Text(s),
+            serde_json::Value::Array(items) => {
+                Value::List(items.into_iter().filter_map(Value::from_json).collect())
+            }
+            serde_json::Value::Object(fields) => Value::Struct(
+                fields
+                    .into_iter()
+                    .filter_map(|(name, value)| Some((name, Value::from_json(value)?)))
+                    .collect(),
+            ),
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Struct(fields) => fields.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Numeric fields as `(dotted.path, value)`, descending into nested messages but
+    /// not into arrays. Message headers are left out.
+    pub fn scalars(&self) -> Vec<(String, f64)> {
+        let mut scalars = Vec::new();
+        self.collect_scalars("", &mut scalars);
+        scalars
+    }
+
+    fn collect_scalars(&self, path: &str, scalars: &mut Vec<(String, f64)>) {
+        match self {
+            Value::Number(n) => scalars.push((path.to_string(), *n)),
+            Value::Struct(fields) => {
+                for (name, value) in fields {
+                    if path.is_empty() && name == "header" {
+                        continue;
+                    }
+                    let path = if path.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{}.{}", path, name)
+                    };
+                    value.collect_scalars(&path, scalars);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Field types of a ROS message definition, as found in `ros1msg` and `ros2msg`
+/// schemas: the message itself followed by every type it depends on.
+#[derive(Debug, Clone)]
+pub struct MessageSchema {
+    root: String,
+    types: HashMap<String, Vec<Field>>,
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    name: String,
+    kind: FieldKind,
+    arity: Arity,
+}
+
+#[derive(Debug, Clone)]
+enum FieldKind {
+    Primitive(Primitive),
+    Message(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Arity {
+    Single,
+    Fixed(usize),
+    Dynamic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Primitive {
+    Bool,
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Int64,
+    UInt64,
+    Float32,
+    Float64,
+    String,
+    /// ROS 1 `time`: seconds and nanoseconds.
+    Time,
+    /// ROS 1 `duration`: signed seconds and nanoseconds.
+    Duration,
+}
+
+impl Primitive {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "bool" => Primitive::Bool,
+            "int8" => Primitive::Int8,
+            "uint8" | "byte" | "char" => Primitive::UInt8,
+            "int16" => Primitive::Int16,
+            "uint16" => Primitive::UInt16,
+            "int32" => Primitive::Int32,
+            "uint32" => Primitive::UInt32,
+            "int64" => Primitive::Int64,
+            "uint64" => Primitive::UInt64,
+            "float32" => Primitive::Float32,
+            "float64" => Primitive::Float64,
+            "string" => Primitive::String,
+            "time" => Primitive::Time,
+            "duration" => Primitive::Duration,
+            _ => return None,
+        })
+    }
+}
+
+/// `pkg/msg/Type` and `Type` within `pkg` both become `pkg/Type`.
+fn qualify(name: &str, package: &str) -> String {
+    match name.split_once('/') {
+        Some((pkg, rest)) => format!("{}/{}", pkg, rest.trim_start_matches("msg/")),
+        None if name == "Header" => "std_msgs/Header".to_string(),
+        None => format!("{}/{}", package, name),
+    }
+}
+
+impl MessageSchema {
+    pub fn parse(name: &str, definition: &str) -> Result<Self, anyhow::Error> {
+        let root = qualify(name, "");
+        let mut types = HashMap::new();
+        let mut current = root.clone();
+        let mut fields = Vec::new();
+        for line in definition.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.starts_with("===") {
+                types.insert(std::mem::take(&mut current), std::mem::take(&mut fields));
+                continue;
+            }
+            if let Some(name) = line.strip_prefix("MSG:") {
+                current = qualify(name.trim(), "");
+                continue;
+            }
+            // Blank lines and constants
+            if line.is_empty() || (line.contains('=') && !line.contains("<=")) {
+                continue;
+            }
+            let mut tokens = line.split_whitespace();
+            let (Some(ty), Some(field)) = (tokens.next(), tokens.next()) else {
+                continue;
+            };
+            let package = current.split('/').next().unwrap_or("");
+            fields.push(Field::parse(ty, field, package)?);
+        }
+        types.insert(current, fields);
+        Ok(Self { root, types })
+    }
+
+    /// Decodes a ROS 1 serialized message.
+    pub fn decode_ros1(&self, data: &[u8]) -> Result<Value, anyhow::Error> {
+        let mut reader = Reader {
+            data,
+            pos: 0,
+            origin: 0,
+            cdr: false,
+            big_endian: false,
+        };
+        self.decode_message(&mut reader, &self.root, 0)
+    }
+
+    /// Decodes a ROS 2 message serialized as CDR with its encapsulation header.
+    pub fn decode_cdr(&self, data: &[u8]) -> Result<Value, anyhow::Error> {
+        let big_endian = match data.get(..2) {
+            Some([0, 0]) => true,
+            Some([0, 1]) => false,
+            _ => return Err(anyhow::anyhow!("Unsupported CDR encapsulation")),
+        };
+        let mut reader = Reader {
+            data,
+            pos: 4,
+            origin: 4,
+            cdr: true,
+            big_endian,
+        };
+        self.decode_message(&mut reader, &self.root, 0)
+    }
+
+    fn decode_message(
+        &self,
+        reader: &mut Reader,
+        name: &str,
+        depth: usize,
+    ) -> Result<Value, anyhow::Error> {
+        if depth > 64 {
+            return Err(anyhow::anyhow!("Message nesting too deep"));
+        }
+        let fields = self
+            .types
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("No definition for {}", name))?;
+        let mut values = Vec::with_capacity(fields.len());
+        for field in fields {
+            let count = match field.arity {
+                Arity::Single => None,
+                Arity::Fixed(n) => Some(n),
+                Arity::Dynamic => Some(reader.u32()? as usize),
+            };
+            let value = match (count, &field.kind) {
+                (None, kind) => self.decode_one(reader, kind, depth)?,
+                (Some(n), FieldKind::Primitive(Primitive::UInt8 | Primitive::Int8)) => {
+                    Value::Bytes(reader.take(n)?.to_vec())
+                }
+                (Some(n), kind) => {
+                    // Every element takes at least a byte
+                    if n > reader.remaining() {
+                        return Err(anyhow::anyhow!("Array of {} exceeds message", n));
+                    }
+                    Value::List(
+                        (0..n)
+                            .map(|_| self.decode_one(reader, kind, depth))
+                            .collect::<Result<_, _>>()?,
+                    )
+                }
+            };
+            values.push((field.name.clone(), value));
+        }
+        Ok(Value::Struct(values))
+    }
+
+    fn decode_one(
+        &self,
+        reader: &mut Reader,
+        kind: &FieldKind,
+        depth: usize,
+    ) -> Result<Value, anyhow::Error> {
+        let primitive = match kind {
+            FieldKind::Message(name) => return self.decode_message(reader, name, depth + 1),
+            FieldKind::Primitive(primitive) => *primitive,
+        };
+        Ok(match primitive {
+            Primitive::Bool | Primitive::UInt8 => Value::Number(reader.take(1)?[0] as f64),
+            Primitive::Int8 => Value::Number(reader.take(1)?[0] as i8 as f64),
+            Primitive::Int16 => Value::Number(i16::decode(reader.bytes()?) as f64),
+            Primitive::UInt16 => Value::Number(u16::decode(reader.bytes()?) as f64),
+            Primitive::Int32 => Value::Number(i32::decode(reader.bytes()?) as f64),
+            Primitive::UInt32 => Value::Number(reader.u32()? as f64),
+            Primitive::Int64 => Value::Number(i64::decode(reader.bytes()?) as f64),
+            Primitive::UInt64 => Value::Number(u64::decode(reader.bytes()?) as f64),
+            Primitive::Float32 => Value::Number(f32::decode(reader.bytes()?) as f64),
+            Primitive::Float64 => Value::Number(f64::decode(reader.bytes()?)),
+            Primitive::String => {
+                let len = reader.u32()? as usize;
+                let bytes = reader.take(len)?;
+                // CDR counts the terminating NUL
+                let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+                Value::Text(String::from_utf8_lossy(bytes).into_owned())
+            }
+            Primitive::Time => {
+                let (sec, nsec) = (reader.u32()?, reader.u32()?);
+                Value::Number(sec as f64 + nsec as f64 * 1e-9)
+            }
+            Primitive::Duration => {
+                let (sec, nsec) = (reader.u32()? as i32, reader.u32()? as i32);
+                Value::Number(sec as f64 + nsec as f64 * 1e-9)
+            }
+        })
+    }
+}
+
+impl Field {
+    fn parse(ty: &str, name: &str, package: &str) -> Result<Self, anyhow::Error> {
+        let (base, arity) = match ty.strip_suffix(']').and_then(|t| t.split_once('[')) {
+            Some((base, "")) => (base, Arity::Dynamic),
+            // Bounded sequences are serialized like unbounded ones
+            Some((base, bound)) if bound.starts_with("<=") => (base, Arity::Dynamic),
+            Some((base, n)) => (base, Arity::Fixed(n.parse()?)),
+            None => (ty, Arity::Single),
+        };
+        // Bounded strings, `string<=10`
+        let base = base.split("<=").next().unwrap_or(base);
+        let kind = match Primitive::parse(base) {
+            Some(primitive) => FieldKind::Primitive(primitive),
+            None if base == "wstring" => {
+                return Err(anyhow::anyhow!("wstring fields are not supported"))
+            }
+            None => FieldKind::Message(qualify(base, package)),
+        };
+        Ok(Self {
+            name: name.to_string(),
+            kind,
+            arity,
+        })
+    }
+}
+
+/// Reads ROS 1 or CDR serialized data. CDR aligns every primitive to its size,
+/// counted from the end of the encapsulation header.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    origin: usize,
+    cdr: bool,
+    big_endian: bool,
+}
+
+/// Fixed-size numbers decodable from raw bytes.
+trait Decode<const N: usize> {
+    fn decode(bytes: ([u8; N], bool)) -> Self;
+}
+
+macro_rules! decode {
+    ($($t:ty),*) => {
+        $(impl Decode<{ std::mem::size_of::<$t>() }> for $t {
+            fn decode((bytes, big_endian): ([u8; std::mem::size_of::<$t>()], bool)) -> Self {
+                if big_endian {
+                    <$t>::from_be_bytes(bytes)
+                } else {
+                    <$t>::from_le_bytes(bytes)
+                }
+            }
+        })*
+    };
+}
+
+decode!(i16, u16, i32, u32, i64, u64, f32, f64);
+
+impl Reader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.pos)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&[u8], anyhow::Error> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| anyhow::anyhow!("Message ends early"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Result<([u8; N], bool), anyhow::Error> {
+        if self.cdr {
+            let offset = (self.pos - self.origin) % N;
+            if offset != 0 {
+                self.take(N - offset)?;
+            }
+        }
+        let bytes = self.take(N)?.try_into().expect("took N bytes");
+        Ok((bytes, self.big_endian))
+    }
+
+    fn u32(&mut self) -> Result<u32, anyhow::Error> {
+        Ok(u32::decode(self.bytes()?))
+    }
+}
+
+/// Points of a `sensor_msgs/PointCloud2` or `foxglove.PointCloud` message, moved by
+/// the cloud's pose if it has one. Points with a non-finite coordinate are dropped.
+pub fn point_cloud(message: &Value) -> Option<Vec<(f64, f64, f64)>> {
+    let data: Cow<[u8]> = match message.get("data")? {
+        Value::Bytes(bytes) => Cow::Borrowed(bytes),
+        Value::Text(encoded) => Cow::Owned(
+            base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .ok()?,
+        ),
+        Value::List(items) => Cow::Owned(
+            items
+                .iter()
+                .map(|v| v.as_f64().unwrap_or(0.0) as u8)
+                .collect(),
+        ),
+        _ => return None,
+    };
+    // foxglove.PointCloud numbers its field types differently from PointField
+    let (stride, foxglove) = match message.get("point_stride") {
+        Some(stride) => (stride.as_f64()?, true),
+        None => (message.get("point_step")?.as_f64()?, false),
+    };
+    let stride = stride as usize;
+    let big_endian = message
+        .get("is_bigendian")
+        .and_then(Value::as_f64)
+        .is_some_and(|b| b != 0.0);
+    let Value::List(fields) = message.get("fields")? else {
+        return None;
+    };
+    let axis = |name: &str| {
+        let field = fields
+            .iter()
+            .find(|f| matches!(f.get("name"), Some(Value::Text(n)) if n == name))?;
+        let offset = field.get("offset")?.as_f64()? as usize;
+        let code = field
+            .get(if foxglove { "type" } else { "datatype" })?
+            .as_f64()? as u8;
+        let ty = match (foxglove, code) {
+            (_, 7) => NumericType::Float32,
+            (_, 8) => NumericType::Float64,
+            (false, 1) | (true, 2) => NumericType::Int8,
+            (false, 2) | (true, 1) => NumericType::UInt8,
+            (false, 3) | (true, 4) => NumericType::Int16,
+            (false, 4) | (true, 3) => NumericType::UInt16,
+            (false, 5) | (true, 6) => NumericType::Int32,
+            (false, 6) | (true, 5) => NumericType::UInt32,
+            _ => return None,
+        };
+        (offset + ty.size() <= stride).then_some((offset, ty))
+    };
+    let (x, y, z) = (axis("x")?, axis("y")?, axis("z")?);
+    if stride == 0 {
+        return None;
+    }
+    let pose = message.get("pose").and_then(pose);
+    let read = |point: &[u8], (offset, ty): (usize, NumericType)| {
+        ty.read(&point[offset..offset + ty.size()], big_endian)
+    };
+    Some(
+        data.chunks_exact(stride)
+            .map(|point| (read(point, x), read(point, y), read(point, z)))
+            .filter(|p| p.0.is_finite() && p.1.is_finite() && p.2.is_finite())
+            .map(|p| pose.map_or(p, |pose| pose.transform_point(p)))
+            .collect(),
+    )
+}
+
+fn pose(value: &Value) -> Option<Pose> {
+    let position = value.get("position")?;
+    let orientation = value.get("orientation")?;
+    let coord = |v: &Value, axis: &str| v.get(axis).and_then(Value::as_f64);
+    Some(Pose {
+        position: (
+            coord(position, "x")?,
+            coord(position, "y")?,
+            coord(position, "z")?,
+        ),
+        orientation: (
+            coord(orientation, "x")?,
+            coord(orientation, "y")?,
+            coord(orientation, "z")?,
+            coord(orientation, "w")?,
+        ),
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NumericType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl NumericType {
+    fn size(&self) -> usize {
+        match self {
+            NumericType::Int8 | NumericType::UInt8 => 1,
+            NumericType::Int16 | NumericType::UInt16 => 2,
+            NumericType::Int32 | NumericType::UInt32 | NumericType::Float32 => 4,
+            NumericType::Float64 => 8,
+        }
+    }
+
+    fn read(&self, bytes: &[u8], big_endian: bool) -> f64 {
+        fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+            bytes.try_into().expect("sliced to size")
+        }
+        let be = big_endian;
+        match self {
+            NumericType::Int8 => bytes[0] as i8 as f64,
+            NumericType::UInt8 => bytes[0] as f64,
+            NumericType::Int16 => i16::decode((array(bytes), be)) as f64,
+            NumericType::UInt16 => u16::decode((array(bytes), be)) as f64,
+            NumericType::Int32 => i32::decode((array(bytes), be)) as f64,
+            NumericType::UInt32 => u32::decode((array(bytes), be)) as f64,
+            NumericType::Float32 => f32::decode((array(bytes), be)) as f64,
+            NumericType::Float64 => f64::decode((array(bytes), be)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMU: &str = "std_msgs/Header header
+float64 speed
+int32 gear
+float32[] wheels
+geometry_msgs/Vector3 accel
+================================================================================
+MSG: std_msgs/Header
+builtin_interfaces/Time stamp
+string frame_id
+================================================================================
+MSG: builtin_interfaces/Time
+int32 sec
+uint32 nanosec
+================================================================================
+MSG: geometry_msgs/Vector3
+float64 x
+float64 y
+float64 z
+";
+
+    /// CDR writer aligning every value to its size, after the encapsulation header.
+    struct Cdr {
+        data: Vec<u8>,
+        big_endian: bool,
+    }
+
+    impl Cdr {
+        fn new(big_endian: bool) -> Self {
+            let data = vec![0, if big_endian { 0 } else { 1 }, 0, 0];
+            Self { data, big_endian }
+        }
+
+        fn put<const N: usize>(&mut self, le: [u8; N], be: [u8; N]) -> &mut Self {
+            while !(self.data.len() - 4).is_multiple_of(N) {
+                self.data.push(0);
+            }
+            self.data
+                .extend_from_slice(if self.big_endian { &be } else { &le });
+            self
+        }
+
+        fn u32(&mut self, v: u32) -> &mut Self {
+            self.put(v.to_le_bytes(), v.to_be_bytes())
+        }
+
+        fn f64(&mut self, v: f64) -> &mut Self {
+            self.put(v.to_le_bytes(), v.to_be_bytes())
+        }
+
+        fn f32(&mut self, v: f32) -> &mut Self {
+            self.put(v.to_le_bytes(), v.to_be_bytes())
+        }
+
+        fn string(&mut self, s: &str) -> &mut Self {
+            self.u32(s.len() as u32 + 1);
+            self.data.extend_from_slice(s.as_bytes());
+            self.data.push(0);
+            self
+        }
+    }
+
+    fn imu(big_endian: bool) -> Vec<u8> {
+        let mut cdr = Cdr::new(big_endian);
+        cdr.u32(12)
+            .u32(500)
+            .string("imu")
+            .f64(2.5)
+            .u32(-3i32 as u32);
+        cdr.u32(2).f32(1.5).f32(-0.5);
+        cdr.f64(0.1).f64(0.2).f64(9.81);
+        cdr.data
+    }
+
+    #[test]
+    fn cdr_is_aligned_in_both_byte_orders() {
+        let schema = MessageSchema::parse("sensors/msg/Imu", IMU).unwrap();
+        for big_endian in [false, true] {
+            let message = schema.decode_cdr(&imu(big_endian)).unwrap();
+            let header = message.get("header").unwrap();
+            assert_eq!(
+                header.get("frame_id"),
+                Some(&Value::Text("imu".to_string()))
+            );
+            let stamp = header.get("stamp").unwrap();
+            assert_eq!(stamp.get("nanosec").and_then(Value::as_f64), Some(500.0));
+            assert_eq!(
+                message.get("wheels"),
+                Some(&Value::List(vec![Value::Number(1.5), Value::Number(-0.5)]))
+            );
+            assert_eq!(
+                message.scalars(),
+                vec![
+                    ("speed".to_string(), 2.5),
+                    ("gear".to_string(), -3.0),
+                    ("accel.x".to_string(), 0.1),
+                    ("accel.y".to_string(), 0.2),
+                    ("accel.z".to_string(), 9.81),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn cdr_errors_instead_of_reading_past_the_message() {
+        let schema = MessageSchema::parse("sensors/msg/Imu", IMU).unwrap();
+        let data = imu(false);
+        assert!(schema.decode_cdr(&data[..data.len() - 1]).is_err());
+        assert!(schema.decode_cdr(&[1, 0, 0, 0]).is_err());
+
+        // An array claiming more elements than there are bytes left
+        let mut cdr = Cdr::new(false);
+        cdr.u32(0).u32(0).string("").f64(0.0).u32(0).u32(u32::MAX);
+        assert!(schema.decode_cdr(&cdr.data).is_err());
+    }
+
+    #[test]
+    fn ros1_is_packed() {
+        let definition =
+            "Header header\nfloat32 temperature\nuint8[] raw\ntime stamp\nint16[2] pair\n\
+            int32 LIMIT=10\n\
+            ================================================================================\n\
+            MSG: std_msgs/Header\nuint32 seq\ntime stamp\nstring frame_id\n";
+        let schema = MessageSchema::parse("sensors/Temperature", definition).unwrap();
+        let mut data = Vec::new();
+        data.extend_from_slice(&7u32.to_le_bytes());
+        data.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.push(b'x');
+        data.extend_from_slice(&21.5f32.to_le_bytes());
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(&[1, 2, 3]);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&500_000_000u32.to_le_bytes());
+        data.extend_from_slice(&(-4i16).to_le_bytes());
+        data.extend_from_slice(&5i16.to_le_bytes());
+
+        let message = schema.decode_ros1(&data).unwrap();
+        assert_eq!(message.get("raw"), Some(&Value::Bytes(vec![1, 2, 3])));
+        assert_eq!(message.get("stamp").and_then(Value::as_f64), Some(2.5));
+        assert_eq!(
+            message.get("pair"),
+            Some(&Value::List(vec![Value::Number(-4.0), Value::Number(5.0)]))
+        );
+        assert_eq!(
+            message.scalars(),
+            vec![
+                ("temperature".to_string(), 21.5),
+                ("stamp".to_string(), 2.5)
+            ]
+        );
+    }
+
+    #[test]
+    fn point_clouds_are_read_from_either_layout() {
+        let mut data = Vec::new();
+        for (x, y, z) in [(1.0f32, 2.0, 3.0), (f32::NAN, 0.0, 0.0), (4.0, 5.0, 6.0)] {
+            for v in [x, y, z] {
+                data.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        let ros = Value::from_json(serde_json::json!({
+            "point_step": 12,
+            "is_bigendian": false,
+            "fields": [
+                { "name": "x", "offset": 0, "datatype": 7 },
+                { "name": "y", "offset": 4, "datatype": 7 },
+                { "name": "z", "offset": 8, "datatype": 7 },
+            ],
+            "data": data.iter().map(|&b| b as u32).collect::<Vec<_>>(),
+        }))
+        .unwrap();
+        assert_eq!(
+            point_cloud(&ros),
+            Some(vec![(1.0, 2.0, 3.0), (4.0, 5.0, 6.0)])
+        );
+
+        let foxglove = Value::from_json(serde_json::json!({
+            "point_stride": 12,
+            "pose": {
+                "position": { "x": 10.0, "y": 0.0, "z": 0.0 },
+                "orientation": { "x": 0.0, "y": 0.0, "z": 0.0, "w": 1.0 },
+            },
+            "fields": [
+                { "name": "x", "offset": 0, "type": 7 },
+                { "name": "y", "offset": 4, "type": 7 },
+                { "name": "z", "offset": 8, "type": 7 },
+            ],
+            "data": base64::engine::general_purpose::STANDARD.encode(&data),
+        }))
+        .unwrap();
+        assert_eq!(
+            point_cloud(&foxglove),
+            Some(vec![(11.0, 2.0, 3.0), (14.0, 5.0, 6.0)])
+        );
+
+        // A field that does not fit in a point
+        let mut short = ros.clone();
+        if let Value::Struct(fields) = &mut short {
+            fields[0].1 = Value::Number(8.0);
+        }
+        assert_eq!(point_cloud(&short), None);
+    }
+}
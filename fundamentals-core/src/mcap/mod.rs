@@ -0,0 +1,1122 @@
+//! Import and export of [MCAP](https://mcap.dev) files.
+//!
+//! Reading maps every channel to vizs: point cloud messages become 3D views and
+//! other messages one scalar plot per numeric field, named `topic.field.path`.
+//! JSON, ROS 1 and ROS 2 (CDR) messages are decoded.
+//!
+//! Writing stores scalar plots and point clouds as JSON messages Foxglove and other
+//! MCAP tools understand; other widgets are kept as fundamentals JSON so the file
+//! reads back into the same recording. Non-finite scalars are written as the
+//! strings `"NaN"`, `"Infinity"` and `"-Infinity"`, which JSON has no numbers for.
+//!
+//! Files are only ever read, never rewritten: the bridge saves annotations made on
+//! an imported MCAP file to its annotation sidecar, like for any other recording.
+
+pub mod messages;
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap},
+    io::{Read, Write},
+};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotation::Annotation,
+    assets::AssetTable,
+    recording::{Recording, RecordingHeader, RecordingMetadata},
+    viz::Viz,
+    widgets::{
+        non_finite::NonFinite,
+        plot_scalar::PlotScalarData,
+        precision::Precision,
+        three_d_view::{ThreeDPrimative, ThreeDViewData},
+        Widget,
+    },
+};
+
+use messages::{MessageSchema, Value};
+
+pub const MAGIC: &[u8; 8] = b"\x89MCAP0\r\n";
+
+const OP_HEADER: u8 = 0x01;
+const OP_FOOTER: u8 = 0x02;
+const OP_SCHEMA: u8 = 0x03;
+const OP_CHANNEL: u8 = 0x04;
+const OP_MESSAGE: u8 = 0x05;
+const OP_CHUNK: u8 = 0x06;
+const OP_ATTACHMENT: u8 = 0x09;
+const OP_METADATA: u8 = 0x0c;
+const OP_DATA_END: u8 = 0x0f;
+
+const SCALAR_SCHEMA: &str = "fundamentals.Scalar";
+const WIDGET_SCHEMA: &str = "fundamentals.Widget";
+const POINT_CLOUD_SCHEMA: &str = "foxglove.PointCloud";
+const POINT_CLOUD_SCHEMAS: &[&str] = &[
+    POINT_CLOUD_SCHEMA,
+    "sensor_msgs/PointCloud2",
+    "sensor_msgs/msg/PointCloud2",
+];
+/// Metadata record holding the recording's name, session id and metadata.
+const RECORDING_METADATA: &str = "fundamentals.recording";
+/// Channel metadata key holding a viz's source, range and metadata.
+const VIZ_METADATA: &str = "fundamentals.viz";
+/// Channel metadata key holding the `Precision` of a plot's values or a cloud's
+/// points, which the messages themselves carry as `f64`.
+const PRECISION_METADATA: &str = "fundamentals.precision";
+const ANNOTATIONS_ATTACHMENT: &str = "fundamentals/annotations.json";
+const ASSETS_ATTACHMENT: &str = "fundamentals/assets.json";
+/// Tag listing topics whose messages could not be decoded.
+pub const UNDECODED_TAG: &str = "mcap_undecoded_topics";
+
+/// Reads an MCAP file into a recording. `name` names recordings that were not
+/// written by fundamentals, e.g. the file stem.
+pub fn read_recording(reader: impl Read, name: &str) -> Result<Recording, anyhow::Error> {
+    let mut import = Import::default();
+    for_each_record(reader, &[], |op, content| {
+        import.record(op, content)?;
+        Ok(true)
+    })?;
+    import.finish(name)
+}
+
+/// Reads only the recording's identity, stopping at the first messages.
+pub fn read_header(reader: impl Read, name: &str) -> Result<RecordingHeader, anyhow::Error> {
+    let mut header = None;
+    // fundamentals writes its metadata before any message
+    for_each_record(reader, &[OP_MESSAGE, OP_CHUNK], |op, content| {
+        if op == OP_METADATA {
+            header = recording_metadata(content)?;
+        }
+        Ok(header.is_none())
+    })?;
+    Ok(header.unwrap_or_else(|| RecordingHeader {
+        name: name.to_string(),
+        session_id: name.to_string(),
+        metadata: RecordingMetadata::default(),
+    }))
+}
+
+/// Calls `handle` with the opcode and content of every record up to the footer,
+/// decompressing chunks. Stops early at a record in `stop_at` or once `handle`
+/// returns false.
+fn for_each_record(
+    mut reader: impl Read,
+    stop_at: &[u8],
+    mut handle: impl FnMut(u8, &[u8]) -> Result<bool, anyhow::Error>,
+) -> Result<(), anyhow::Error> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(anyhow::anyhow!("Not an MCAP file"));
+    }
+    let mut content = Vec::new();
+    loop {
+        let mut prefix = [0; 9];
+        match reader.read_exact(&mut prefix) {
+            Ok(()) => {}
+            // Files cut off while recording still hold every complete record
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+        let op = prefix[0];
+        let len = u64::from_le_bytes(prefix[1..].try_into().expect("8 bytes"));
+        if op == OP_FOOTER || stop_at.contains(&op) {
+            return Ok(());
+        }
+        content.clear();
+        (&mut reader).take(len).read_to_end(&mut content)?;
+        if (content.len() as u64) < len {
+            return Ok(());
+        }
+        if op == OP_CHUNK {
+            let records = chunk_records(&content)?;
+            let mut cursor = Cursor::new(&records);
+            while !cursor.is_empty() {
+                let op = cursor.u8()?;
+                let len = cursor.u64()? as usize;
+                if !handle(op, cursor.take(len)?)? {
+                    return Ok(());
+                }
+            }
+        } else if !handle(op, &content)? {
+            return Ok(());
+        }
+    }
+}
+
+fn chunk_records(content: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+    let mut cursor = Cursor::new(content);
+    let _start_time = cursor.u64()?;
+    let _end_time = cursor.u64()?;
+    let uncompressed_size = cursor.u64()?;
+    let _crc = cursor.u32()?;
+    let compression = cursor.string()?;
+    let records = cursor.bytes_u64()?;
+    // The recorded size only limits the output; it is not allocated up front, as a
+    // corrupt size could ask for any amount of memory
+    let mut out = Vec::new();
+    match compression.as_str() {
+        "" => return Ok(records.to_vec()),
+        "zstd" => zstd::Decoder::with_buffer(records)?
+            .take(uncompressed_size)
+            .read_to_end(&mut out)?,
+        "lz4" => lz4_flex::frame::FrameDecoder::new(records)
+            .take(uncompressed_size)
+            .read_to_end(&mut out)?,
+        other => return Err(anyhow::anyhow!("Unsupported chunk compression {}", other)),
+    };
+    if out.len() as u64 != uncompressed_size {
+        return Err(anyhow::anyhow!(
+            "Chunk holds {} bytes, its header says {}",
+            out.len(),
+            uncompressed_size
+        ));
+    }
+    Ok(out)
+}
+
+fn recording_metadata(content: &[u8]) -> Result<Option<RecordingHeader>, anyhow::Error> {
+    let mut cursor = Cursor::new(content);
+    if cursor.string()? != RECORDING_METADATA {
+        return Ok(None);
+    }
+    let mut fields = cursor.map()?;
+    let metadata = match fields.remove("metadata") {
+        Some(json) => serde_json::from_str(&json)?,
+        None => RecordingMetadata::default(),
+    };
+    Ok(Some(RecordingHeader {
+        name: fields.remove("name").unwrap_or_default(),
+        session_id: fields.remove("session_id").unwrap_or_default(),
+        metadata,
+    }))
+}
+
+/// Source, range and metadata of a viz, kept in channel metadata.
+#[derive(Default, Serialize, Deserialize)]
+struct VizInfo {
+    source: Option<String>,
+    range: Option<(f64, f64)>,
+    #[serde(default)]
+    metadata: BTreeMap<String, serde_json::Value>,
+}
+
+enum Decoder {
+    Json,
+    Ros1(MessageSchema),
+    Cdr(MessageSchema),
+}
+
+impl Decoder {
+    fn decode(&self, data: &[u8]) -> Result<Value, anyhow::Error> {
+        match self {
+            Decoder::Json => Value::from_json(serde_json::from_slice(data)?)
+                .ok_or_else(|| anyhow::anyhow!("Null message")),
+            Decoder::Ros1(schema) => schema.decode_ros1(data),
+            Decoder::Cdr(schema) => schema.decode_cdr(data),
+        }
+    }
+}
+
+/// What a channel's messages become.
+enum Sink {
+    /// `fundamentals.Scalar` messages, one plot named after the topic.
+    Scalar(Vec<(f64, f64)>),
+    Points(Vec<(f64, ThreeDPrimative)>),
+    Widgets(Vec<Widget>),
+    /// A plot per numeric field of any other message.
+    Fields(BTreeMap<String, Vec<(f64, f64)>>),
+}
+
+struct Channel {
+    topic: String,
+    decoder: Option<Decoder>,
+    sink: Sink,
+    info: Option<VizInfo>,
+    precision: Precision,
+    failed: bool,
+}
+
+struct Schema {
+    name: String,
+    encoding: String,
+    data: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Import {
+    header: Option<RecordingHeader>,
+    schemas: HashMap<u16, Schema>,
+    channels: BTreeMap<u16, Channel>,
+    annotations: Vec<Annotation>,
+    assets: AssetTable,
+}
+
+impl Import {
+    fn record(&mut self, op: u8, content: &[u8]) -> Result<(), anyhow::Error> {
+        let mut cursor = Cursor::new(content);
+        match op {
+            OP_SCHEMA => {
+                let id = cursor.u16()?;
+                let schema = Schema {
+                    name: cursor.string()?,
+                    encoding: cursor.string()?,
+                    data: cursor.bytes_u32()?.to_vec(),
+                };
+                self.schemas.insert(id, schema);
+            }
+            OP_CHANNEL => {
+                let id = cursor.u16()?;
+                let schema_id = cursor.u16()?;
+                let topic = cursor.string()?;
+                let encoding = cursor.string()?;
+                let metadata = cursor.map()?;
+                let channel =
+                    self.channel(topic, &encoding, self.schemas.get(&schema_id), metadata);
+                self.channels.insert(id, channel);
+            }
+            OP_MESSAGE => {
+                let channel_id = cursor.u16()?;
+                let _sequence = cursor.u32()?;
+                let log_time = cursor.u64()?;
+                let _publish_time = cursor.u64()?;
+                if let Some(channel) = self.channels.get_mut(&channel_id) {
+                    let time = log_time as f64 / 1e9;
+                    if channel.message(time, cursor.rest()).is_err() {
+                        channel.failed = true;
+                    }
+                }
+            }
+            OP_METADATA => {
+                if let Some(header) = recording_metadata(content)? {
+                    self.header = Some(header);
+                }
+            }
+            OP_ATTACHMENT => {
+                let _log_time = cursor.u64()?;
+                let _create_time = cursor.u64()?;
+                let name = cursor.string()?;
+                let _media_type = cursor.string()?;
+                let data = cursor.bytes_u64()?;
+                match name.as_str() {
+                    ANNOTATIONS_ATTACHMENT => self.annotations = serde_json::from_slice(data)?,
+                    ASSETS_ATTACHMENT => self.assets = serde_json::from_slice(data)?,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn channel(
+        &self,
+        topic: String,
+        encoding: &str,
+        schema: Option<&Schema>,
+        metadata: BTreeMap<String, String>,
+    ) -> Channel {
+        let schema_name = schema.map_or("", |s| s.name.as_str());
+        let ros_schema = || {
+            let schema = schema?;
+            let definition = std::str::from_utf8(&schema.data).ok()?;
+            MessageSchema::parse(&schema.name, definition).ok()
+        };
+        let decoder = match (encoding, schema.map(|s| s.encoding.as_str())) {
+            ("json", _) => Some(Decoder::Json),
+            ("ros1", Some("ros1msg")) => ros_schema().map(Decoder::Ros1),
+            ("cdr", Some("ros2msg")) => ros_schema().map(Decoder::Cdr),
+            _ => None,
+        };
+        let sink = match schema_name {
+            SCALAR_SCHEMA => Sink::Scalar(Vec::new()),
+            WIDGET_SCHEMA => Sink::Widgets(Vec::new()),
+            name if POINT_CLOUD_SCHEMAS.contains(&name) => Sink::Points(Vec::new()),
+            _ => Sink::Fields(BTreeMap::new()),
+        };
+        Channel {
+            topic,
+            decoder,
+            sink,
+            info: metadata
+                .get(VIZ_METADATA)
+                .and_then(|json| serde_json::from_str(json).ok()),
+            precision: metadata
+                .get(PRECISION_METADATA)
+                .and_then(|json| serde_json::from_str(json).ok())
+                .unwrap_or_default(),
+            failed: false,
+        }
+    }
+
+    fn finish(self, name: &str) -> Result<Recording, anyhow::Error> {
+        let header = self.header.unwrap_or_else(|| RecordingHeader {
+            name: name.to_string(),
+            session_id: name.to_string(),
+            metadata: RecordingMetadata::default(),
+        });
+        let mut recording = Recording::new(header.name, header.session_id);
+        recording.metadata = header.metadata;
+        recording.annotations = self.annotations;
+        recording.assets = self.assets;
+
+        // Vizs in channel order, handed out by index
+        let mut vizs: Vec<Viz> = Vec::new();
+        let mut viz = |name: &str| match vizs.iter().position(|v| v.name == name) {
+            Some(i) => i,
+            None => {
+                vizs.push(Viz::new(name.to_string()));
+                vizs.len() - 1
+            }
+        };
+        let mut undecoded = BTreeSet::new();
+        let mut widgets: Vec<(usize, Widget)> = Vec::new();
+        let mut infos = Vec::new();
+        for channel in self.channels.into_values() {
+            if channel.decoder.is_none() || channel.failed {
+                undecoded.insert(channel.topic.clone());
+            }
+            match channel.sink {
+                Sink::Scalar(data) if !data.is_empty() => {
+                    let data = PlotScalarData::new(data).with_precision(channel.precision);
+                    widgets.push((viz(&channel.topic), Widget::PlotScalar(data)));
+                }
+                Sink::Points(primatives) if !primatives.is_empty() => {
+                    let data = ThreeDViewData { primatives };
+                    widgets.push((viz(&channel.topic), Widget::ThreeDView(data)));
+                }
+                Sink::Widgets(list) => {
+                    let i = viz(&channel.topic);
+                    widgets.extend(list.into_iter().map(|w| (i, w)));
+                }
+                Sink::Fields(fields) => {
+                    for (path, data) in fields {
+                        let name = format!("{}.{}", channel.topic, path);
+                        widgets.push((viz(&name), Widget::PlotScalar(PlotScalarData::new(data))));
+                    }
+                }
+                _ => {}
+            }
+            if let Some(info) = channel.info {
+                infos.push((viz(&channel.topic), info));
+            }
+        }
+        for (i, widget) in widgets {
+            let viz = &mut vizs[i];
+            // Point clouds and the rest of a 3D view are stored on separate channels,
+            // and may share timestamps, so interleave rather than merge
+            let existing = viz.widgets.iter_mut().find_map(|w| match (w, &widget) {
+                (Widget::ThreeDView(data), Widget::ThreeDView(_)) => Some(data),
+                _ => None,
+            });
+            match (existing, widget) {
+                (Some(existing), Widget::ThreeDView(other)) => {
+                    existing.primatives.extend(other.primatives);
+                    existing.primatives.sort_by(|a, b| a.0.total_cmp(&b.0));
+                }
+                (_, widget) => viz.add_widget(widget),
+            }
+        }
+        for (i, info) in infos {
+            let viz = &mut vizs[i];
+            viz.source = info.source;
+            viz.range = info.range;
+            viz.metadata = info.metadata;
+        }
+        for viz in vizs {
+            if !viz.widgets.is_empty() {
+                recording.add_viz(viz);
+            }
+        }
+        if !undecoded.is_empty() {
+            let topics: Vec<_> = undecoded.into_iter().collect();
+            recording.set_tag(UNDECODED_TAG, &topics.join(","));
+        }
+        Ok(recording)
+    }
+}
+
+impl Channel {
+    fn message(&mut self, time: f64, data: &[u8]) -> Result<(), anyhow::Error> {
+        if let Sink::Widgets(widgets) = &mut self.sink {
+            widgets.push(serde_json::from_slice(data)?);
+            return Ok(());
+        }
+        let decoder = self
+            .decoder
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No decoder"))?;
+        let message = decoder.decode(data)?;
+        match &mut self.sink {
+            Sink::Scalar(data) => {
+                let value = match message.get("value") {
+                    Some(Value::Text(text)) => text.parse().ok(),
+                    value => value.and_then(Value::as_f64),
+                };
+                let value = value.ok_or_else(|| anyhow::anyhow!("Missing value"))?;
+                data.push((time, value));
+            }
+            Sink::Points(primatives) => {
+                let points = messages::point_cloud(&message)
+                    .ok_or_else(|| anyhow::anyhow!("Unreadable point cloud"))?;
+                primatives.push((time, ThreeDPrimative::points(&points, self.precision)));
+            }
+            Sink::Fields(fields) => {
+                for (path, value) in message.scalars() {
+                    fields.entry(path).or_default().push((time, value));
+                }
+            }
+            Sink::Widgets(_) => {}
+        }
+        Ok(())
+    }
+}
+
+/// Writes `recording` as an MCAP file.
+pub fn write_recording<'a>(
+    writer: impl Write,
+    recording: &'a Recording,
+) -> Result<(), anyhow::Error> {
+    let mut out = RecordWriter { writer };
+    out.writer.write_all(MAGIC)?;
+    out.record(OP_HEADER, |r| {
+        r.string("");
+        r.string("fundamentals");
+    })?;
+    let metadata = serde_json::to_string(&recording.metadata)?;
+    out.record(OP_METADATA, |r| {
+        r.string(RECORDING_METADATA);
+        r.map(&[
+            ("name", recording.name.as_str()),
+            ("session_id", recording.session_id.as_str()),
+            ("metadata", metadata.as_str()),
+        ]);
+    })?;
+    if !recording.annotations.is_empty() {
+        out.attachment(
+            ANNOTATIONS_ATTACHMENT,
+            &serde_json::to_vec(&recording.annotations)?,
+        )?;
+    }
+    if !recording.assets.is_empty() {
+        out.attachment(ASSETS_ATTACHMENT, &serde_json::to_vec(&recording.assets)?)?;
+    }
+    let schemas = [
+        (1, SCALAR_SCHEMA, SCALAR_JSON_SCHEMA),
+        (2, POINT_CLOUD_SCHEMA, POINT_CLOUD_JSON_SCHEMA),
+        (3, WIDGET_SCHEMA, "{\"type\":\"object\"}"),
+    ];
+    for (id, name, schema) in schemas {
+        out.record(OP_SCHEMA, |r| {
+            r.u16(id);
+            r.string(name);
+            r.string("jsonschema");
+            r.bytes_u32(schema.as_bytes());
+        })?;
+    }
+
+    let mut channels: Vec<OutChannel> = Vec::new();
+    for viz in recording.get_vizs() {
+        let info = serde_json::to_string(&VizInfo {
+            source: viz.source.clone(),
+            range: viz.range,
+            metadata: viz.metadata.clone(),
+        })?;
+        let mut channel = |schema: u16, precision: Precision, messages: Messages<'a>| {
+            channels.push(OutChannel {
+                topic: &viz.name,
+                schema,
+                info: info.clone(),
+                precision,
+                messages,
+            });
+        };
+        for widget in viz.widgets.iter() {
+            match widget {
+                Widget::PlotScalar(data) => {
+                    let messages = data.data_x.iter().map(|&(t, value)| {
+                        let payload: Payload = Box::new(move || scalar_message(value));
+                        (nanos(t), payload)
+                    });
+                    channel(1, data.precision, Box::new(messages));
+                }
+                Widget::ThreeDView(data) => {
+                    let keyframes = data.primatives.iter().filter_map(|(t, p)| match p {
+                        ThreeDPrimative::Point(_) => Some((*t, Precision::F64)),
+                        ThreeDPrimative::TypedPoint(buffer) => Some((*t, buffer.precision())),
+                        _ => None,
+                    });
+                    let mut first = None;
+                    for (t, precision) in keyframes {
+                        let (t0, common) = first.unwrap_or((t, precision));
+                        first = Some((t0, common.common(precision)));
+                    }
+                    if let Some((t0, precision)) = first {
+                        // Every delta is expanded into the cloud it produces; those
+                        // before the first keyframe have nothing to apply to
+                        let messages = data
+                            .primatives
+                            .iter()
+                            .filter(move |(t, p)| is_points(p) && *t >= t0)
+                            .map(move |&(t, _)| {
+                                let payload: Payload = Box::new(move || {
+                                    point_cloud_message(t, &data.points_at(t).unwrap_or_default())
+                                });
+                                (nanos(t), payload)
+                            });
+                        channel(2, precision, Box::new(messages));
+                    }
+                    let rest = data.primatives.iter().filter(|(_, p)| !is_points(p));
+                    if let Some((t, _)) = rest.clone().next() {
+                        let payload: Payload = Box::new(move || {
+                            let primatives = rest.cloned().collect();
+                            Ok(serde_json::to_vec(&Widget::ThreeDView(ThreeDViewData {
+                                primatives,
+                            }))?)
+                        });
+                        channel(
+                            3,
+                            Precision::F64,
+                            Box::new(std::iter::once((nanos(*t), payload))),
+                        );
+                    }
+                }
+                widget => {
+                    let t = widget.time_bounds().map_or(0.0, |(t0, _)| t0);
+                    let payload: Payload = Box::new(move || Ok(serde_json::to_vec(widget)?));
+                    channel(
+                        3,
+                        Precision::F64,
+                        Box::new(std::iter::once((nanos(t), payload))),
+                    );
+                }
+            }
+        }
+    }
+    for (i, channel) in channels.iter().enumerate() {
+        let precision = serde_json::to_string(&channel.precision)?;
+        let mut metadata = vec![(VIZ_METADATA, channel.info.as_str())];
+        if !channel.precision.is_f64() {
+            metadata.push((PRECISION_METADATA, precision.as_str()));
+        }
+        out.record(OP_CHANNEL, |r| {
+            r.u16(i as u16 + 1);
+            r.u16(channel.schema);
+            r.string(channel.topic);
+            r.string("json");
+            r.map(&metadata);
+        })?;
+    }
+    // Channels are merged in time order and each message is only serialized when it
+    // is written, so no more than one message is held in memory at a time
+    let mut next = BinaryHeap::new();
+    let mut payloads: Vec<Option<Payload>> = Vec::with_capacity(channels.len());
+    for (i, channel) in channels.iter_mut().enumerate() {
+        let message = channel.messages.next();
+        if let Some((time, _)) = &message {
+            next.push(Reverse((*time, i)));
+        }
+        payloads.push(message.map(|(_, payload)| payload));
+    }
+    let mut sequences = vec![0u32; channels.len()];
+    while let Some(Reverse((time, i))) = next.pop() {
+        let data = payloads[i].take().expect("queued channels have a payload")()?;
+        sequences[i] += 1;
+        out.record(OP_MESSAGE, |r| {
+            r.u16(i as u16 + 1);
+            r.u32(sequences[i]);
+            r.u64(time);
+            r.u64(time);
+            r.0.extend_from_slice(&data);
+        })?;
+        if let Some((time, payload)) = channels[i].messages.next() {
+            next.push(Reverse((time, i)));
+            payloads[i] = Some(payload);
+        }
+    }
+    // Zero CRCs and summary offsets mean "not written"
+    out.record(OP_DATA_END, |r| r.u32(0))?;
+    out.record(OP_FOOTER, |r| {
+        r.u64(0);
+        r.u64(0);
+        r.u32(0);
+    })?;
+    out.writer.write_all(MAGIC)?;
+    Ok(())
+}
+
+/// Messages of a channel as `(log time, payload)`.
+type Messages<'a> = Box<dyn Iterator<Item = (u64, Payload<'a>)> + 'a>;
+
+/// Serializes a message once it is written.
+type Payload<'a> = Box<dyn FnOnce() -> Result<Vec<u8>, anyhow::Error> + 'a>;
+
+struct OutChannel<'a> {
+    topic: &'a str,
+    schema: u16,
+    info: String,
+    precision: Precision,
+    messages: Messages<'a>,
+}
+
+fn is_points(primative: &ThreeDPrimative) -> bool {
+    matches!(
+        primative,
+        ThreeDPrimative::Point(_) | ThreeDPrimative::TypedPoint(_) | ThreeDPrimative::PointDelta(_)
+    )
+}
+
+fn scalar_message(value: f64) -> Result<Vec<u8>, anyhow::Error> {
+    Ok(serde_json::to_vec(
+        &serde_json::json!({ "value": NonFinite(value) }),
+    )?)
+}
+
+/// MCAP times are unsigned nanoseconds; earlier times are clamped to zero.
+fn nanos(t: f64) -> u64 {
+    (t.max(0.0) * 1e9).round() as u64
+}
+
+fn point_cloud_message(t: f64, points: &[(f64, f64, f64)]) -> Result<Vec<u8>, anyhow::Error> {
+    let mut data = Vec::with_capacity(points.len() * 24);
+    for &(x, y, z) in points {
+        for coord in [x, y, z] {
+            data.extend_from_slice(&coord.to_le_bytes());
+        }
+    }
+    let ns = nanos(t);
+    let message = serde_json::json!({
+        "timestamp": { "sec": ns / 1_000_000_000, "nsec": ns % 1_000_000_000 },
+        "frame_id": "",
+        "pose": {
+            "position": { "x": 0.0, "y": 0.0, "z": 0.0 },
+            "orientation": { "x": 0.0, "y": 0.0, "z": 0.0, "w": 1.0 },
+        },
+        "point_stride": 24,
+        // foxglove.NumericType FLOAT64
+        "fields": [
+            { "name": "x", "offset": 0, "type": 8 },
+            { "name": "y", "offset": 8, "type": 8 },
+            { "name": "z", "offset": 16, "type": 8 },
+        ],
+        "data": base64::engine::general_purpose::STANDARD.encode(data),
+    });
+    Ok(serde_json::to_vec(&message)?)
+}
+
+const SCALAR_JSON_SCHEMA: &str =
+    r#"{"type":"object","properties":{"value":{"type":["number","string"]}},"required":["value"]}"#;
+
+const POINT_CLOUD_JSON_SCHEMA: &str = r#"{"type":"object","properties":{"timestamp":{"type":"object","properties":{"sec":{"type":"integer"},"nsec":{"type":"integer"}}},"frame_id":{"type":"string"},"pose":{"type":"object","properties":{"position":{"type":"object","properties":{"x":{"type":"number"},"y":{"type":"number"},"z":{"type":"number"}}},"orientation":{"type":"object","properties":{"x":{"type":"number"},"y":{"type":"number"},"z":{"type":"number"},"w":{"type":"number"}}}}},"point_stride":{"type":"integer"},"fields":{"type":"array","items":{"type":"object","properties":{"name":{"type":"string"},"offset":{"type":"integer"},"type":{"type":"integer"}}}},"data":{"type":"string","contentEncoding":"base64"}}}"#;
+
+struct RecordWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> RecordWriter<W> {
+    fn record(&mut self, op: u8, build: impl FnOnce(&mut Content)) -> Result<(), anyhow::Error> {
+        let mut content = Content(Vec::new());
+        build(&mut content);
+        self.writer.write_all(&[op])?;
+        self.writer
+            .write_all(&(content.0.len() as u64).to_le_bytes())?;
+        self.writer.write_all(&content.0)?;
+        Ok(())
+    }
+
+    fn attachment(&mut self, name: &str, data: &[u8]) -> Result<(), anyhow::Error> {
+        self.record(OP_ATTACHMENT, |r| {
+            r.u64(0);
+            r.u64(0);
+            r.string(name);
+            r.string("application/json");
+            r.u64(data.len() as u64);
+            r.0.extend_from_slice(data);
+            r.u32(0);
+        })
+    }
+}
+
+/// Record content being built, in MCAP's little-endian encoding.
+struct Content(Vec<u8>);
+
+impl Content {
+    fn u16(&mut self, v: u16) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn string(&mut self, s: &str) {
+        self.bytes_u32(s.as_bytes());
+    }
+
+    fn bytes_u32(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn map(&mut self, entries: &[(&str, &str)]) {
+        let mut map = Content(Vec::new());
+        for (key, value) in entries {
+            map.string(key);
+            map.string(value);
+        }
+        self.bytes_u32(&map.0);
+    }
+}
+
+/// Reads record content.
+struct Cursor<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], anyhow::Error> {
+        if n > self.data.len() {
+            return Err(anyhow::anyhow!("Truncated MCAP record"));
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.data)
+    }
+
+    fn u8(&mut self) -> Result<u8, anyhow::Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, anyhow::Error> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, anyhow::Error> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, anyhow::Error> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn string(&mut self) -> Result<String, anyhow::Error> {
+        Ok(String::from_utf8_lossy(self.bytes_u32()?).into_owned())
+    }
+
+    fn bytes_u32(&mut self) -> Result<&'a [u8], anyhow::Error> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn bytes_u64(&mut self) -> Result<&'a [u8], anyhow::Error> {
+        let len = self.u64()? as usize;
+        self.take(len)
+    }
+
+    fn map(&mut self) -> Result<BTreeMap<String, String>, anyhow::Error> {
+        let mut entries = Cursor::new(self.bytes_u32()?);
+        let mut map = BTreeMap::new();
+        while !entries.is_empty() {
+            map.insert(entries.string()?, entries.string()?);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::{
+        occupancy::{GridOrigin, OccupancyGrid},
+        three_d_view::PointDelta,
+    };
+
+    fn plot(name: &str, data: PlotScalarData) -> Viz {
+        Viz::new(name.to_string()).with_widget(Widget::PlotScalar(data))
+    }
+
+    fn recording() -> Recording {
+        let mut recording = Recording::new("drive".to_string(), "run-7".to_string());
+        recording.set_tag("car", "blue");
+        recording.add_annotation(Annotation::instant(1.0, "stall").with_viz("speed"));
+        let speed = vec![
+            (0.0, 1.0),
+            (0.5, f64::NAN),
+            (1.0, f64::INFINITY),
+            (1.5, f64::NEG_INFINITY),
+            (2.0, -2.5),
+        ];
+        let mut speed = plot("speed", PlotScalarData::new(speed));
+        speed.source = Some("can".to_string());
+        speed.set_metadata("unit", "m/s");
+        recording.add_viz(speed);
+        let current = PlotScalarData::new(vec![(0.25, 0.3), (0.75, -1.7)])
+            .with_precision(Precision::i16_range(-2.0, 2.0));
+        recording.add_viz(plot("current", current));
+        let grid = OccupancyGrid::new(0.5, 2, 1, GridOrigin::default(), &[0, 100]).unwrap();
+        let lidar = ThreeDViewData {
+            primatives: vec![
+                (
+                    0.0,
+                    ThreeDPrimative::points(&[(1.0, 2.0, 3.0)], Precision::F32),
+                ),
+                (0.5, ThreeDPrimative::OccupancyGrid(grid)),
+                (
+                    1.0,
+                    ThreeDPrimative::PointDelta(PointDelta {
+                        len: 2,
+                        changed: vec![(1, (4.0, 5.0, 6.0))],
+                    }),
+                ),
+            ],
+        };
+        recording.add_viz(Viz::new("lidar".to_string()).with_widget(Widget::ThreeDView(lidar)));
+        recording
+    }
+
+    fn written(recording: &Recording) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_recording(&mut bytes, recording).unwrap();
+        bytes
+    }
+
+    fn plot_data<'a>(recording: &'a Recording, name: &str) -> &'a PlotScalarData {
+        match recording.get_viz(name).and_then(|viz| viz.widgets.first()) {
+            Some(Widget::PlotScalar(data)) => data,
+            other => panic!("{} is not a plot: {:?}", name, other),
+        }
+    }
+
+    #[test]
+    fn recordings_round_trip() {
+        let original = recording();
+        let read = read_recording(written(&original).as_slice(), "ignored").unwrap();
+        assert_eq!(read.name, "drive");
+        assert_eq!(read.session_id, "run-7");
+        assert_eq!(read.get_tag("car"), Some("blue"));
+        assert_eq!(read.annotations.len(), 1);
+
+        let speed = read.get_viz("speed").unwrap();
+        assert_eq!(speed.source.as_deref(), Some("can"));
+        assert_eq!(speed.get_metadata("unit"), Some(&serde_json::json!("m/s")));
+        let values: Vec<String> = plot_data(&read, "speed")
+            .data_x
+            .iter()
+            .map(|(_, v)| v.to_string())
+            .collect();
+        assert_eq!(values, ["1", "NaN", "inf", "-inf", "-2.5"]);
+
+        let current = plot_data(&read, "current");
+        let expected = plot_data(&original, "current");
+        assert_eq!(current.precision, expected.precision);
+        assert_eq!(current.data_x, expected.data_x);
+
+        let Some(Widget::ThreeDView(lidar)) = read.get_viz("lidar").unwrap().widgets.first() else {
+            panic!("lidar is not a 3D view");
+        };
+        assert!(matches!(
+            &lidar.primatives[0].1,
+            ThreeDPrimative::TypedPoint(buffer) if buffer.precision() == Precision::F32
+        ));
+        assert!(matches!(
+            lidar.primatives[1].1,
+            ThreeDPrimative::OccupancyGrid(_)
+        ));
+        assert_eq!(
+            lidar.points_at(1.0),
+            Some(vec![(1.0, 2.0, 3.0), (4.0, 5.0, 6.0)])
+        );
+    }
+
+    #[test]
+    fn messages_are_written_in_time_order() {
+        let mut times = Vec::new();
+        let mut channels = 0;
+        for_each_record(written(&recording()).as_slice(), &[], |op, content| {
+            match op {
+                OP_CHANNEL => channels += 1,
+                OP_MESSAGE => {
+                    assert_eq!(channels, 4, "channels come before messages");
+                    times.push(u64::from_le_bytes(content[6..14].try_into().unwrap()));
+                }
+                _ => {}
+            }
+            Ok(true)
+        })
+        .unwrap();
+        assert_eq!(times.len(), 10);
+        assert!(times.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn header_is_read_from_a_cut_off_file() {
+        let bytes = written(&recording());
+        let cut = &bytes[..bytes.len() / 2];
+        let header = read_header(cut, "ignored").unwrap();
+        assert_eq!(header.name, "drive");
+        assert!(read_recording(cut, "ignored").is_ok());
+        assert!(read_recording(&b"not mcap"[..], "ignored").is_err());
+    }
+
+    /// A file as another tool would write it: JSON messages in a chunk.
+    fn foreign_file(
+        compression: &str,
+        uncompressed_size: impl FnOnce(usize) -> u64,
+        channel_encoding: &str,
+    ) -> Vec<u8> {
+        let mut records = RecordWriter { writer: Vec::new() };
+        records
+            .record(OP_SCHEMA, |r| {
+                r.u16(1);
+                r.string("Odometry");
+                r.string("jsonschema");
+                r.bytes_u32(b"{}");
+            })
+            .unwrap();
+        records
+            .record(OP_CHANNEL, |r| {
+                r.u16(1);
+                r.u16(1);
+                r.string("/odom");
+                r.string(channel_encoding);
+                r.map(&[]);
+            })
+            .unwrap();
+        for (i, speed) in [1.0, 2.0].into_iter().enumerate() {
+            let message = serde_json::json!({ "speed": speed, "pose": { "x": i } });
+            records
+                .record(OP_MESSAGE, |r| {
+                    r.u16(1);
+                    r.u32(i as u32);
+                    r.u64(i as u64 * 1_000_000_000);
+                    r.u64(0);
+                    r.0.extend_from_slice(&serde_json::to_vec(&message).unwrap());
+                })
+                .unwrap();
+        }
+        let records = records.writer;
+        let compressed = match compression {
+            "zstd" => zstd::bulk::compress(&records, 0).unwrap(),
+            "lz4" => {
+                let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+                encoder.write_all(&records).unwrap();
+                encoder.finish().unwrap()
+            }
+            _ => records.clone(),
+        };
+        let mut file = RecordWriter { writer: Vec::new() };
+        file.writer.extend_from_slice(MAGIC);
+        file.record(OP_CHUNK, |r| {
+            r.u64(0);
+            r.u64(1_000_000_000);
+            r.u64(uncompressed_size(records.len()));
+            r.u32(0);
+            r.string(compression);
+            r.u64(compressed.len() as u64);
+            r.0.extend_from_slice(&compressed);
+        })
+        .unwrap();
+        file.record(OP_FOOTER, |r| {
+            r.u64(0);
+            r.u64(0);
+            r.u32(0);
+        })
+        .unwrap();
+        file.writer
+    }
+
+    #[test]
+    fn chunks_are_decompressed() {
+        for compression in ["", "zstd", "lz4"] {
+            let file = foreign_file(compression, |len| len as u64, "json");
+            let recording = read_recording(file.as_slice(), "odometry").unwrap();
+            assert_eq!(recording.name, "odometry");
+            assert_eq!(recording.get_tag(UNDECODED_TAG), None);
+            assert_eq!(
+                plot_data(&recording, "/odom.speed").data_x,
+                vec![(0.0, 1.0), (1.0, 2.0)]
+            );
+            assert_eq!(
+                plot_data(&recording, "/odom.pose.x").data_x,
+                vec![(0.0, 0.0), (1.0, 1.0)]
+            );
+        }
+    }
+
+    #[test]
+    fn chunk_sizes_are_only_a_limit() {
+        for compression in ["zstd", "lz4"] {
+            let huge = foreign_file(compression, |_| u64::MAX, "json");
+            assert!(read_recording(huge.as_slice(), "odometry").is_err());
+            let short = foreign_file(compression, |len| len as u64 - 1, "json");
+            assert!(read_recording(short.as_slice(), "odometry").is_err());
+        }
+    }
+
+    #[test]
+    fn undecodable_topics_are_tagged() {
+        let file = foreign_file("", |len| len as u64, "protobuf");
+        let recording = read_recording(file.as_slice(), "odometry").unwrap();
+        assert_eq!(recording.get_tag(UNDECODED_TAG), Some("/odom"));
+        assert!(recording.get_vizs().is_empty());
+    }
+
+    #[test]
+    fn cdr_channels_become_plots() {
+        let mut file = RecordWriter { writer: Vec::new() };
+        file.writer.extend_from_slice(MAGIC);
+        file.record(OP_SCHEMA, |r| {
+            r.u16(1);
+            r.string("nav_msgs/msg/Speed");
+            r.string("ros2msg");
+            r.bytes_u32(b"uint8 gear\nfloat64 speed\n");
+        })
+        .unwrap();
+        file.record(OP_CHANNEL, |r| {
+            r.u16(1);
+            r.u16(1);
+            r.string("/speed");
+            r.string("cdr");
+            r.map(&[]);
+        })
+        .unwrap();
+        file.record(OP_MESSAGE, |r| {
+            r.u16(1);
+            r.u32(0);
+            r.u64(500_000_000);
+            r.u64(0);
+            // Encapsulation header, gear, then speed aligned to 8 bytes
+            r.0.extend_from_slice(&[0, 1, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0]);
+            r.0.extend_from_slice(&7.5f64.to_le_bytes());
+        })
+        .unwrap();
+        let recording = read_recording(file.writer.as_slice(), "speed").unwrap();
+        assert_eq!(
+            plot_data(&recording, "/speed.gear").data_x,
+            vec![(0.5, 3.0)]
+        );
+        assert_eq!(
+            plot_data(&recording, "/speed.speed").data_x,
+            vec![(0.5, 7.5)]
+        );
+    }
+}
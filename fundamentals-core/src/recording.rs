@@ -1,17 +1,100 @@
 use std::{
-    fs::File,
-    io::{BufReader, BufWriter},
-    path::PathBuf,
+    collections::BTreeMap,
+    io::Read,
+    path::{Path, PathBuf},
+    str::FromStr,
 };
 
-use crate::viz::Viz;
-use serde::{Deserialize, Serialize};
+use crate::{
+    annotation::Annotation,
+    assets::{Asset, AssetTable},
+    compression::{self, CompressedWriter, Compression},
+    format::{self, Format},
+    mcap,
+    store::VizStore,
+    viz::Viz,
+    widgets::LatestValue,
+};
+use serde::{
+    de::{DeserializeSeed, IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+
+/// Most recent value of every widget of a viz at the queried time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatestAt {
+    pub viz: String,
+    pub values: Vec<LatestValue>,
+}
+
+/// What `Recording::merge` does with a viz whose name already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergePolicy {
+    /// Merge both series into one, by time, see `Viz::combine`.
+    #[default]
+    Combine,
+    /// Keep the existing viz and drop the incoming one.
+    KeepExisting,
+    /// Replace the existing viz with the incoming one.
+    Replace,
+    /// Keep both, suffixing the incoming viz name with its source session id.
+    Rename,
+}
+
+impl FromStr for MergePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "combine" => Ok(MergePolicy::Combine),
+            "keep_existing" | "keep-existing" => Ok(MergePolicy::KeepExisting),
+            "replace" => Ok(MergePolicy::Replace),
+            "rename" => Ok(MergePolicy::Rename),
+            _ => Err(anyhow::anyhow!("Unknown merge policy {}", s)),
+        }
+    }
+}
+
+/// Where and how a recording was produced.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecordingMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Wall-clock start time in seconds since the unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command_line: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
 
+/// Identity and metadata of a recording without its vizs, for cheap listings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingHeader {
+    pub name: String,
+    pub session_id: String,
+    #[serde(default)]
+    pub metadata: RecordingMetadata,
+}
+
+// Header fields come first so `load_header` can stop before the vizs
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Recording {
     pub name: String,
     pub session_id: String,
-    pub vizs: Vec<Viz>,
+    #[serde(default)]
+    pub metadata: RecordingMetadata,
+    pub vizs: VizStore,
+    /// Event markers, sorted by start time.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    /// Meshes, textures and static point clouds referenced by id from vizs.
+    #[serde(default, skip_serializing_if = "AssetTable::is_empty")]
+    pub assets: AssetTable,
 }
 
 impl Recording {
@@ -19,29 +102,559 @@ impl Recording {
         Self {
             name,
             session_id,
-            vizs: Vec::new(),
+            vizs: VizStore::new(),
+            metadata: RecordingMetadata::default(),
+            annotations: Vec::new(),
+            assets: AssetTable::new(),
         }
     }
 
+    pub fn set_tag(&mut self, key: &str, value: &str) {
+        self.metadata
+            .tags
+            .insert(key.to_string(), value.to_string());
+    }
+
+    pub fn get_tag(&self, key: &str) -> Option<&str> {
+        self.metadata.tags.get(key).map(String::as_str)
+    }
+
+    pub fn add_annotation(&mut self, annotation: Annotation) {
+        let index = self
+            .annotations
+            .partition_point(|a| a.start() <= annotation.start());
+        self.annotations.insert(index, annotation);
+    }
+
+    /// Annotations overlapping `t0..=t1`.
+    pub fn annotations_in(&self, t0: f64, t1: f64) -> impl Iterator<Item = &Annotation> {
+        self.annotations.iter().filter(move |a| a.overlaps(t0, t1))
+    }
+
+    /// Annotations that apply to the named viz.
+    pub fn annotations_for<'a>(&'a self, viz: &'a str) -> impl Iterator<Item = &'a Annotation> {
+        self.annotations.iter().filter(move |a| a.applies_to(viz))
+    }
+
+    /// Stores an asset, returning the id primitives use to refer to it.
+    pub fn add_asset(&mut self, asset: Asset) -> String {
+        self.assets.insert(asset)
+    }
+
+    pub fn get_asset(&self, id: &str) -> Option<&Asset> {
+        self.assets.get(id)
+    }
+
+    /// Adds a viz, merging it into an existing viz of the same name.
     pub fn add_viz(&mut self, viz: Viz) {
-        self.vizs.push(viz);
+        self.vizs.insert(viz);
     }
 
-    pub fn get_vizs(&self) -> &Vec<Viz> {
+    pub fn get_vizs(&self) -> &[Viz] {
         &self.vizs
     }
 
-    pub fn save_to_file(&self, path: &PathBuf) -> Result<(), anyhow::Error> {
-        let file = File::create(path)?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer(writer, self)?;
+    pub fn get_viz(&self, name: &str) -> Option<&Viz> {
+        self.vizs.get(name)
+    }
+
+    /// Applies `f` to the named viz, see `VizStore::update`.
+    pub fn update_viz<R>(&mut self, name: &str, f: impl FnOnce(&mut Viz) -> R) -> Option<R> {
+        self.vizs.update(name, f)
+    }
+
+    /// First and last timestamp over all vizs.
+    pub fn time_bounds(&self) -> Option<(f64, f64)> {
+        self.vizs
+            .iter()
+            .filter_map(|viz| viz.time_bounds())
+            .reduce(|(a0, a1), (b0, b1)| (a0.min(b0), a1.max(b1)))
+    }
+
+    /// Adds every viz of `other` to this recording, resolving name clashes with
+    /// `policy`. Vizs without a source are tagged with the session id of the
+    /// recording they came from. The session id of this recording is kept; give the
+    /// result a new one if it is saved as a recording of its own.
+    ///
+    /// Fails, leaving this recording's data unchanged, if `policy` combines vizs
+    /// that `Viz::combine` refuses.
+    pub fn merge(
+        &mut self,
+        mut other: Recording,
+        policy: MergePolicy,
+    ) -> Result<(), anyhow::Error> {
+        self.tag_sources();
+        other.tag_sources();
+        if policy == MergePolicy::Combine {
+            for viz in other.vizs.iter() {
+                if let Some(existing) = self.vizs.get(&viz.name) {
+                    existing.check_combine(viz)?;
+                }
+            }
+        }
+        for (key, value) in other.metadata.tags {
+            self.metadata.tags.entry(key).or_insert(value);
+        }
+        self.assets.extend(other.assets);
+        let session_id = other.session_id.clone();
+        let mut renamed = BTreeMap::new();
+        for mut viz in other.vizs.into_vec() {
+            if !self.vizs.contains(&viz.name) {
+                self.vizs.insert(viz);
+                continue;
+            }
+            match policy {
+                MergePolicy::Combine => self.vizs.combine(viz)?,
+                MergePolicy::KeepExisting => {}
+                MergePolicy::Replace => self.vizs.replace(viz),
+                MergePolicy::Rename => {
+                    let name = format!("{} ({})", viz.name, session_id);
+                    renamed.insert(std::mem::replace(&mut viz.name, name.clone()), name);
+                    self.vizs.insert(viz);
+                }
+            }
+        }
+        for mut annotation in other.annotations {
+            // Scopes follow renamed vizs
+            for viz in annotation.vizs.iter_mut() {
+                if let Some(name) = renamed.get(viz) {
+                    *viz = name.clone();
+                }
+            }
+            if !self.annotations.contains(&annotation) {
+                self.add_annotation(annotation);
+            }
+        }
         Ok(())
     }
 
+    /// Appends a later run: every timestamp of `other` is shifted by `offset` and the
+    /// series are combined with this recording's.
+    pub fn concat(&mut self, mut other: Recording, offset: f64) -> Result<(), anyhow::Error> {
+        other.shift_time(offset);
+        self.merge(other, MergePolicy::Combine)
+    }
+
+    /// Adds `offset` to every timestamp of every viz.
+    pub fn shift_time(&mut self, offset: f64) {
+        self.vizs.update_all(|viz| viz.shift_time(offset));
+        for annotation in self.annotations.iter_mut() {
+            annotation.shift_time(offset);
+        }
+    }
+
+    fn tag_sources(&mut self) {
+        let session_id = &self.session_id;
+        self.vizs.update_all(|viz| {
+            if viz.source.is_none() {
+                viz.set_source(session_id.clone());
+            }
+        });
+    }
+
+    /// Every viz with its data restricted to `t0 <= time <= t1`.
+    pub fn query_range(&self, t0: f64, t1: f64) -> Vec<Viz> {
+        self.vizs.iter().map(|viz| viz.slice(t0, t1)).collect()
+    }
+
+    /// The named viz restricted to `t0 <= time <= t1`.
+    pub fn query_viz_range(&self, name: &str, t0: f64, t1: f64) -> Option<Viz> {
+        self.get_viz(name).map(|viz| viz.slice(t0, t1))
+    }
+
+    /// Most recent value or primitive per viz at time `t`. Vizs with no data at or
+    /// before `t` are omitted.
+    pub fn latest_at(&self, t: f64) -> Vec<LatestAt> {
+        self.vizs
+            .iter()
+            .map(|viz| LatestAt {
+                viz: viz.name.clone(),
+                values: viz.widgets.iter().filter_map(|w| w.latest_at(t)).collect(),
+            })
+            .filter(|latest| !latest.values.is_empty())
+            .collect()
+    }
+
+    /// Writes the recording in the format named by the extension (`.json`,
+    /// `.msgpack`, `.cbor` or `.mcap`, JSON if unknown), compressed with zstd or lz4 when the
+    /// path ends in `.zst` or `.lz4`. Compression is streamed, so no uncompressed
+    /// copy is held in memory.
+    pub fn save_to_file(&self, path: &PathBuf) -> Result<(), anyhow::Error> {
+        self.save_to_file_as(path, Format::from_path(path).unwrap_or_default())
+    }
+
+    pub fn save_to_file_as(&self, path: &PathBuf, format: Format) -> Result<(), anyhow::Error> {
+        let mut writer = CompressedWriter::create(path)?;
+        match format {
+            Format::Mcap => mcap::write_recording(&mut writer, self)?,
+            format => format.write(&mut writer, self)?,
+        }
+        writer.finish()
+    }
+
+    /// Reads only the name, session id and metadata of a recording file. Reading
+    /// stops once they are found, which is before the viz data in files written
+    /// since the header fields were moved to the front.
+    pub fn load_header(path: &PathBuf) -> Result<RecordingHeader, anyhow::Error> {
+        let (format, reader) = format::open(path)?;
+        match format {
+            Format::Mcap => mcap::read_header(reader, &file_name(path)),
+            format => read_header(format, reader),
+        }
+    }
+
+    /// Reads a recording written by `save_to_file`. Compression and format are
+    /// detected from the content, see `Format::detect`.
     pub fn load_from_file(path: &PathBuf) -> Result<Self, anyhow::Error> {
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        let recording: Self = serde_json::from_reader(reader)?;
-        Ok(recording)
+        let (format, reader) = format::open(path)?;
+        Self::read_as(reader, format, path)
+    }
+
+    pub fn load_from_file_as(path: &PathBuf, format: Format) -> Result<Self, anyhow::Error> {
+        Self::read_as(compression::open(path)?, format, path)
+    }
+
+    fn read_as(reader: impl Read, format: Format, path: &Path) -> Result<Self, anyhow::Error> {
+        match format {
+            // MCAP files from other tools carry no recording name
+            Format::Mcap => mcap::read_recording(reader, &file_name(path)),
+            format => format.read(reader),
+        }
+    }
+}
+
+fn read_header(format: Format, reader: impl Read) -> Result<RecordingHeader, anyhow::Error> {
+    let mut header = None;
+    // The formats complain about the unread rest of the map once the visitor
+    // returns early, so the header is taken from `header` whatever the result
+    let result = match format {
+        Format::Json => HeaderSeed(&mut header)
+            .deserialize(&mut serde_json::Deserializer::from_reader(reader))
+            .map_err(anyhow::Error::from),
+        Format::MessagePack => HeaderSeed(&mut header)
+            .deserialize(&mut rmp_serde::Deserializer::new(reader))
+            .map_err(anyhow::Error::from),
+        // Takes no seed, but does not mind the early return either
+        Format::Cbor => ciborium::from_reader::<HeaderProbe, _>(reader)
+            .map(|probe| header = probe.0)
+            .map_err(anyhow::Error::from),
+        Format::Mcap => unreachable!("MCAP headers are read by the mcap module"),
+    };
+    match (header, result) {
+        (Some(header), _) => Ok(header),
+        (None, Err(e)) => Err(e),
+        (None, Ok(())) => Err(anyhow::anyhow!("Recording has no name or session id")),
+    }
+}
+
+struct HeaderSeed<'a>(&'a mut Option<RecordingHeader>);
+
+impl<'de> DeserializeSeed<'de> for HeaderSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(HeaderVisitor(self.0))
+    }
+}
+
+struct HeaderProbe(Option<RecordingHeader>);
+
+impl<'de> Deserialize<'de> for HeaderProbe {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut header = None;
+        HeaderSeed(&mut header).deserialize(deserializer)?;
+        Ok(HeaderProbe(header))
+    }
+}
+
+struct HeaderVisitor<'a>(&'a mut Option<RecordingHeader>);
+
+impl<'de> Visitor<'de> for HeaderVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a recording")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let (mut name, mut session_id, mut metadata) = (None, None, None);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "name" => name = Some(map.next_value()?),
+                "session_id" => session_id = Some(map.next_value()?),
+                "metadata" => metadata = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+            if name.is_some() && session_id.is_some() && metadata.is_some() {
+                break;
+            }
+        }
+        if let (Some(name), Some(session_id)) = (name, session_id) {
+            *self.0 = Some(RecordingHeader {
+                name,
+                session_id,
+                metadata: metadata.unwrap_or_default(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// `path`'s file name without format and compression extensions.
+fn file_name(path: &Path) -> String {
+    Compression::strip_extension(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::{
+        plot_scalar::PlotScalarData,
+        precision::Precision,
+        three_d_view::{PointDelta, ThreeDPrimative, ThreeDViewData},
+        LatestValue, Widget,
+    };
+
+    fn plot(name: &str, data: Vec<(f64, f64)>) -> Viz {
+        Viz::new(name.to_string()).with_widget(Widget::PlotScalar(PlotScalarData::new(data)))
+    }
+
+    #[test]
+    fn rename_policy_moves_annotation_scopes() {
+        let mut first = Recording::new("a".to_string(), "run-a".to_string());
+        first.add_viz(plot("speed", vec![(0.0, 1.0)]));
+        let mut second = Recording::new("b".to_string(), "run-b".to_string());
+        second.add_viz(plot("speed", vec![(0.0, 2.0)]));
+        second.add_annotation(Annotation::instant(0.0, "stall").with_viz("speed"));
+
+        first.merge(second, MergePolicy::Rename).unwrap();
+        assert!(first.get_viz("speed (run-b)").is_some());
+        assert_eq!(first.annotations[0].vizs, vec!["speed (run-b)".to_string()]);
+        assert_eq!(
+            first.get_viz("speed").unwrap().source.as_deref(),
+            Some("run-a")
+        );
+    }
+
+    fn scalars(viz: &Viz) -> &[(f64, f64)] {
+        match &viz.widgets[0] {
+            Widget::PlotScalar(data) => &data.data_x,
+            _ => panic!("not a scalar plot"),
+        }
+    }
+
+    #[test]
+    fn combine_keeps_samples_of_both_runs_at_equal_times() {
+        let mut first = Recording::new("a".to_string(), "run-a".to_string());
+        first.add_viz(plot("speed", vec![(0.0, 1.0), (1.0, 1.0)]));
+        let mut second = Recording::new("b".to_string(), "run-b".to_string());
+        second.add_viz(plot("speed", vec![(1.0, 2.0), (2.0, 2.0)]));
+
+        first.merge(second, MergePolicy::Combine).unwrap();
+        let speed = first.get_viz("speed").unwrap();
+        assert_eq!(
+            scalars(speed),
+            [(0.0, 1.0), (1.0, 1.0), (1.0, 2.0), (2.0, 2.0)]
+        );
+        assert_eq!(speed.source.as_deref(), Some("run-a,run-b"));
+
+        // Merging a run again replaces its samples instead of duplicating them
+        let mut again = Recording::new("b".to_string(), "run-b".to_string());
+        again.add_viz(plot("speed", vec![(1.0, 3.0)]));
+        first.merge(again, MergePolicy::Combine).unwrap();
+        let speed = first.get_viz("speed").unwrap();
+        assert_eq!(scalars(speed), [(0.0, 1.0), (1.0, 3.0), (2.0, 2.0)]);
+    }
+
+    #[test]
+    fn combine_refuses_overlapping_3d_views_of_two_runs() {
+        let view = |times: &[f64]| {
+            let primatives = times
+                .iter()
+                .map(|&t| (t, ThreeDPrimative::points(&[(t, 0.0, 0.0)], Precision::F64)))
+                .collect();
+            Viz::new("cloud".to_string())
+                .with_widget(Widget::ThreeDView(ThreeDViewData { primatives }))
+        };
+        let mut first = Recording::new("a".to_string(), "run-a".to_string());
+        first.add_viz(view(&[0.0, 2.0]));
+        first.add_viz(plot("speed", vec![(0.0, 1.0)]));
+        let mut second = Recording::new("b".to_string(), "run-b".to_string());
+        second.add_viz(view(&[1.0, 3.0]));
+        second.add_viz(plot("speed", vec![(0.0, 2.0)]));
+
+        let error = first
+            .clone()
+            .merge(second.clone(), MergePolicy::Combine)
+            .unwrap_err();
+        assert!(error.to_string().contains("cloud"), "{}", error);
+        // Nothing was merged
+        let before = first.clone();
+        assert!(first.merge(second.clone(), MergePolicy::Combine).is_err());
+        assert_eq!(
+            scalars(first.get_viz("speed").unwrap()),
+            scalars(before.get_viz("speed").unwrap())
+        );
+
+        // A later run can follow, and the other policies do not combine
+        first.clone().concat(second.clone(), 10.0).unwrap();
+        first.merge(second, MergePolicy::Rename).unwrap();
+    }
+
+    fn moving_cloud() -> Viz {
+        let primatives = (0..4)
+            .map(|i| {
+                let cloud = vec![(i as f64, 0.0, 0.0), (0.0, 0.0, 0.0)];
+                let primative = if i == 0 {
+                    ThreeDPrimative::Point(cloud)
+                } else {
+                    ThreeDPrimative::PointDelta(PointDelta::between(
+                        &[((i - 1) as f64, 0.0, 0.0), (0.0, 0.0, 0.0)],
+                        &cloud,
+                        0.0,
+                    ))
+                };
+                (i as f64, primative)
+            })
+            .collect();
+        Viz::new("cloud".to_string()).with_widget(Widget::ThreeDView(ThreeDViewData { primatives }))
+    }
+
+    #[test]
+    fn query_range_restricts_every_viz() {
+        let mut recording = Recording::new("drive".to_string(), "run-7".to_string());
+        recording.add_viz(plot("speed", (0..10).map(|i| (i as f64, 1.0)).collect()));
+        recording.add_viz(plot("late", vec![(8.0, 1.0)]));
+        recording.add_viz(moving_cloud());
+
+        let vizs = recording.query_range(2.0, 4.0);
+        assert_eq!(vizs.len(), 3);
+        let viz = |name: &str| vizs.iter().find(|viz| viz.name == name).unwrap();
+        assert_eq!(scalars(viz("speed")), [(2.0, 1.0), (3.0, 1.0), (4.0, 1.0)]);
+        assert!(scalars(viz("late")).is_empty());
+        // The cloud starts mid-delta, so its state at t0 is rebuilt
+        let Widget::ThreeDView(cloud) = &viz("cloud").widgets[0] else {
+            panic!("not a 3D view");
+        };
+        assert!(matches!(
+            cloud.primatives[0],
+            (2.0, ThreeDPrimative::Point(_))
+        ));
+        assert_eq!(
+            cloud.points_at(3.0),
+            Some(vec![(3.0, 0.0, 0.0), (0.0, 0.0, 0.0)])
+        );
+
+        assert_eq!(
+            scalars(&recording.query_viz_range("speed", 8.5, 20.0).unwrap()),
+            [(9.0, 1.0)]
+        );
+        assert!(recording.query_viz_range("missing", 0.0, 1.0).is_none());
+    }
+
+    #[test]
+    fn latest_at_skips_vizs_without_data_yet() {
+        let mut recording = Recording::new("drive".to_string(), "run-7".to_string());
+        recording.add_viz(plot("speed", vec![(0.0, 1.0), (2.0, 2.0)]));
+        recording.add_viz(plot("late", vec![(8.0, 1.0)]));
+        recording.add_viz(moving_cloud());
+
+        let latest = recording.latest_at(2.5);
+        let mut names: Vec<_> = latest.iter().map(|latest| latest.viz.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["cloud", "speed"]);
+        for latest in latest.iter() {
+            match &latest.values[..] {
+                [LatestValue::PlotScalar(x, y)] => assert_eq!((*x, *y), (2.0, 2.0)),
+                // Deltas are returned as the full cloud they produce
+                [LatestValue::ThreeDView(t, ThreeDPrimative::Point(points))] => {
+                    assert_eq!(*t, 2.0);
+                    assert_eq!(points, &[(2.0, 0.0, 0.0), (0.0, 0.0, 0.0)]);
+                }
+                values => panic!("unexpected values for {}: {:?}", latest.viz, values),
+            }
+        }
+        assert!(recording.latest_at(-1.0).is_empty());
+    }
+
+    fn tagged_recording() -> Recording {
+        let mut recording = Recording::new("drive".to_string(), "run-7".to_string());
+        recording.set_tag("car", "blue");
+        recording.add_viz(plot("speed", (0..100).map(|i| (i as f64, 1.0)).collect()));
+        recording
+    }
+
+    fn assert_header(header: RecordingHeader) {
+        assert_eq!(header.name, "drive");
+        assert_eq!(header.session_id, "run-7");
+        assert_eq!(
+            header.metadata.tags.get("car").map(String::as_str),
+            Some("blue")
+        );
+    }
+
+    #[test]
+    fn header_is_read_from_every_format() {
+        for format in [Format::Json, Format::MessagePack, Format::Cbor] {
+            let mut bytes = Vec::new();
+            format.write(&mut bytes, &tagged_recording()).unwrap();
+            assert_header(read_header(format, bytes.as_slice()).unwrap());
+        }
+    }
+
+    #[test]
+    fn header_stops_before_the_vizs() {
+        for format in [Format::Json, Format::MessagePack, Format::Cbor] {
+            let mut bytes = Vec::new();
+            format.write(&mut bytes, &tagged_recording()).unwrap();
+            // Cut off in the middle of the viz data
+            bytes.truncate(bytes.len() / 2);
+            assert_header(read_header(format, bytes.as_slice()).unwrap());
+        }
+    }
+
+    #[test]
+    fn header_is_read_from_old_field_order() {
+        let json = serde_json::json!({
+            "vizs": serde_json::to_value(&tagged_recording().vizs).unwrap(),
+            "metadata": {"tags": {"car": "blue"}},
+            "name": "drive",
+            "session_id": "run-7",
+        });
+        let bytes = serde_json::to_vec(&json).unwrap();
+        assert_header(read_header(Format::Json, bytes.as_slice()).unwrap());
+
+        let missing = br#"{"name": "drive", "vizs": {}}"#;
+        assert!(read_header(Format::Json, missing.as_slice()).is_err());
+    }
+
+    #[test]
+    fn compressed_recordings_round_trip() {
+        let dir =
+            std::env::temp_dir().join(format!("fundamentals-recording-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["drive.json.zst", "drive.msgpack.lz4", "drive.cbor.zst"] {
+            let path = dir.join(name);
+            tagged_recording().save_to_file(&path).unwrap();
+            assert!(!matches!(
+                Compression::detect(&std::fs::read(&path).unwrap()),
+                Compression::None
+            ));
+            let recording = Recording::load_from_file(&path).unwrap();
+            assert_eq!(recording.vizs.len(), 1);
+            let Some(Widget::PlotScalar(data)) =
+                recording.get_viz("speed").unwrap().widgets.first()
+            else {
+                panic!("speed plot is missing from {}", name);
+            };
+            assert_eq!(data.data_x.len(), 100);
+            assert!(data.data_x.iter().all(|(_, y)| *y == 1.0));
+            assert_header(Recording::load_header(&path).unwrap());
+        }
     }
 }
@@ -0,0 +1,175 @@
+use std::{collections::BTreeMap, ops::Deref};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::viz::Viz;
+
+/// Vizs of a recording, indexed by name.
+///
+/// Inserting a viz whose name is already present merges its widget data into the
+/// existing series instead of adding a second entry, so logging the same
+/// `Plotter` twice yields one time-sorted series. Serializes as a plain list of
+/// vizs, so files written before the index existed still load.
+#[derive(Debug, Clone, Default)]
+pub struct VizStore {
+    vizs: Vec<Viz>,
+    index: BTreeMap<String, usize>,
+}
+
+impl VizStore {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds `viz`, merging it into an existing viz with the same name.
+    pub fn insert(&mut self, mut viz: Viz) {
+        match self.index.get(&viz.name) {
+            Some(&i) => self.vizs[i].merge(viz),
+            None => {
+                viz.sort_by_time();
+                self.index.insert(viz.name.clone(), self.vizs.len());
+                self.vizs.push(viz);
+            }
+        }
+    }
+
+    /// Adds `viz`, combining it with an existing viz of the same name, see
+    /// `Viz::combine`.
+    pub fn combine(&mut self, viz: Viz) -> Result<(), anyhow::Error> {
+        match self.index.get(&viz.name) {
+            Some(&i) => self.vizs[i].combine(viz),
+            None => {
+                self.insert(viz);
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds `viz` without merging, replacing any existing viz with the same name.
+    pub fn replace(&mut self, mut viz: Viz) {
+        viz.sort_by_time();
+        match self.index.get(&viz.name) {
+            Some(&i) => self.vizs[i] = viz,
+            None => {
+                self.index.insert(viz.name.clone(), self.vizs.len());
+                self.vizs.push(viz);
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Viz> {
+        self.index.get(name).map(|&i| &self.vizs[i])
+    }
+
+    /// Applies `f` to the named viz. If `f` renames it, the index follows, merging
+    /// it into a viz that already has the new name.
+    pub fn update<R>(&mut self, name: &str, f: impl FnOnce(&mut Viz) -> R) -> Option<R> {
+        let i = *self.index.get(name)?;
+        let result = f(&mut self.vizs[i]);
+        self.reindex();
+        Some(result)
+    }
+
+    /// Applies `f` to every viz, keeping the index in step as `update` does.
+    pub fn update_all(&mut self, f: impl FnMut(&mut Viz)) {
+        self.vizs.iter_mut().for_each(f);
+        self.reindex();
+    }
+
+    fn reindex(&mut self) {
+        let renamed = self
+            .vizs
+            .iter()
+            .enumerate()
+            .any(|(i, viz)| self.index.get(&viz.name) != Some(&i));
+        if renamed || self.index.len() != self.vizs.len() {
+            *self = std::mem::take(&mut self.vizs).into_iter().collect();
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.index.contains_key(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Viz> {
+        let i = self.index.remove(name)?;
+        let viz = self.vizs.remove(i);
+        for position in self.index.values_mut() {
+            if *position > i {
+                *position -= 1;
+            }
+        }
+        Some(viz)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.vizs.iter().map(|viz| viz.name.as_str())
+    }
+
+    pub fn into_vec(self) -> Vec<Viz> {
+        self.vizs
+    }
+}
+
+impl Deref for VizStore {
+    type Target = [Viz];
+
+    fn deref(&self) -> &Self::Target {
+        &self.vizs
+    }
+}
+
+impl FromIterator<Viz> for VizStore {
+    fn from_iter<I: IntoIterator<Item = Viz>>(iter: I) -> Self {
+        let mut store = Self::new();
+        for viz in iter {
+            store.insert(viz);
+        }
+        store
+    }
+}
+
+impl From<Vec<Viz>> for VizStore {
+    fn from(vizs: Vec<Viz>) -> Self {
+        vizs.into_iter().collect()
+    }
+}
+
+impl Serialize for VizStore {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.vizs.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for VizStore {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Viz>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::{plot_scalar::PlotScalarData, Widget};
+
+    fn plot(name: &str, data: Vec<(f64, f64)>) -> Viz {
+        Viz::new(name.to_string()).with_widget(Widget::PlotScalar(PlotScalarData::new(data)))
+    }
+
+    #[test]
+    fn renaming_keeps_index_in_step() {
+        let mut store: VizStore =
+            vec![plot("a", vec![(0.0, 1.0)]), plot("b", vec![(1.0, 2.0)])].into();
+        store.update("a", |viz| viz.name = "c".to_string());
+        assert!(!store.contains("a"));
+        assert_eq!(store.get("c").map(|viz| viz.name.as_str()), Some("c"));
+
+        // Renaming onto an existing name merges the two
+        store.update("c", |viz| viz.name = "b".to_string());
+        assert_eq!(store.len(), 1);
+        let Widget::PlotScalar(data) = &store.get("b").unwrap().widgets[0] else {
+            panic!("not a plot");
+        };
+        assert_eq!(data.data_x, vec![(0.0, 1.0), (1.0, 2.0)]);
+    }
+}
@@ -0,0 +1,403 @@
+use serde::{Deserialize, Serialize};
+
+use crate::widgets::{
+    histogram::{BinSpec, HistogramBins},
+    plot_scalar::PlotScalarData,
+};
+
+/// Most bins a `Histogram` transform may ask for.
+pub const MAX_HISTOGRAM_BINS: usize = 10_000;
+/// Most samples a `Resample` transform may produce.
+pub const MAX_RESAMPLED_POINTS: usize = 1_000_000;
+
+/// A derived series computed from a scalar plot.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    Derivative,
+    /// Running integral from the first sample (trapezoidal rule).
+    Integral,
+    /// Centered mean over `window` samples.
+    MovingAverage {
+        window: usize,
+    },
+    /// Centered median over `window` samples.
+    MovingMedian {
+        window: usize,
+    },
+    /// Linearly interpolated samples at a uniform `rate` in Hz.
+    Resample {
+        rate: f64,
+    },
+    /// Single-sided amplitude spectrum: (frequency in Hz, magnitude).
+    FftMagnitude,
+    /// Sample count per value bin: (bin center, count).
+    Histogram {
+        bins: usize,
+    },
+}
+
+impl Transform {
+    pub fn apply(&self, data: &PlotScalarData) -> PlotScalarData {
+        let samples = &data.data_x;
+        PlotScalarData::new(match *self {
+            Transform::Derivative => derivative(samples),
+            Transform::Integral => integral(samples),
+            Transform::MovingAverage { window } => moving_average(samples, window),
+            Transform::MovingMedian { window } => moving_median(samples, window),
+            Transform::Resample { rate } => resample(samples, rate),
+            Transform::FftMagnitude => fft_magnitude(samples),
+            Transform::Histogram { bins } => histogram(samples, bins),
+        })
+    }
+
+    /// Checks the parameters against the series they will be applied to, so a
+    /// request cannot ask for more memory than the result could need.
+    pub fn validate(&self, data: &PlotScalarData) -> Result<(), anyhow::Error> {
+        let samples = data.data_x.len();
+        match *self {
+            Transform::MovingAverage { window } | Transform::MovingMedian { window } => {
+                if window == 0 || window > samples.max(1) {
+                    anyhow::bail!("Window must be between 1 and {} samples", samples.max(1));
+                }
+            }
+            Transform::Resample { rate } => {
+                if !(rate > 0.0 && rate.is_finite()) {
+                    anyhow::bail!("Resample rate must be positive, got {}", rate);
+                }
+                if resample_count(&data.data_x, rate).is_none() {
+                    anyhow::bail!(
+                        "Resampling at {} Hz gives more than {} samples",
+                        rate,
+                        MAX_RESAMPLED_POINTS
+                    );
+                }
+            }
+            Transform::Histogram { bins } => {
+                if bins == 0 || bins > MAX_HISTOGRAM_BINS {
+                    anyhow::bail!("Bins must be between 1 and {}", MAX_HISTOGRAM_BINS);
+                }
+            }
+            Transform::Derivative | Transform::Integral | Transform::FftMagnitude => {}
+        }
+        Ok(())
+    }
+
+    /// Short name used to label derived vizs, e.g. `moving_average(10)`.
+    pub fn label(&self) -> String {
+        match self {
+            Transform::Derivative => "derivative".to_string(),
+            Transform::Integral => "integral".to_string(),
+            Transform::MovingAverage { window } => format!("moving_average({})", window),
+            Transform::MovingMedian { window } => format!("moving_median({})", window),
+            Transform::Resample { rate } => format!("resample({} Hz)", rate),
+            Transform::FftMagnitude => "fft".to_string(),
+            Transform::Histogram { bins } => format!("histogram({})", bins),
+        }
+    }
+}
+
+/// Summary statistics of the y values of a series.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Summary {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Population standard deviation.
+    pub stddev: f64,
+}
+
+impl Summary {
+    pub fn of(data: &[(f64, f64)]) -> Self {
+        if data.is_empty() {
+            return Self::default();
+        }
+        let n = data.len() as f64;
+        let mean = data.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let variance = data.iter().map(|(_, y)| (y - mean).powi(2)).sum::<f64>() / n;
+        Self {
+            count: data.len(),
+            min: data.iter().map(|(_, y)| *y).fold(f64::INFINITY, f64::min),
+            max: data
+                .iter()
+                .map(|(_, y)| *y)
+                .fold(f64::NEG_INFINITY, f64::max),
+            mean,
+            stddev: variance.sqrt(),
+        }
+    }
+}
+
+/// Finite-difference derivative, placed at the midpoint of each pair of samples.
+pub fn derivative(data: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    data.windows(2)
+        .filter(|w| w[1].0 > w[0].0)
+        .map(|w| {
+            let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+            ((x0 + x1) / 2.0, (y1 - y0) / (x1 - x0))
+        })
+        .collect()
+}
+
+pub fn integral(data: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut total = 0.0;
+    let mut integrated = Vec::with_capacity(data.len());
+    for (i, &(x, y)) in data.iter().enumerate() {
+        if let Some(&(x0, y0)) = i.checked_sub(1).and_then(|j| data.get(j)) {
+            total += (x - x0) * (y + y0) / 2.0;
+        }
+        integrated.push((x, total));
+    }
+    integrated
+}
+
+/// Indices of the centered window around `i`, clamped to the series.
+fn window_around(i: usize, window: usize, len: usize) -> std::ops::Range<usize> {
+    let window = window.clamp(1, len.max(1));
+    let half = window / 2;
+    i.saturating_sub(half)..(i + window - half).min(len)
+}
+
+pub fn moving_average(data: &[(f64, f64)], window: usize) -> Vec<(f64, f64)> {
+    // Prefix sums keep this linear in the series length
+    let mut prefix = Vec::with_capacity(data.len() + 1);
+    prefix.push(0.0);
+    for (_, y) in data {
+        prefix.push(prefix.last().unwrap() + y);
+    }
+    (0..data.len())
+        .map(|i| {
+            let range = window_around(i, window, data.len());
+            let mean = (prefix[range.end] - prefix[range.start]) / range.len() as f64;
+            (data[i].0, mean)
+        })
+        .collect()
+}
+
+pub fn moving_median(data: &[(f64, f64)], window: usize) -> Vec<(f64, f64)> {
+    let mut values = Vec::with_capacity(window.min(data.len()));
+    (0..data.len())
+        .map(|i| {
+            values.clear();
+            values.extend(
+                data[window_around(i, window, data.len())]
+                    .iter()
+                    .map(|(_, y)| *y),
+            );
+            values.sort_by(f64::total_cmp);
+            let mid = values.len() / 2;
+            let median = if values.len() % 2 == 0 {
+                (values[mid - 1] + values[mid]) / 2.0
+            } else {
+                values[mid]
+            };
+            (data[i].0, median)
+        })
+        .collect()
+}
+
+pub fn resample(data: &[(f64, f64)], rate: f64) -> Vec<(f64, f64)> {
+    let Some(first) = data.first() else {
+        return Vec::new();
+    };
+    if rate <= 0.0 || !rate.is_finite() {
+        return data.to_vec();
+    }
+    let series = PlotScalarData::new(data.to_vec());
+    let count = resample_count(data, rate).unwrap_or(MAX_RESAMPLED_POINTS);
+    (0..count)
+        .map(|i| first.0 + i as f64 / rate)
+        .filter_map(|x| Some((x, series.interpolate(x)?)))
+        .collect()
+}
+
+/// Number of samples `resample` produces, `None` if over `MAX_RESAMPLED_POINTS`.
+fn resample_count(data: &[(f64, f64)], rate: f64) -> Option<usize> {
+    let (Some(first), Some(last)) = (data.first(), data.last()) else {
+        return Some(0);
+    };
+    let count = ((last.0 - first.0) * rate).floor() + 1.0;
+    (count <= MAX_RESAMPLED_POINTS as f64).then_some(count.max(0.0) as usize)
+}
+
+/// Amplitude spectrum of the series. Unevenly sampled data is first resampled at
+/// its mean rate; the series is zero-padded to a power of two.
+pub fn fft_magnitude(data: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    if data.len() < 2 {
+        return Vec::new();
+    }
+    let duration = data[data.len() - 1].0 - data[0].0;
+    if duration <= 0.0 {
+        return Vec::new();
+    }
+    let rate = (data.len() - 1) as f64 / duration;
+    let uniform = resample(data, rate);
+
+    let n = uniform.len().next_power_of_two();
+    let mut re: Vec<f64> = uniform.iter().map(|(_, y)| *y).collect();
+    re.resize(n, 0.0);
+    let mut im = vec![0.0; n];
+    fft(&mut re, &mut im);
+
+    let scale = uniform.len() as f64;
+    (0..=n / 2)
+        .map(|k| {
+            let magnitude = re[k].hypot(im[k]) / scale;
+            let one_sided = if k == 0 || k == n / 2 { 1.0 } else { 2.0 };
+            (k as f64 * rate / n as f64, magnitude * one_sided)
+        })
+        .collect()
+}
+
+/// In-place iterative radix-2 FFT. `re.len()` must be a power of two.
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f64::consts::PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_re, w_im) = ((angle * k as f64).cos(), (angle * k as f64).sin());
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// The values of `data` in `bins` equal-width bins, binned like histogram widgets
+/// with `BinSpec::Count`, as `(bin center, count)` pairs.
+pub fn histogram(data: &[(f64, f64)], bins: usize) -> Vec<(f64, f64)> {
+    let values: Vec<f64> = data.iter().map(|(_, y)| *y).collect();
+    let Some(edges) = BinSpec::Count(bins).edges(&values).filter(|_| bins > 0) else {
+        return Vec::new();
+    };
+    let binned = HistogramBins::from_samples(&values, edges);
+    binned
+        .edges
+        .windows(2)
+        .zip(binned.counts)
+        .map(|(edges, count)| ((edges[0] + edges[1]) / 2.0, count))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(data: Vec<(f64, f64)>) -> PlotScalarData {
+        PlotScalarData::new(data)
+    }
+
+    fn ramp(n: usize) -> Vec<(f64, f64)> {
+        (0..n).map(|i| (i as f64, 2.0 * i as f64)).collect()
+    }
+
+    #[test]
+    fn derivative_and_integral_of_a_ramp() {
+        assert_eq!(derivative(&ramp(3)), vec![(0.5, 2.0), (1.5, 2.0)]);
+        assert_eq!(integral(&ramp(3)), vec![(0.0, 0.0), (1.0, 1.0), (2.0, 4.0)]);
+        // Repeated timestamps have no slope
+        assert!(derivative(&[(1.0, 0.0), (1.0, 5.0)]).is_empty());
+    }
+
+    #[test]
+    fn moving_windows_are_centered_and_clamped() {
+        let data = vec![(0.0, 1.0), (1.0, 9.0), (2.0, 2.0), (3.0, 4.0)];
+        assert_eq!(
+            moving_average(&data, 2),
+            vec![(0.0, 1.0), (1.0, 5.0), (2.0, 5.5), (3.0, 3.0)]
+        );
+        assert_eq!(
+            moving_median(&data, 3),
+            vec![(0.0, 5.0), (1.0, 2.0), (2.0, 4.0), (3.0, 3.0)]
+        );
+        // Windows past the series length are clamped to it, without overflow
+        assert_eq!(moving_average(&data, usize::MAX), moving_average(&data, 4));
+        assert_eq!(moving_median(&data, usize::MAX), moving_median(&data, 4));
+    }
+
+    #[test]
+    fn resample_interpolates_at_the_rate() {
+        assert_eq!(resample(&ramp(3), 2.0).len(), 5);
+        assert_eq!(resample(&ramp(3), 2.0)[1], (0.5, 1.0));
+        assert!(resample(&[], 2.0).is_empty());
+        assert_eq!(resample(&ramp(3), 1e300).len(), MAX_RESAMPLED_POINTS);
+    }
+
+    #[test]
+    fn histogram_counts_every_sample() {
+        let counts = histogram(&ramp(10), 5);
+        assert_eq!(counts.len(), 5);
+        assert_eq!(counts.iter().map(|(_, c)| c).sum::<f64>(), 10.0);
+        assert_eq!(histogram(&[(0.0, 1.0), (1.0, 1.0)], 4), vec![(1.0, 2.0)]);
+        assert_eq!(histogram(&ramp(10), usize::MAX).len(), MAX_HISTOGRAM_BINS);
+    }
+
+    #[test]
+    fn fft_finds_the_dominant_frequency() {
+        // 4 Hz sine sampled at 64 Hz for one second
+        let data: Vec<(f64, f64)> = (0..64)
+            .map(|i| {
+                let t = i as f64 / 64.0;
+                (t, (2.0 * std::f64::consts::PI * 4.0 * t).sin())
+            })
+            .collect();
+        let spectrum = fft_magnitude(&data);
+        let peak = spectrum.iter().max_by(|a, b| a.1.total_cmp(&b.1)).unwrap();
+        assert!((peak.0 - 4.0).abs() < 0.5, "peak at {}", peak.0);
+        assert!(fft_magnitude(&[(0.0, 1.0)]).is_empty());
+    }
+
+    #[test]
+    fn validate_rejects_oversized_parameters() {
+        let data = series(ramp(10));
+        assert!(Transform::MovingAverage { window: 10 }
+            .validate(&data)
+            .is_ok());
+        assert!(Transform::MovingAverage { window: 11 }
+            .validate(&data)
+            .is_err());
+        assert!(Transform::MovingMedian { window: 0 }
+            .validate(&data)
+            .is_err());
+        assert!(Transform::Resample { rate: 100.0 }.validate(&data).is_ok());
+        assert!(Transform::Resample { rate: 1e12 }.validate(&data).is_err());
+        assert!(Transform::Resample { rate: f64::NAN }
+            .validate(&data)
+            .is_err());
+        assert!(Transform::Resample { rate: -1.0 }.validate(&data).is_err());
+        assert!(Transform::Histogram {
+            bins: MAX_HISTOGRAM_BINS
+        }
+        .validate(&data)
+        .is_ok());
+        assert!(Transform::Histogram {
+            bins: MAX_HISTOGRAM_BINS + 1
+        }
+        .validate(&data)
+        .is_err());
+        assert!(Transform::FftMagnitude.validate(&data).is_ok());
+    }
+}
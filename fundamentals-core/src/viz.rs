@@ -1,6 +1,8 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 use serde::{Deserialize, Serialize};
 
-use crate::widgets::Widget;
+use crate::widgets::{Ties, Widget};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 
@@ -9,6 +11,8 @@ pub struct Viz {
     pub source: Option<String>,
     pub widgets: Vec<Widget>,
     pub range: Option<(f64, f64)>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, serde_json::Value>,
 }
 
 impl Viz {
@@ -18,6 +22,7 @@ impl Viz {
             source: None,
             widgets: Vec::new(),
             range: None,
+            metadata: BTreeMap::new(),
         }
     }
 
@@ -38,6 +43,131 @@ impl Viz {
         self.range = Some(range);
     }
 
+    pub fn set_metadata(&mut self, key: &str, value: impl Into<serde_json::Value>) {
+        self.metadata.insert(key.to_string(), value.into());
+    }
+
+    pub fn get_metadata(&self, key: &str) -> Option<&serde_json::Value> {
+        self.metadata.get(key)
+    }
+
+    pub fn time_bounds(&self) -> Option<(f64, f64)> {
+        self.widgets
+            .iter()
+            .filter_map(|w| w.time_bounds())
+            .reduce(|(a0, a1), (b0, b1)| (a0.min(b0), a1.max(b1)))
+    }
+
+    /// Adds `offset` to every timestamp, including the range.
+    pub fn shift_time(&mut self, offset: f64) {
+        for widget in self.widgets.iter_mut() {
+            widget.shift_time(offset);
+        }
+        if let Some((t0, t1)) = self.range {
+            self.range = Some((t0 + offset, t1 + offset));
+        }
+    }
+
+    pub fn sort_by_time(&mut self) {
+        for widget in self.widgets.iter_mut() {
+            widget.sort_by_time();
+        }
+    }
+
+    /// Merges another log of the same viz into this one: widgets are paired by
+    /// position and their series merged by time, sources and metadata are combined
+    /// and the range widened. Samples of `other` replace those logged at the same
+    /// time.
+    pub fn merge(&mut self, other: Viz) {
+        self.merge_with(other, Ties::Replace);
+    }
+
+    /// Like `merge`, for a viz of another recording: when `other` has a source this
+    /// viz does not list, samples at equal timestamps are all kept. Fails if a 3D
+    /// view of each overlaps in time, since their keyframe and delta tracks cannot
+    /// be interleaved.
+    pub fn combine(&mut self, other: Viz) -> Result<(), anyhow::Error> {
+        self.check_combine(&other)?;
+        let ties = if self.has_other_source(&other) {
+            Ties::KeepBoth
+        } else {
+            Ties::Replace
+        };
+        self.merge_with(other, ties);
+        Ok(())
+    }
+
+    /// Whether `combine` would accept `other`.
+    pub fn check_combine(&self, other: &Viz) -> Result<(), anyhow::Error> {
+        if !self.has_other_source(other) {
+            return Ok(());
+        }
+        for (a, b) in self.widgets.iter().zip(other.widgets.iter()) {
+            if !matches!((a, b), (Widget::ThreeDView(_), Widget::ThreeDView(_))) {
+                continue;
+            }
+            if let (Some((a0, a1)), Some((b0, b1))) = (a.time_bounds(), b.time_bounds()) {
+                if a0 < b1 && b0 < a1 {
+                    return Err(anyhow::anyhow!(
+                        "Cannot combine 3D view {} of {} with {}: their times overlap",
+                        self.name,
+                        other.source.as_deref().unwrap_or_default(),
+                        self.source.as_deref().unwrap_or_default()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn has_other_source(&self, other: &Viz) -> bool {
+        match (&self.source, &other.source) {
+            (Some(sources), Some(other)) => !sources.split(',').any(|s| s == other),
+            _ => false,
+        }
+    }
+
+    fn merge_with(&mut self, other: Viz, ties: Ties) {
+        for (i, widget) in other.widgets.into_iter().enumerate() {
+            let unmerged = match self.widgets.get_mut(i) {
+                Some(current) => current.merge(widget, ties).err(),
+                None => Some(widget),
+            };
+            if let Some(mut widget) = unmerged {
+                widget.sort_by_time();
+                self.widgets.push(widget);
+            }
+        }
+        self.source = match (self.source.take(), other.source) {
+            // Keep every contributing source as a comma separated list
+            (Some(source), Some(other)) if !source.split(',').any(|s| s == other) => {
+                Some(format!("{},{}", source, other))
+            }
+            (source, other) => source.or(other),
+        };
+        self.range = match (self.range, other.range) {
+            (Some((a0, a1)), Some((b0, b1))) => Some((a0.min(b0), a1.max(b1))),
+            (range, None) | (None, range) => range,
+        };
+        self.metadata.extend(other.metadata);
+    }
+
+    /// Copy of the viz with every widget restricted to `t0 <= time <= t1`.
+    pub fn slice(&self, t0: f64, t1: f64) -> Viz {
+        Viz {
+            name: self.name.clone(),
+            source: self.source.clone(),
+            widgets: self.widgets.iter().map(|w| w.slice(t0, t1)).collect(),
+            range: Some((t0, t1)),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    /// Ids of the recording assets referenced by any widget.
+    pub fn asset_ids(&self) -> BTreeSet<&str> {
+        self.widgets.iter().flat_map(|w| w.asset_ids()).collect()
+    }
+
     pub fn get_name(&self) -> &str {
         &self.name
     }
@@ -0,0 +1,320 @@
+use serde::{Deserialize, Serialize};
+
+use crate::transforms::MAX_HISTOGRAM_BINS;
+
+/// How raw samples are binned. Bin counts above `MAX_HISTOGRAM_BINS` are capped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinSpec {
+    /// `bins` equal-width bins spanning every finite sample of the histogram, or a
+    /// single bin around the value if all samples are equal.
+    Count(usize),
+    /// `bins` equal-width bins between `min` and `max`. Samples outside are clamped
+    /// into the first and last bin.
+    Uniform { min: f64, max: f64, bins: usize },
+    /// Explicit bin edges: finite and strictly increasing.
+    Edges(Vec<f64>),
+}
+
+impl Default for BinSpec {
+    fn default() -> Self {
+        BinSpec::Count(20)
+    }
+}
+
+impl BinSpec {
+    /// Bin edges for `samples`; `None` if there is nothing to bin or the spec is
+    /// invalid, see `validate`.
+    pub fn edges<'a>(&self, samples: impl IntoIterator<Item = &'a f64>) -> Option<Vec<f64>> {
+        if self.validate().is_err() {
+            return None;
+        }
+        let uniform = |min: f64, max: f64, bins: usize| {
+            let bins = bins.clamp(1, MAX_HISTOGRAM_BINS);
+            let width = (max - min) / bins as f64;
+            (0..=bins).map(|i| min + i as f64 * width).collect()
+        };
+        match self {
+            BinSpec::Count(bins) => {
+                let (min, max) = samples.into_iter().filter(|s| s.is_finite()).fold(
+                    None,
+                    |acc: Option<(f64, f64)>, &s| match acc {
+                        Some((min, max)) => Some((min.min(s), max.max(s))),
+                        None => Some((s, s)),
+                    },
+                )?;
+                if max > min {
+                    Some(uniform(min, max, *bins))
+                } else {
+                    Some(vec![min - 0.5, min + 0.5])
+                }
+            }
+            BinSpec::Uniform { min, max, bins } => Some(uniform(*min, *max, *bins)),
+            BinSpec::Edges(edges) => Some(edges.clone()),
+        }
+    }
+
+    /// Checks that `Uniform` bounds are finite and increasing and that `Edges` are
+    /// valid bin edges.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        match self {
+            BinSpec::Count(_) => Ok(()),
+            BinSpec::Uniform { min, max, .. } => {
+                if min.is_finite() && max.is_finite() && min < max {
+                    Ok(())
+                } else {
+                    Err(anyhow::anyhow!(
+                        "Uniform bins need finite bounds with min < max, got {}..{}",
+                        min,
+                        max
+                    ))
+                }
+            }
+            BinSpec::Edges(edges) => check_edges(edges),
+        }
+    }
+}
+
+/// Checks that `edges` delimit between 1 and `MAX_HISTOGRAM_BINS` bins and are
+/// finite and strictly increasing.
+fn check_edges(edges: &[f64]) -> Result<(), anyhow::Error> {
+    if edges.len() < 2 || edges.len() > MAX_HISTOGRAM_BINS + 1 {
+        return Err(anyhow::anyhow!(
+            "{} bin edges do not make between 1 and {} bins",
+            edges.len(),
+            MAX_HISTOGRAM_BINS
+        ));
+    }
+    if let Some(edge) = edges.iter().find(|e| !e.is_finite()) {
+        return Err(anyhow::anyhow!("Bin edge {} is not finite", edge));
+    }
+    if let Some(pair) = edges.windows(2).find(|pair| pair[0] >= pair[1]) {
+        return Err(anyhow::anyhow!(
+            "Bin edges must be strictly increasing, got {} then {}",
+            pair[0],
+            pair[1]
+        ));
+    }
+    Ok(())
+}
+
+/// Pre-binned histogram: `counts[i]` samples fell in `edges[i]..edges[i + 1]`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct HistogramBins {
+    pub edges: Vec<f64>,
+    pub counts: Vec<f64>,
+}
+
+impl HistogramBins {
+    pub fn new(edges: Vec<f64>, counts: Vec<f64>) -> Result<Self, anyhow::Error> {
+        check_edges(&edges)?;
+        if edges.len() != counts.len() + 1 {
+            return Err(anyhow::anyhow!(
+                "{} bin edges do not match {} counts",
+                edges.len(),
+                counts.len()
+            ));
+        }
+        Ok(Self { edges, counts })
+    }
+
+    /// Counts `samples` into the bins delimited by `edges`. Values outside the edges
+    /// go into the first or last bin; NaNs are skipped.
+    pub fn from_samples(samples: &[f64], edges: Vec<f64>) -> Self {
+        let mut counts = vec![0.0; edges.len().saturating_sub(1)];
+        if !counts.is_empty() {
+            for sample in samples.iter().filter(|s| !s.is_nan()) {
+                let bin = edges[1..edges.len() - 1].partition_point(|edge| edge <= sample);
+                counts[bin] += 1.0;
+            }
+        }
+        Self { edges, counts }
+    }
+
+    pub fn total(&self) -> f64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// One histogram: either bins computed by the logger or raw samples to be binned by
+/// the bridge.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistogramFrame {
+    Binned(HistogramBins),
+    Samples(Vec<f64>),
+}
+
+impl HistogramFrame {
+    pub fn samples(&self) -> &[f64] {
+        match self {
+            HistogramFrame::Samples(samples) => samples,
+            HistogramFrame::Binned(_) => &[],
+        }
+    }
+}
+
+/// Distribution widget. A single frame is a static histogram; several frames make a
+/// time-varying one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistogramData {
+    pub frames: Vec<(f64, HistogramFrame)>,
+    /// Binning for `Samples` frames.
+    #[serde(default)]
+    pub bins: BinSpec,
+}
+
+impl HistogramData {
+    pub fn new(frames: Vec<(f64, HistogramFrame)>) -> Self {
+        Self {
+            frames,
+            bins: BinSpec::default(),
+        }
+    }
+
+    pub fn with_bins(mut self, bins: BinSpec) -> Self {
+        self.bins = bins;
+        self
+    }
+
+    /// Frames with `t0 <= time <= t1`, assuming `frames` is sorted by time.
+    pub fn range(&self, t0: f64, t1: f64) -> &[(f64, HistogramFrame)] {
+        let start = self.frames.partition_point(|(t, _)| *t < t0);
+        let end = self.frames.partition_point(|(t, _)| *t <= t1);
+        &self.frames[start..end.max(start)]
+    }
+
+    /// Copy of the histogram restricted to `t0 <= time <= t1`.
+    pub fn slice(&self, t0: f64, t1: f64) -> Self {
+        Self {
+            frames: self.range(t0, t1).to_vec(),
+            bins: self.bins.clone(),
+        }
+    }
+
+    /// Most recent frame with `time <= t`.
+    pub fn latest_at(&self, t: f64) -> Option<&(f64, HistogramFrame)> {
+        let end = self.frames.partition_point(|(time, _)| *time <= t);
+        end.checked_sub(1).map(|i| &self.frames[i])
+    }
+
+    /// Whether every frame is already binned.
+    pub fn is_binned(&self) -> bool {
+        self.frames
+            .iter()
+            .all(|(_, frame)| matches!(frame, HistogramFrame::Binned(_)))
+    }
+
+    /// Copy with every `Samples` frame binned. With `BinSpec::Count` all frames share
+    /// edges spanning the samples of every frame, so time-varying histograms stay
+    /// comparable.
+    pub fn binned(&self) -> Self {
+        let edges = self
+            .bins
+            .edges(self.frames.iter().flat_map(|(_, frame)| frame.samples()));
+        let frames = self
+            .frames
+            .iter()
+            .map(|(t, frame)| {
+                let frame = match (frame, &edges) {
+                    (HistogramFrame::Samples(samples), Some(edges)) => {
+                        HistogramFrame::Binned(HistogramBins::from_samples(samples, edges.clone()))
+                    }
+                    (HistogramFrame::Samples(_), None) => {
+                        HistogramFrame::Binned(HistogramBins::default())
+                    }
+                    (binned, _) => binned.clone(),
+                };
+                (*t, frame)
+            })
+            .collect();
+        Self {
+            frames,
+            bins: self.bins.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bin_counts_are_capped() {
+        let samples = [0.0, 1.0];
+        let edges = BinSpec::Count(usize::MAX).edges(&samples).unwrap();
+        assert_eq!(edges.len(), MAX_HISTOGRAM_BINS + 1);
+        let spec = BinSpec::Uniform {
+            min: 0.0,
+            max: 1.0,
+            bins: usize::MAX,
+        };
+        assert_eq!(spec.edges(&samples).unwrap().len(), MAX_HISTOGRAM_BINS + 1);
+        assert_eq!(BinSpec::Count(0).edges(&samples).unwrap(), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn equal_samples_make_one_bin() {
+        let edges = BinSpec::Count(10).edges(&[2.0, 2.0, f64::NAN]).unwrap();
+        assert_eq!(edges, vec![1.5, 2.5]);
+        assert_eq!(BinSpec::Count(10).edges(&[f64::NAN]), None);
+    }
+
+    #[test]
+    fn invalid_edges_are_rejected() {
+        for edges in [
+            vec![0.0],
+            vec![0.0, 0.0],
+            vec![1.0, 0.0],
+            vec![0.0, f64::NAN],
+            vec![f64::NEG_INFINITY, 0.0],
+            vec![0.0; MAX_HISTOGRAM_BINS + 2],
+        ] {
+            let spec = BinSpec::Edges(edges.clone());
+            assert!(
+                spec.validate().is_err(),
+                "{:?}",
+                &edges[..2.min(edges.len())]
+            );
+            assert_eq!(spec.edges(&[0.0]), None);
+            let counts = vec![0.0; edges.len().saturating_sub(1)];
+            assert!(HistogramBins::new(edges, counts).is_err());
+        }
+        let uniform = BinSpec::Uniform {
+            min: 1.0,
+            max: 1.0,
+            bins: 4,
+        };
+        assert!(uniform.validate().is_err());
+        assert!(BinSpec::Edges(vec![0.0, 0.5, 2.0]).validate().is_ok());
+    }
+
+    #[test]
+    fn samples_outside_the_edges_go_to_the_outer_bins() {
+        let bins = HistogramBins::from_samples(
+            &[-5.0, 0.0, 0.5, 1.0, 1.5, 9.0, f64::NAN, f64::INFINITY],
+            vec![0.0, 1.0, 2.0],
+        );
+        assert_eq!(bins.counts, vec![3.0, 4.0]);
+        assert_eq!(bins.total(), 7.0);
+    }
+
+    #[test]
+    fn frames_of_a_count_spec_share_edges() {
+        let data = HistogramData::new(vec![
+            (0.0, HistogramFrame::Samples(vec![0.0, 1.0])),
+            (1.0, HistogramFrame::Samples(vec![3.0, 4.0])),
+        ])
+        .with_bins(BinSpec::Count(4));
+        let binned = data.binned();
+        assert!(binned.is_binned());
+        let [(_, HistogramFrame::Binned(a)), (_, HistogramFrame::Binned(b))] = &binned.frames[..]
+        else {
+            panic!("expected two binned frames");
+        };
+        assert_eq!(a.edges, vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(a.edges, b.edges);
+        assert_eq!(a.counts, vec![1.0, 1.0, 0.0, 0.0]);
+        assert_eq!(b.counts, vec![0.0, 0.0, 0.0, 2.0]);
+    }
+}
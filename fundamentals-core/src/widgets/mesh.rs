@@ -0,0 +1,367 @@
+use std::f64::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+/// Rigid transform: a translation and a unit quaternion `(x, y, z, w)`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Pose {
+    pub position: (f64, f64, f64),
+    pub orientation: (f64, f64, f64, f64),
+}
+
+impl Default for Pose {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Pose {
+    pub fn identity() -> Self {
+        Self {
+            position: (0.0, 0.0, 0.0),
+            orientation: (0.0, 0.0, 0.0, 1.0),
+        }
+    }
+
+    pub fn from_position(position: (f64, f64, f64)) -> Self {
+        Self {
+            position,
+            ..Self::identity()
+        }
+    }
+
+    /// Translation plus fixed-axis roll, pitch and yaw, as in URDF `origin` tags.
+    pub fn from_xyz_rpy(position: (f64, f64, f64), rpy: (f64, f64, f64)) -> Self {
+        let (roll, pitch, yaw) = (rpy.0 / 2.0, rpy.1 / 2.0, rpy.2 / 2.0);
+        let (sr, cr) = roll.sin_cos();
+        let (sp, cp) = pitch.sin_cos();
+        let (sy, cy) = yaw.sin_cos();
+        Self {
+            position,
+            orientation: (
+                sr * cp * cy - cr * sp * sy,
+                cr * sp * cy + sr * cp * sy,
+                cr * cp * sy - sr * sp * cy,
+                cr * cp * cy + sr * sp * sy,
+            ),
+        }
+    }
+
+    /// Rotation of `angle` radians around `axis`, which need not be normalized.
+    pub fn from_axis_angle(axis: (f64, f64, f64), angle: f64) -> Self {
+        let norm = (axis.0 * axis.0 + axis.1 * axis.1 + axis.2 * axis.2).sqrt();
+        if norm == 0.0 {
+            return Self::identity();
+        }
+        let (s, c) = (angle / 2.0).sin_cos();
+        let k = s / norm;
+        Self {
+            position: (0.0, 0.0, 0.0),
+            orientation: (axis.0 * k, axis.1 * k, axis.2 * k, c),
+        }
+    }
+
+    /// `other` expressed in the frame this pose maps from, i.e. `self * other`.
+    pub fn then(&self, other: &Pose) -> Pose {
+        let (x1, y1, z1, w1) = self.orientation;
+        let (x2, y2, z2, w2) = other.orientation;
+        Pose {
+            position: self.transform_point(other.position),
+            orientation: (
+                w1 * x2 + x1 * w2 + y1 * z2 - z1 * y2,
+                w1 * y2 - x1 * z2 + y1 * w2 + z1 * x2,
+                w1 * z2 + x1 * y2 - y1 * x2 + z1 * w2,
+                w1 * w2 - x1 * x2 - y1 * y2 - z1 * z2,
+            ),
+        }
+    }
+
+    pub fn rotate(&self, v: (f64, f64, f64)) -> (f64, f64, f64) {
+        let (x, y, z, w) = self.orientation;
+        // v + 2w(q x v) + 2q x (q x v)
+        let t = (
+            2.0 * (y * v.2 - z * v.1),
+            2.0 * (z * v.0 - x * v.2),
+            2.0 * (x * v.1 - y * v.0),
+        );
+        (
+            v.0 + w * t.0 + (y * t.2 - z * t.1),
+            v.1 + w * t.1 + (z * t.0 - x * t.2),
+            v.2 + w * t.2 + (x * t.1 - y * t.0),
+        )
+    }
+
+    pub fn transform_point(&self, p: (f64, f64, f64)) -> (f64, f64, f64) {
+        let r = self.rotate(p);
+        (
+            r.0 + self.position.0,
+            r.1 + self.position.1,
+            r.2 + self.position.2,
+        )
+    }
+}
+
+/// Triangle mesh geometry, in the mesh's own frame.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Mesh {
+    pub vertices: Vec<(f64, f64, f64)>,
+    /// Vertex indices of each triangle, counter-clockwise when seen from outside.
+    pub triangles: Vec<(u32, u32, u32)>,
+    /// Default color of the mesh; instances may override it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl Mesh {
+    pub fn with_color(mut self, color: &str) -> Self {
+        self.color = Some(color.to_string());
+        self
+    }
+
+    /// Appends `other`, moved by `pose`.
+    pub fn append(&mut self, other: &Mesh, pose: &Pose) {
+        let offset = self.vertices.len() as u32;
+        self.vertices
+            .extend(other.vertices.iter().map(|&v| pose.transform_point(v)));
+        self.triangles.extend(
+            other
+                .triangles
+                .iter()
+                .map(|&(a, b, c)| (a + offset, b + offset, c + offset)),
+        );
+        if self.color.is_none() {
+            self.color = other.color.clone();
+        }
+    }
+
+    /// Axis-aligned box of the given edge lengths, centered on the origin.
+    pub fn cuboid(size: (f64, f64, f64)) -> Self {
+        let (x, y, z) = (size.0 / 2.0, size.1 / 2.0, size.2 / 2.0);
+        let vertices = (0..8)
+            .map(|i| {
+                (
+                    if i & 1 == 0 { -x } else { x },
+                    if i & 2 == 0 { -y } else { y },
+                    if i & 4 == 0 { -z } else { z },
+                )
+            })
+            .collect();
+        let triangles = vec![
+            (0, 2, 1),
+            (1, 2, 3),
+            (4, 5, 6),
+            (5, 7, 6),
+            (0, 1, 4),
+            (1, 5, 4),
+            (2, 6, 3),
+            (3, 6, 7),
+            (0, 4, 2),
+            (2, 4, 6),
+            (1, 3, 5),
+            (3, 7, 5),
+        ];
+        Self {
+            vertices,
+            triangles,
+            color: None,
+        }
+    }
+
+    /// Cylinder along z, centered on the origin, approximated by `segments` sides.
+    pub fn cylinder(radius: f64, length: f64, segments: u32) -> Self {
+        let segments = segments.max(3);
+        let h = length / 2.0;
+        let mut vertices = vec![(0.0, 0.0, -h), (0.0, 0.0, h)];
+        for i in 0..segments {
+            let (s, c) = (TAU * i as f64 / segments as f64).sin_cos();
+            vertices.push((radius * c, radius * s, -h));
+            vertices.push((radius * c, radius * s, h));
+        }
+        let mut triangles = Vec::with_capacity(4 * segments as usize);
+        for i in 0..segments {
+            let (b0, t0) = (2 + 2 * i, 3 + 2 * i);
+            let (b1, t1) = (2 + 2 * ((i + 1) % segments), 3 + 2 * ((i + 1) % segments));
+            triangles.push((0, b1, b0));
+            triangles.push((1, t0, t1));
+            triangles.push((b0, b1, t0));
+            triangles.push((t0, b1, t1));
+        }
+        Self {
+            vertices,
+            triangles,
+            color: None,
+        }
+    }
+
+    /// UV sphere centered on the origin with `segments` slices and half as many
+    /// stacks.
+    pub fn sphere(radius: f64, segments: u32) -> Self {
+        let slices = segments.max(3);
+        let stacks = (segments / 2).max(2);
+        let mut vertices = Vec::with_capacity(((stacks + 1) * slices) as usize);
+        for stack in 0..=stacks {
+            let (sp, cp) = (std::f64::consts::PI * stack as f64 / stacks as f64).sin_cos();
+            for slice in 0..slices {
+                let (st, ct) = (TAU * slice as f64 / slices as f64).sin_cos();
+                vertices.push((radius * sp * ct, radius * sp * st, radius * cp));
+            }
+        }
+        let mut triangles = Vec::with_capacity((2 * stacks * slices) as usize);
+        for stack in 0..stacks {
+            for slice in 0..slices {
+                let a = stack * slices + slice;
+                let b = stack * slices + (slice + 1) % slices;
+                let (c, d) = (a + slices, b + slices);
+                if stack > 0 {
+                    triangles.push((a, c, b));
+                }
+                if stack + 1 < stacks {
+                    triangles.push((b, c, d));
+                }
+            }
+        }
+        Self {
+            vertices,
+            triangles,
+            color: None,
+        }
+    }
+}
+
+/// One placement of a mesh asset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeshInstance {
+    /// Id of an `Asset::Mesh` in the recording's asset table.
+    pub mesh: String,
+    pub pose: Pose,
+    #[serde(default = "unit_scale")]
+    pub scale: (f64, f64, f64),
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+fn unit_scale() -> (f64, f64, f64) {
+    (1.0, 1.0, 1.0)
+}
+
+impl MeshInstance {
+    pub fn new(mesh: &str, pose: Pose) -> Self {
+        Self {
+            mesh: mesh.to_string(),
+            pose,
+            scale: unit_scale(),
+            color: None,
+        }
+    }
+
+    pub fn with_scale(mut self, scale: (f64, f64, f64)) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_color(mut self, color: Option<String>) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn assert_near(a: (f64, f64, f64), b: (f64, f64, f64)) {
+        let error = (a.0 - b.0).abs() + (a.1 - b.1).abs() + (a.2 - b.2).abs();
+        assert!(error < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn rpy_matches_axis_angle() {
+        let x = (1.0, 0.0, 0.0);
+        let yaw = Pose::from_xyz_rpy((0.0, 0.0, 0.0), (0.0, 0.0, FRAC_PI_2));
+        assert_near(yaw.rotate(x), (0.0, 1.0, 0.0));
+        assert_near(
+            yaw.rotate(x),
+            Pose::from_axis_angle((0.0, 0.0, 2.0), FRAC_PI_2).rotate(x),
+        );
+        let pitch = Pose::from_xyz_rpy((0.0, 0.0, 0.0), (0.0, FRAC_PI_2, 0.0));
+        assert_near(pitch.rotate(x), (0.0, 0.0, -1.0));
+        let roll = Pose::from_xyz_rpy((0.0, 0.0, 0.0), (FRAC_PI_2, 0.0, 0.0));
+        assert_near(roll.rotate((0.0, 1.0, 0.0)), (0.0, 0.0, 1.0));
+        assert_eq!(
+            Pose::from_axis_angle((0.0, 0.0, 0.0), 1.0),
+            Pose::identity()
+        );
+    }
+
+    #[test]
+    fn fixed_axis_rpy_applies_roll_first() {
+        // URDF rotations are about the fixed axes: roll, then pitch, then yaw
+        let rpy = (0.3, -0.7, 1.1);
+        let composed = Pose::from_axis_angle((0.0, 0.0, 1.0), rpy.2)
+            .then(&Pose::from_axis_angle((0.0, 1.0, 0.0), rpy.1))
+            .then(&Pose::from_axis_angle((1.0, 0.0, 0.0), rpy.0));
+        let pose = Pose::from_xyz_rpy((0.0, 0.0, 0.0), rpy);
+        for v in [(1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (0.2, -0.4, 0.9)] {
+            assert_near(pose.rotate(v), composed.rotate(v));
+        }
+    }
+
+    #[test]
+    fn then_applies_the_inner_pose_first() {
+        let turn = Pose::from_xyz_rpy((1.0, 0.0, 0.0), (0.0, 0.0, FRAC_PI_2));
+        let step = Pose::from_position((2.0, 0.0, 0.0));
+        let p = (0.0, 0.0, 1.0);
+        assert_near(turn.then(&step).transform_point(p), (1.0, 2.0, 1.0));
+        assert_near(
+            turn.then(&step).transform_point(p),
+            turn.transform_point(step.transform_point(p)),
+        );
+        assert_near(step.then(&turn).transform_point(p), (3.0, 0.0, 1.0));
+    }
+
+    fn assert_valid(mesh: &Mesh) {
+        let count = mesh.vertices.len() as u32;
+        assert!(mesh
+            .triangles
+            .iter()
+            .all(|&(a, b, c)| a < count && b < count && c < count));
+    }
+
+    #[test]
+    fn primitives_have_the_requested_size() {
+        let cuboid = Mesh::cuboid((2.0, 4.0, 6.0));
+        assert_eq!((cuboid.vertices.len(), cuboid.triangles.len()), (8, 12));
+        assert!(cuboid.vertices.contains(&(1.0, 2.0, 3.0)));
+        assert_valid(&cuboid);
+
+        let cylinder = Mesh::cylinder(0.5, 2.0, 8);
+        assert_eq!(cylinder.triangles.len(), 32);
+        assert!(cylinder.vertices[2..]
+            .iter()
+            .all(|v| ((v.0 * v.0 + v.1 * v.1).sqrt() - 0.5).abs() < 1e-12 && v.2.abs() == 1.0));
+        assert_valid(&cylinder);
+
+        let sphere = Mesh::sphere(2.0, 12);
+        assert!(sphere
+            .vertices
+            .iter()
+            .all(|v| ((v.0 * v.0 + v.1 * v.1 + v.2 * v.2).sqrt() - 2.0).abs() < 1e-12));
+        assert_valid(&sphere);
+    }
+
+    #[test]
+    fn append_offsets_indices_and_moves_vertices() {
+        let mut mesh = Mesh::cuboid((1.0, 1.0, 1.0));
+        mesh.append(
+            &Mesh::cuboid((1.0, 1.0, 1.0)).with_color("red"),
+            &Pose::from_position((10.0, 0.0, 0.0)),
+        );
+        assert_eq!(mesh.vertices.len(), 16);
+        assert_eq!(mesh.triangles[12], (8, 10, 9));
+        assert_eq!(mesh.vertices[8], (9.5, -0.5, -0.5));
+        assert_eq!(mesh.color.as_deref(), Some("red"));
+        assert_valid(&mesh);
+    }
+}
@@ -1,5 +1,14 @@
+pub mod histogram;
+pub mod mesh;
+pub mod non_finite;
+pub mod occupancy;
 pub mod plot_scalar;
+pub mod precision;
+pub mod tensor;
 pub mod three_d_view;
+pub mod two_d_view;
+use std::collections::BTreeSet;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -8,4 +17,282 @@ pub enum Widget {
     PlotScalar(plot_scalar::PlotScalarData),
     #[serde(rename = "3d_view")]
     ThreeDView(three_d_view::ThreeDViewData),
+    #[serde(rename = "histogram")]
+    Histogram(histogram::HistogramData),
+    #[serde(rename = "2d_view")]
+    TwoDView(two_d_view::TwoDViewData),
+    #[serde(rename = "tensor")]
+    Tensor(tensor::TensorData),
+}
+
+/// Value of a single widget at a point in time, as returned by latest-at queries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LatestValue {
+    #[serde(rename = "plot_scalar")]
+    PlotScalar(f64, f64),
+    #[serde(rename = "3d_view")]
+    ThreeDView(f64, three_d_view::ThreeDPrimative),
+    #[serde(rename = "histogram")]
+    Histogram(f64, histogram::HistogramFrame),
+    #[serde(rename = "2d_view")]
+    TwoDView(f64, two_d_view::TwoDPrimative),
+    #[serde(rename = "tensor")]
+    Tensor(f64, tensor::Tensor),
+}
+
+/// Sorts time-tagged samples by time, keeping the order of equal timestamps.
+pub(crate) fn sort_by_time<T>(samples: &mut [(f64, T)]) {
+    if !samples.is_sorted_by(|a, b| a.0 <= b.0) {
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+}
+
+/// What merging two series does with the samples both have at one timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ties {
+    /// The samples in the incoming series replace all existing samples at that
+    /// timestamp, so re-logging the same data does not duplicate it, even where
+    /// several primitives share a timestamp.
+    Replace,
+    /// Both are kept, the existing samples first. For series from different sources.
+    KeepBoth,
+}
+
+/// Merges two time-sorted series, resolving equal timestamps with `ties`.
+pub(crate) fn merge_by_time<T>(
+    existing: Vec<(f64, T)>,
+    incoming: Vec<(f64, T)>,
+    ties: Ties,
+) -> Vec<(f64, T)> {
+    match (existing.last(), incoming.first()) {
+        (None, _) => return incoming,
+        (_, None) => return existing,
+        (Some(last), Some(first)) if first.0 > last.0 => {
+            let mut merged = existing;
+            merged.extend(incoming);
+            return merged;
+        }
+        _ => {}
+    }
+
+    let mut merged = Vec::with_capacity(existing.len() + incoming.len());
+    let mut existing = existing.into_iter().peekable();
+    let mut incoming = incoming.into_iter().peekable();
+    loop {
+        match (existing.peek(), incoming.peek()) {
+            (Some(a), Some(b)) if a.0 < b.0 => merged.extend(existing.next()),
+            (Some(a), Some(b)) if a.0 == b.0 => {
+                let time = b.0;
+                while let Some(sample) = existing.next_if(|(t, _)| *t == time) {
+                    if ties == Ties::KeepBoth {
+                        merged.push(sample);
+                    }
+                }
+                while let Some(sample) = incoming.next_if(|(t, _)| *t == time) {
+                    merged.push(sample);
+                }
+            }
+            (_, Some(_)) => merged.extend(incoming.next()),
+            (Some(_), None) => merged.extend(existing.next()),
+            (None, None) => break,
+        }
+    }
+    merged
+}
+
+impl Widget {
+    /// Serialized tag of the widget, e.g. `plot_scalar`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Widget::PlotScalar(_) => "plot_scalar",
+            Widget::ThreeDView(_) => "3d_view",
+            Widget::Histogram(_) => "histogram",
+            Widget::TwoDView(_) => "2d_view",
+            Widget::Tensor(_) => "tensor",
+        }
+    }
+
+    /// Number of time-stamped samples or primitives.
+    pub fn len(&self) -> usize {
+        match self {
+            Widget::PlotScalar(data) => data.data_x.len(),
+            Widget::ThreeDView(data) => data.primatives.len(),
+            Widget::Histogram(data) => data.frames.len(),
+            Widget::TwoDView(data) => data.primatives.len(),
+            Widget::Tensor(data) => data.frames.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copy of the widget with its data restricted to `t0 <= time <= t1`.
+    pub fn slice(&self, t0: f64, t1: f64) -> Widget {
+        match self {
+            Widget::PlotScalar(data) => Widget::PlotScalar(data.slice(t0, t1)),
+            Widget::ThreeDView(data) => Widget::ThreeDView(data.slice(t0, t1)),
+            Widget::Histogram(data) => Widget::Histogram(data.slice(t0, t1)),
+            Widget::TwoDView(data) => Widget::TwoDView(data.slice(t0, t1)),
+            Widget::Tensor(data) => Widget::Tensor(data.slice(t0, t1)),
+        }
+    }
+
+    /// First and last timestamp of the widget's data.
+    pub fn time_bounds(&self) -> Option<(f64, f64)> {
+        match self {
+            Widget::PlotScalar(data) => Some((data.data_x.first()?.0, data.data_x.last()?.0)),
+            Widget::ThreeDView(data) => {
+                Some((data.primatives.first()?.0, data.primatives.last()?.0))
+            }
+            Widget::Histogram(data) => Some((data.frames.first()?.0, data.frames.last()?.0)),
+            Widget::TwoDView(data) => Some((data.primatives.first()?.0, data.primatives.last()?.0)),
+            Widget::Tensor(data) => Some((data.frames.first()?.0, data.frames.last()?.0)),
+        }
+    }
+
+    /// Adds `offset` to every timestamp.
+    pub fn shift_time(&mut self, offset: f64) {
+        match self {
+            Widget::PlotScalar(data) => data.data_x.iter_mut().for_each(|(t, _)| *t += offset),
+            Widget::ThreeDView(data) => data.primatives.iter_mut().for_each(|(t, _)| *t += offset),
+            Widget::Histogram(data) => data.frames.iter_mut().for_each(|(t, _)| *t += offset),
+            Widget::TwoDView(data) => data.primatives.iter_mut().for_each(|(t, _)| *t += offset),
+            Widget::Tensor(data) => data.frames.iter_mut().for_each(|(t, _)| *t += offset),
+        }
+    }
+
+    pub fn sort_by_time(&mut self) {
+        match self {
+            Widget::PlotScalar(data) => sort_by_time(&mut data.data_x),
+            Widget::ThreeDView(data) => sort_by_time(&mut data.primatives),
+            Widget::Histogram(data) => sort_by_time(&mut data.frames),
+            Widget::TwoDView(data) => sort_by_time(&mut data.primatives),
+            Widget::Tensor(data) => sort_by_time(&mut data.frames),
+        }
+    }
+
+    /// Merges the data of `other` into this widget. Returns `other` unchanged if it
+    /// is a different kind of widget.
+    pub fn merge(&mut self, other: Widget, ties: Ties) -> Result<(), Widget> {
+        match (self, other) {
+            (Widget::PlotScalar(data), Widget::PlotScalar(mut other)) => {
+                sort_by_time(&mut other.data_x);
+                data.data_x = merge_by_time(std::mem::take(&mut data.data_x), other.data_x, ties);
+                data.precision = data.precision.common(other.precision);
+                Ok(())
+            }
+            (Widget::ThreeDView(data), Widget::ThreeDView(mut other)) => {
+                sort_by_time(&mut other.primatives);
+                data.primatives =
+                    merge_by_time(std::mem::take(&mut data.primatives), other.primatives, ties);
+                Ok(())
+            }
+            (Widget::Histogram(data), Widget::Histogram(mut other)) => {
+                sort_by_time(&mut other.frames);
+                data.frames = merge_by_time(std::mem::take(&mut data.frames), other.frames, ties);
+                Ok(())
+            }
+            (Widget::TwoDView(data), Widget::TwoDView(mut other)) => {
+                sort_by_time(&mut other.primatives);
+                data.primatives =
+                    merge_by_time(std::mem::take(&mut data.primatives), other.primatives, ties);
+                Ok(())
+            }
+            (Widget::Tensor(data), Widget::Tensor(mut other)) => {
+                sort_by_time(&mut other.frames);
+                data.frames = merge_by_time(std::mem::take(&mut data.frames), other.frames, ties);
+                Ok(())
+            }
+            (_, other) => Err(other),
+        }
+    }
+
+    /// Ids of the recording assets referenced by the widget's data.
+    pub fn asset_ids(&self) -> BTreeSet<&str> {
+        match self {
+            Widget::ThreeDView(data) => data
+                .primatives
+                .iter()
+                .flat_map(|(_, primative)| primative.asset_ids())
+                .collect(),
+            _ => BTreeSet::new(),
+        }
+    }
+
+    pub fn latest_at(&self, t: f64) -> Option<LatestValue> {
+        match self {
+            Widget::PlotScalar(data) => data
+                .latest_at(t)
+                .map(|(x, y)| LatestValue::PlotScalar(x, y)),
+            Widget::ThreeDView(data) => data
+                .latest_state_at(t)
+                .map(|(time, primative)| LatestValue::ThreeDView(time, primative)),
+            Widget::Histogram(data) => data
+                .latest_at(t)
+                .map(|(time, frame)| LatestValue::Histogram(*time, frame.clone())),
+            Widget::TwoDView(data) => data
+                .latest_at(t)
+                .map(|(time, primative)| LatestValue::TwoDView(*time, primative.clone())),
+            Widget::Tensor(data) => data
+                .latest_at(t)
+                .map(|(time, tensor)| LatestValue::Tensor(*time, tensor.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_replaces_whole_runs_of_equal_timestamps() {
+        let existing = vec![(0.0, 'a'), (1.0, 'A'), (1.0, 'B'), (2.0, 'c')];
+        let merged = merge_by_time(existing, vec![(1.0, 'C')], Ties::Replace);
+        assert_eq!(merged, vec![(0.0, 'a'), (1.0, 'C'), (2.0, 'c')]);
+
+        let merged = merge_by_time(
+            vec![(1.0, 'A')],
+            vec![(1.0, 'B'), (1.0, 'C')],
+            Ties::Replace,
+        );
+        assert_eq!(merged, vec![(1.0, 'B'), (1.0, 'C')]);
+    }
+
+    #[test]
+    fn merge_can_keep_both_samples_at_equal_timestamps() {
+        let existing = vec![(0.0, 'a'), (1.0, 'A'), (1.0, 'B'), (2.0, 'c')];
+        let merged = merge_by_time(existing, vec![(1.0, 'C'), (2.0, 'd')], Ties::KeepBoth);
+        assert_eq!(
+            merged,
+            vec![
+                (0.0, 'a'),
+                (1.0, 'A'),
+                (1.0, 'B'),
+                (1.0, 'C'),
+                (2.0, 'c'),
+                (2.0, 'd')
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_interleaves_distinct_timestamps() {
+        for ties in [Ties::Replace, Ties::KeepBoth] {
+            let merged = merge_by_time(
+                vec![(0.0, 'a'), (2.0, 'c')],
+                vec![(1.0, 'b'), (3.0, 'd')],
+                ties,
+            );
+            assert_eq!(merged, vec![(0.0, 'a'), (1.0, 'b'), (2.0, 'c'), (3.0, 'd')]);
+            assert_eq!(
+                merge_by_time(vec![], vec![(1.0, 'b')], ties),
+                vec![(1.0, 'b')]
+            );
+            assert_eq!(
+                merge_by_time(vec![(1.0, 'a')], vec![], ties),
+                vec![(1.0, 'a')]
+            );
+        }
+    }
 }
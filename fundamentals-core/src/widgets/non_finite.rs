@@ -0,0 +1,187 @@
+//! Serde helpers for floats that may be NaN or infinite. JSON has no numbers for
+//! them, and `serde_json` writes them as `null`, which then fails to read back as a
+//! float. Human-readable formats get the strings `"NaN"`, `"Infinity"` and
+//! `"-Infinity"` instead; binary formats store the floats as they are. `null` reads
+//! back as NaN, for files written before.
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+pub const NAN: &str = "NaN";
+pub const INFINITY: &str = "Infinity";
+pub const NEG_INFINITY: &str = "-Infinity";
+
+/// `f64` or `f32`.
+pub trait Float: Copy + Serialize {
+    fn to_f64(self) -> f64;
+    fn from_f64(value: f64) -> Self;
+}
+
+impl Float for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+impl Float for f32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+}
+
+/// A float that serializes as described in the module docs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NonFinite<T>(pub T);
+
+impl<T: Float> Serialize for NonFinite<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = self.0.to_f64();
+        if value.is_finite() || !serializer.is_human_readable() {
+            return self.0.serialize(serializer);
+        }
+        serializer.serialize_str(if value.is_nan() {
+            NAN
+        } else if value > 0.0 {
+            INFINITY
+        } else {
+            NEG_INFINITY
+        })
+    }
+}
+
+impl<'de, T: Float> Deserialize<'de> for NonFinite<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FloatVisitor;
+
+        impl Visitor<'_> for FloatVisitor {
+            type Value = f64;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a number, \"NaN\", \"Infinity\" or \"-Infinity\"")
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<f64, E> {
+                Ok(v)
+            }
+
+            fn visit_f32<E: de::Error>(self, v: f32) -> Result<f64, E> {
+                Ok(v as f64)
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<f64, E> {
+                Ok(v as f64)
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<f64, E> {
+                Ok(v as f64)
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<f64, E> {
+                match v {
+                    NAN => Ok(f64::NAN),
+                    INFINITY => Ok(f64::INFINITY),
+                    NEG_INFINITY => Ok(f64::NEG_INFINITY),
+                    other => Err(E::invalid_value(de::Unexpected::Str(other), &self)),
+                }
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<f64, E> {
+                Ok(f64::NAN)
+            }
+
+            fn visit_none<E: de::Error>(self) -> Result<f64, E> {
+                Ok(f64::NAN)
+            }
+        }
+
+        deserializer
+            .deserialize_any(FloatVisitor)
+            .map(|v| NonFinite(T::from_f64(v)))
+    }
+}
+
+/// `#[serde(with = "non_finite::vec")]` for `Vec<f64>` and `Vec<f32>`.
+pub mod vec {
+    use super::*;
+
+    pub fn serialize<T: Float, S: Serializer>(
+        values: &[T],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(|&v| NonFinite(v)))
+    }
+
+    pub fn deserialize<'de, T: Float, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<T>, D::Error> {
+        let values = Vec::<NonFinite<T>>::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|v| v.0).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Samples {
+        pairs: Vec<(NonFinite<f64>, NonFinite<f64>)>,
+        #[serde(with = "vec")]
+        floats: Vec<f32>,
+    }
+
+    fn samples() -> Samples {
+        Samples {
+            pairs: [(0.0, f64::NAN), (1.0, f64::INFINITY), (2.0, -1.5)]
+                .map(|(t, v)| (NonFinite(t), NonFinite(v)))
+                .to_vec(),
+            floats: vec![f32::NEG_INFINITY, 0.25],
+        }
+    }
+
+    fn assert_samples(read: &Samples) {
+        assert!(read.pairs[0].1 .0.is_nan());
+        assert_eq!(read.pairs[1], (NonFinite(1.0), NonFinite(f64::INFINITY)));
+        assert_eq!(read.pairs[2], (NonFinite(2.0), NonFinite(-1.5)));
+        assert_eq!(read.floats, vec![f32::NEG_INFINITY, 0.25]);
+    }
+
+    #[test]
+    fn json_spells_out_non_finite_values() {
+        let json = serde_json::to_string(&samples()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"pairs":[[0.0,"NaN"],[1.0,"Infinity"],[2.0,-1.5]],"floats":["-Infinity",0.25]}"#
+        );
+        assert_samples(&serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn binary_formats_keep_floats() {
+        let bytes = rmp_serde::to_vec_named(&samples()).unwrap();
+        assert!(!bytes.windows(3).any(|w| w == b"NaN"));
+        assert_samples(&rmp_serde::from_slice(&bytes).unwrap());
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&samples(), &mut bytes).unwrap();
+        assert_samples(&ciborium::from_reader(bytes.as_slice()).unwrap());
+    }
+
+    #[test]
+    fn null_reads_as_nan() {
+        let read: Samples =
+            serde_json::from_str(r#"{"pairs":[[0,null]],"floats":[null]}"#).unwrap();
+        assert!(read.pairs[0].1 .0.is_nan());
+        assert!(read.floats[0].is_nan());
+        assert!(serde_json::from_str::<Samples>(r#"{"pairs":[[0,"nan"]],"floats":[]}"#).is_err());
+    }
+}
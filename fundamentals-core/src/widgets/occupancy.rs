@@ -0,0 +1,571 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+/// Cell value of an unexplored cell, as in ROS `nav_msgs/OccupancyGrid`. Known
+/// cells hold an occupancy probability from 0 to 100.
+pub const UNKNOWN: i8 = -1;
+
+/// Cell values of a grid, row-major from the grid origin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CellEncoding {
+    Raw(Vec<i8>),
+    /// `(count, value)` runs. Maps are mostly unknown or free space, so this is
+    /// usually far smaller than `Raw`.
+    RunLength(Vec<(u32, i8)>),
+}
+
+impl CellEncoding {
+    pub fn run_length(cells: &[i8]) -> Self {
+        let mut runs: Vec<(u32, i8)> = Vec::new();
+        for &cell in cells {
+            match runs.last_mut() {
+                Some((count, value)) if *value == cell && *count < u32::MAX => *count += 1,
+                _ => runs.push((1, cell)),
+            }
+        }
+        CellEncoding::RunLength(runs)
+    }
+
+    /// Run-length encoding if it is smaller, raw cells otherwise.
+    pub fn compact(cells: &[i8]) -> Self {
+        match Self::run_length(cells) {
+            // A run takes about as much space as three raw cells
+            CellEncoding::RunLength(runs) if runs.len() * 3 < cells.len() => {
+                CellEncoding::RunLength(runs)
+            }
+            _ => CellEncoding::Raw(cells.to_vec()),
+        }
+    }
+
+    /// Number of cells encoded.
+    pub fn len(&self) -> u64 {
+        match self {
+            CellEncoding::Raw(cells) => cells.len() as u64,
+            CellEncoding::RunLength(runs) => runs.iter().map(|&(count, _)| count as u64).sum(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Checks that the encoding holds exactly the cells of a `width` x `height`
+    /// area.
+    fn check_size(&self, width: usize, height: usize) -> Result<(), anyhow::Error> {
+        let expected = width
+            .checked_mul(height)
+            .ok_or_else(|| anyhow::anyhow!("{}x{} grid is too large", width, height))?;
+        if self.len() != expected as u64 {
+            return Err(anyhow::anyhow!(
+                "{}x{} grid needs {} cells, got {}",
+                width,
+                height,
+                expected,
+                self.len()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn decode(&self) -> Vec<i8> {
+        match self {
+            CellEncoding::Raw(cells) => cells.clone(),
+            CellEncoding::RunLength(runs) => runs
+                .iter()
+                .flat_map(|&(count, value)| std::iter::repeat_n(value, count as usize))
+                .collect(),
+        }
+    }
+}
+
+/// Where cell (0, 0) of a grid sits in the 3D scene; cells extend along the
+/// grid's x and y axes, rotated by `yaw` around z.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct GridOrigin {
+    pub x: f64,
+    pub y: f64,
+    #[serde(default)]
+    pub z: f64,
+    #[serde(default)]
+    pub yaw: f64,
+}
+
+/// A complete 2D occupancy grid.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "OccupancyGridParts")]
+pub struct OccupancyGrid {
+    /// Cell size in meters.
+    pub resolution: f64,
+    pub width: usize,
+    pub height: usize,
+    pub origin: GridOrigin,
+    pub cells: CellEncoding,
+}
+
+/// Unchecked `OccupancyGrid`, so deserialized grids hold `width * height` cells.
+#[derive(Deserialize)]
+struct OccupancyGridParts {
+    resolution: f64,
+    width: usize,
+    height: usize,
+    origin: GridOrigin,
+    cells: CellEncoding,
+}
+
+impl TryFrom<OccupancyGridParts> for OccupancyGrid {
+    type Error = anyhow::Error;
+
+    fn try_from(parts: OccupancyGridParts) -> Result<Self, Self::Error> {
+        parts.cells.check_size(parts.width, parts.height)?;
+        Ok(Self {
+            resolution: parts.resolution,
+            width: parts.width,
+            height: parts.height,
+            origin: parts.origin,
+            cells: parts.cells,
+        })
+    }
+}
+
+impl OccupancyGrid {
+    pub fn new(
+        resolution: f64,
+        width: usize,
+        height: usize,
+        origin: GridOrigin,
+        cells: &[i8],
+    ) -> Result<Self, anyhow::Error> {
+        let cells = CellEncoding::compact(cells);
+        cells.check_size(width, height)?;
+        Ok(Self {
+            resolution,
+            width,
+            height,
+            origin,
+            cells,
+        })
+    }
+
+    /// Whether `other` covers the same cells, so it can be sent as a patch.
+    pub fn same_layout(&self, other: &OccupancyGrid) -> bool {
+        self.resolution == other.resolution
+            && self.width == other.width
+            && self.height == other.height
+            && self.origin == other.origin
+    }
+
+    /// Writes `update` into this grid. Parts of the patch outside the grid are
+    /// ignored.
+    pub fn apply(&mut self, update: &GridUpdate) {
+        let mut cells = self.cells.decode();
+        update.write_into(&mut cells, self.width, self.height);
+        self.cells = CellEncoding::compact(&cells);
+    }
+
+    /// The smallest patch turning this grid into `next`, or `None` if they are
+    /// identical. Both grids must have the same layout.
+    pub fn diff(&self, next: &OccupancyGrid) -> Option<GridUpdate> {
+        let (old, new) = (self.cells.decode(), next.cells.decode());
+        let (mut x0, mut y0, mut x1, mut y1) = (usize::MAX, usize::MAX, 0, 0);
+        for (i, (a, b)) in old.iter().zip(&new).enumerate() {
+            if a != b {
+                let (x, y) = (i % self.width, i / self.width);
+                (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x), y1.max(y));
+            }
+        }
+        if x0 == usize::MAX {
+            return None;
+        }
+        let (width, height) = (x1 - x0 + 1, y1 - y0 + 1);
+        let patch: Vec<i8> = (y0..=y1)
+            .flat_map(|y| {
+                new[y * self.width + x0..y * self.width + x0 + width]
+                    .iter()
+                    .copied()
+            })
+            .collect();
+        Some(GridUpdate {
+            x: x0,
+            y: y0,
+            width,
+            height,
+            cells: CellEncoding::compact(&patch),
+        })
+    }
+}
+
+/// Rectangular patch of changed cells, applied to the most recent full grid of the
+/// same view.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "GridUpdateParts")]
+pub struct GridUpdate {
+    /// Column of the patch's first cell.
+    pub x: usize,
+    /// Row of the patch's first cell.
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub cells: CellEncoding,
+}
+
+/// Unchecked `GridUpdate`, so deserialized patches hold `width * height` cells.
+#[derive(Deserialize)]
+struct GridUpdateParts {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    cells: CellEncoding,
+}
+
+impl TryFrom<GridUpdateParts> for GridUpdate {
+    type Error = anyhow::Error;
+
+    fn try_from(parts: GridUpdateParts) -> Result<Self, Self::Error> {
+        parts.cells.check_size(parts.width, parts.height)?;
+        Ok(Self {
+            x: parts.x,
+            y: parts.y,
+            width: parts.width,
+            height: parts.height,
+            cells: parts.cells,
+        })
+    }
+}
+
+impl GridUpdate {
+    /// Writes the patch into the decoded cells of a `width` x `height` grid.
+    pub fn write_into(&self, cells: &mut [i8], width: usize, height: usize) {
+        let patch = self.cells.decode();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let (x, y) = (self.x + col, self.y + row);
+                if x < width && y < height {
+                    if let (Some(&value), Some(cell)) = (
+                        patch.get(row * self.width + col),
+                        cells.get_mut(y * width + x),
+                    ) {
+                        *cell = value;
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub type Voxel = (i32, i32, i32);
+
+/// Occupied voxels, as integer coordinates in units of the grid resolution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", try_from = "VoxelEncodingParts")]
+pub enum VoxelEncoding {
+    Sparse(Vec<Voxel>),
+    /// One bit per voxel of the box starting at `min` with size `dims`, x fastest,
+    /// packed into 32-bit words (so the values stay exact in JavaScript).
+    Bitset {
+        min: Voxel,
+        dims: (u32, u32, u32),
+        bits: Vec<u32>,
+    },
+}
+
+/// Unchecked `VoxelEncoding`, so deserialized bitsets have one bit per voxel of a
+/// box that fits in voxel coordinates.
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum VoxelEncodingParts {
+    Sparse(Vec<Voxel>),
+    Bitset {
+        min: Voxel,
+        dims: (u32, u32, u32),
+        bits: Vec<u32>,
+    },
+}
+
+impl TryFrom<VoxelEncodingParts> for VoxelEncoding {
+    type Error = anyhow::Error;
+
+    fn try_from(parts: VoxelEncodingParts) -> Result<Self, Self::Error> {
+        let (min, dims, bits) = match parts {
+            VoxelEncodingParts::Sparse(voxels) => return Ok(VoxelEncoding::Sparse(voxels)),
+            VoxelEncodingParts::Bitset { min, dims, bits } => (min, dims, bits),
+        };
+        let fits = |min: i32, size: u32| min as i64 + size as i64 - 1 <= i32::MAX as i64;
+        if !(fits(min.0, dims.0) && fits(min.1, dims.1) && fits(min.2, dims.2)) {
+            return Err(anyhow::anyhow!(
+                "Voxel box at {:?} of size {:?} is out of range",
+                min,
+                dims
+            ));
+        }
+        let words = (dims.0 as u64)
+            .checked_mul(dims.1 as u64)
+            .and_then(|area| area.checked_mul(dims.2 as u64))
+            .map(|volume| volume.div_ceil(32));
+        if words != Some(bits.len() as u64) {
+            return Err(anyhow::anyhow!(
+                "Voxel box of size {:?} does not match {} bitset words",
+                dims,
+                bits.len()
+            ));
+        }
+        Ok(VoxelEncoding::Bitset { min, dims, bits })
+    }
+}
+
+impl VoxelEncoding {
+    /// Whichever of the sparse list and the bitset is smaller.
+    pub fn compact(voxels: &BTreeSet<Voxel>) -> Self {
+        let (Some(first), Some(_)) = (voxels.first(), voxels.last()) else {
+            return VoxelEncoding::Sparse(Vec::new());
+        };
+        let (mut min, mut max) = (*first, *first);
+        for &(x, y, z) in voxels {
+            min = (min.0.min(x), min.1.min(y), min.2.min(z));
+            max = (max.0.max(x), max.1.max(y), max.2.max(z));
+        }
+        // Spans reach 2^32 for voxels at both ends of the i32 range
+        let span = |min: i32, max: i32| (max as i64 - min as i64 + 1) as u64;
+        let spans = (span(min.0, max.0), span(min.1, max.1), span(min.2, max.2));
+        let volume = spans
+            .0
+            .checked_mul(spans.1)
+            .and_then(|area| area.checked_mul(spans.2));
+        let dims = (
+            u32::try_from(spans.0),
+            u32::try_from(spans.1),
+            u32::try_from(spans.2),
+        );
+        let (Ok(dx), Ok(dy), Ok(dz), Some(volume)) = (dims.0, dims.1, dims.2, volume) else {
+            return VoxelEncoding::Sparse(voxels.iter().copied().collect());
+        };
+        // Each sparse voxel costs three numbers, each bitset word covers 32 voxels
+        if volume / 32 >= voxels.len() as u64 * 3 {
+            return VoxelEncoding::Sparse(voxels.iter().copied().collect());
+        }
+
+        let mut bits = vec![0u32; volume.div_ceil(32) as usize];
+        for &(x, y, z) in voxels {
+            let offset = |v: i32, min: i32| (v as i64 - min as i64) as u64;
+            let index =
+                (offset(z, min.2) * spans.1 + offset(y, min.1)) * spans.0 + offset(x, min.0);
+            bits[(index / 32) as usize] |= 1 << (index % 32);
+        }
+        VoxelEncoding::Bitset {
+            min,
+            dims: (dx, dy, dz),
+            bits,
+        }
+    }
+
+    pub fn decode(&self) -> BTreeSet<Voxel> {
+        match self {
+            VoxelEncoding::Sparse(voxels) => voxels.iter().copied().collect(),
+            VoxelEncoding::Bitset { min, dims, bits } => {
+                let (dx, dy) = (dims.0 as u64, dims.1 as u64);
+                let volume = dx * dy * dims.2 as u64;
+                let mut voxels = BTreeSet::new();
+                for (word_index, &word) in bits.iter().enumerate() {
+                    let mut word = word;
+                    // Only the set bits, lowest first
+                    while word != 0 {
+                        let i = word_index as u64 * 32 + word.trailing_zeros() as u64;
+                        word &= word - 1;
+                        if i >= volume {
+                            break;
+                        }
+                        voxels.insert((
+                            (min.0 as i64 + (i % dx) as i64) as i32,
+                            (min.1 as i64 + (i / dx % dy) as i64) as i32,
+                            (min.2 as i64 + (i / (dx * dy)) as i64) as i32,
+                        ));
+                    }
+                }
+                voxels
+            }
+        }
+    }
+}
+
+/// A complete sparse voxel map.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoxelGrid {
+    /// Voxel edge length in meters.
+    pub resolution: f64,
+    /// Position of voxel (0, 0, 0).
+    pub origin: (f64, f64, f64),
+    pub voxels: VoxelEncoding,
+}
+
+impl VoxelGrid {
+    pub fn new(resolution: f64, origin: (f64, f64, f64), voxels: &BTreeSet<Voxel>) -> Self {
+        Self {
+            resolution,
+            origin,
+            voxels: VoxelEncoding::compact(voxels),
+        }
+    }
+
+    pub fn apply(&mut self, update: &VoxelUpdate) {
+        let mut voxels = self.voxels.decode();
+        for voxel in update.cleared.iter() {
+            voxels.remove(voxel);
+        }
+        voxels.extend(update.set.iter().copied());
+        self.voxels = VoxelEncoding::compact(&voxels);
+    }
+}
+
+/// Voxels that became occupied or free since the previous state, applied to the
+/// most recent full voxel grid of the same view.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct VoxelUpdate {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub set: Vec<Voxel>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cleared: Vec<Voxel>,
+}
+
+impl VoxelUpdate {
+    pub fn between(previous: &BTreeSet<Voxel>, next: &BTreeSet<Voxel>) -> Self {
+        Self {
+            set: next.difference(previous).copied().collect(),
+            cleared: previous.difference(next).copied().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.cleared.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(cells: &[i8]) -> OccupancyGrid {
+        OccupancyGrid::new(0.5, 4, 3, GridOrigin::default(), cells).unwrap()
+    }
+
+    #[test]
+    fn cells_use_the_smaller_encoding() {
+        let free = vec![0; 100];
+        let encoded = CellEncoding::compact(&free);
+        assert_eq!(encoded, CellEncoding::RunLength(vec![(100, 0)]));
+        assert_eq!(encoded.decode(), free);
+        assert_eq!(encoded.len(), 100);
+
+        let noisy: Vec<i8> = (0..100).map(|i| (i % 7) as i8).collect();
+        let encoded = CellEncoding::compact(&noisy);
+        assert!(matches!(encoded, CellEncoding::Raw(_)));
+        assert_eq!(encoded.decode(), noisy);
+
+        let runs = CellEncoding::run_length(&[UNKNOWN, UNKNOWN, 100, 0, 0]);
+        assert_eq!(
+            runs,
+            CellEncoding::RunLength(vec![(2, UNKNOWN), (1, 100), (2, 0)])
+        );
+        assert_eq!(runs.decode(), vec![UNKNOWN, UNKNOWN, 100, 0, 0]);
+    }
+
+    #[test]
+    fn diff_is_the_smallest_patch() {
+        let old = grid(&[0; 12]);
+        let mut cells = vec![0; 12];
+        cells[5] = 100; // (1, 1)
+        cells[10] = UNKNOWN; // (2, 2)
+        let new = grid(&cells);
+
+        let update = old.diff(&new).unwrap();
+        assert_eq!(
+            (update.x, update.y, update.width, update.height),
+            (1, 1, 2, 2)
+        );
+        assert_eq!(update.cells.decode(), vec![100, 0, 0, UNKNOWN]);
+        let mut patched = old.clone();
+        patched.apply(&update);
+        assert_eq!(patched, new);
+        assert_eq!(new.diff(&new), None);
+    }
+
+    #[test]
+    fn patches_outside_the_grid_are_clipped() {
+        let update = GridUpdate {
+            x: 3,
+            y: 2,
+            width: 2,
+            height: 2,
+            cells: CellEncoding::Raw(vec![1, 2, 3, 4]),
+        };
+        let mut cells = vec![0; 12];
+        update.write_into(&mut cells, 4, 3);
+        assert_eq!(cells[11], 1);
+        assert_eq!(cells.iter().filter(|&&c| c != 0).count(), 1);
+    }
+
+    #[test]
+    fn grids_with_the_wrong_number_of_cells_are_rejected() {
+        assert!(OccupancyGrid::new(0.5, 4, 3, GridOrigin::default(), &[0; 11]).is_err());
+        let mut json = serde_json::to_value(grid(&[0; 12])).unwrap();
+        json["cells"] = serde_json::json!({ "run_length": [[11, 0]] });
+        assert!(serde_json::from_value::<OccupancyGrid>(json.clone()).is_err());
+        json["cells"] = serde_json::json!({ "run_length": [[4000000000u32, 0]] });
+        assert!(serde_json::from_value::<OccupancyGrid>(json).is_err());
+        let update = serde_json::json!({
+            "x": 0, "y": 0, "width": 2, "height": 2, "cells": { "raw": [1, 2, 3] }
+        });
+        assert!(serde_json::from_value::<GridUpdate>(update).is_err());
+    }
+
+    #[test]
+    fn dense_voxels_use_a_bitset_and_scattered_ones_a_list() {
+        let block: BTreeSet<Voxel> = (0..4)
+            .flat_map(|x| (0..4).flat_map(move |y| (-2..2).map(move |z| (x, y, z))))
+            .filter(|&(x, y, z)| (x + y + z) % 3 != 0)
+            .collect();
+        let encoded = VoxelEncoding::compact(&block);
+        let VoxelEncoding::Bitset { min, dims, bits } = &encoded else {
+            panic!("expected a bitset, got {:?}", encoded);
+        };
+        assert_eq!((*min, *dims, bits.len()), ((0, 0, -2), (4, 4, 4), 2));
+        assert_eq!(encoded.decode(), block);
+
+        let scattered: BTreeSet<Voxel> = [(0, 0, 0), (100, 0, 0), (0, 100, 0)].into();
+        let encoded = VoxelEncoding::compact(&scattered);
+        assert!(matches!(encoded, VoxelEncoding::Sparse(_)));
+        assert_eq!(encoded.decode(), scattered);
+    }
+
+    #[test]
+    fn voxels_at_the_ends_of_the_range_stay_sparse() {
+        let extremes: BTreeSet<Voxel> = [(i32::MIN, 0, 0), (i32::MAX, i32::MAX, i32::MIN)].into();
+        let encoded = VoxelEncoding::compact(&extremes);
+        assert!(matches!(encoded, VoxelEncoding::Sparse(_)));
+        assert_eq!(encoded.decode(), extremes);
+
+        let corner: BTreeSet<Voxel> = [(i32::MAX, i32::MAX, i32::MAX)].into();
+        assert_eq!(VoxelEncoding::compact(&corner).decode(), corner);
+    }
+
+    #[test]
+    fn bitsets_must_match_their_box() {
+        let parse = |json: serde_json::Value| serde_json::from_value::<VoxelEncoding>(json);
+        let bitset = |min: [i64; 3], dims: [u32; 3], words: usize| serde_json::json!({ "bitset": { "min": min, "dims": dims, "bits": vec![u32::MAX; words] } });
+        let decoded = parse(bitset([0, 0, 0], [4, 4, 4], 2)).unwrap().decode();
+        assert_eq!(decoded.len(), 64);
+        // Bits past the box are ignored
+        assert_eq!(
+            parse(bitset([0, 0, 0], [3, 1, 1], 1))
+                .unwrap()
+                .decode()
+                .len(),
+            3
+        );
+
+        assert!(parse(bitset([0, 0, 0], [4, 4, 4], 1)).is_err());
+        assert!(parse(bitset([0, 0, 0], [u32::MAX, u32::MAX, u32::MAX], 0)).is_err());
+        assert!(parse(bitset([i32::MAX as i64, 0, 0], [2, 1, 1], 1)).is_err());
+    }
+}
@@ -1,12 +1,205 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use super::{
+    non_finite::NonFinite,
+    precision::{Precision, TypedBuffer},
+};
+
+/// Samples as `(time, value)` pairs. In memory the values are always `f64`, already
+/// rounded to `precision`; only recordings and the bridge wire format hold them in a
+/// `TypedBuffer`. A reduced precision therefore makes files and messages smaller but
+/// leaves memory use unchanged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(try_from = "PlotScalarRepr", into = "PlotScalarRepr")]
 pub struct PlotScalarData {
     pub data_x: Vec<(f64, f64)>,
+    /// How the values are stored and sent; times are always `f64`.
+    pub precision: Precision,
+}
+
+/// `F64` data keeps the original `data_x` pairs; other precisions store the times
+/// and a typed buffer of values side by side.
+#[derive(Serialize, Deserialize)]
+struct PlotScalarRepr {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data_x: Option<Vec<(NonFinite<f64>, NonFinite<f64>)>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    times: Option<Vec<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    values: Option<TypedBuffer>,
+}
+
+impl From<PlotScalarData> for PlotScalarRepr {
+    fn from(data: PlotScalarData) -> Self {
+        if data.precision.is_f64() {
+            let data_x = data.data_x.into_iter();
+            return Self {
+                data_x: Some(data_x.map(|(t, y)| (NonFinite(t), NonFinite(y))).collect()),
+                times: None,
+                values: None,
+            };
+        }
+        Self {
+            data_x: None,
+            times: Some(data.data_x.iter().map(|(x, _)| *x).collect()),
+            values: Some(data.precision.encode(data.data_x.iter().map(|(_, y)| *y))),
+        }
+    }
+}
+
+impl TryFrom<PlotScalarRepr> for PlotScalarData {
+    type Error = String;
+
+    fn try_from(repr: PlotScalarRepr) -> Result<Self, Self::Error> {
+        let Some(values) = repr.values else {
+            let data_x = repr.data_x.unwrap_or_default().into_iter();
+            return Ok(Self::new(data_x.map(|(t, y)| (t.0, y.0)).collect()));
+        };
+        let times = repr.times.unwrap_or_default();
+        if times.len() != values.len() {
+            return Err(format!("{} times but {} values", times.len(), values.len()));
+        }
+        Ok(Self {
+            data_x: times.into_iter().zip(values.decode()).collect(),
+            precision: values.precision(),
+        })
+    }
 }
 
 impl PlotScalarData {
     pub fn new(data_x: Vec<(f64, f64)>) -> Self {
-        Self { data_x }
+        Self {
+            data_x,
+            precision: Precision::F64,
+        }
+    }
+
+    /// Stores the values with `precision` when saved or sent. They are rounded right
+    /// away, so the data reads the same before and after saving, but stay `f64` in
+    /// memory.
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        if !precision.is_f64() {
+            self.data_x
+                .iter_mut()
+                .for_each(|(_, y)| *y = precision.round(*y));
+        }
+        self.precision = precision;
+        self
+    }
+
+    /// Samples with `t0 <= x <= t1`, assuming `data_x` is sorted by x.
+    pub fn range(&self, t0: f64, t1: f64) -> &[(f64, f64)] {
+        let start = self.data_x.partition_point(|(x, _)| *x < t0);
+        let end = self.data_x.partition_point(|(x, _)| *x <= t1);
+        &self.data_x[start..end.max(start)]
+    }
+
+    /// Copy of the data restricted to `t0 <= x <= t1`.
+    pub fn slice(&self, t0: f64, t1: f64) -> Self {
+        Self {
+            data_x: self.range(t0, t1).to_vec(),
+            precision: self.precision,
+        }
+    }
+
+    /// Most recent sample with `x <= t`.
+    pub fn latest_at(&self, t: f64) -> Option<(f64, f64)> {
+        let end = self.data_x.partition_point(|(x, _)| *x <= t);
+        end.checked_sub(1).map(|i| self.data_x[i])
+    }
+
+    /// Value at `t` linearly interpolated between the neighbouring samples. `None`
+    /// outside the sampled range.
+    pub fn interpolate(&self, t: f64) -> Option<f64> {
+        let end = self.data_x.partition_point(|(x, _)| *x < t);
+        let (x1, y1) = *self.data_x.get(end)?;
+        if x1 == t {
+            return Some(y1);
+        }
+        let (x0, y0) = self.data_x[end.checked_sub(1)?];
+        Some(y0 + (y1 - y0) * (t - x0) / (x1 - x0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples() -> Vec<(f64, f64)> {
+        vec![
+            (0.0, 1.0),
+            (0.5, -2.25),
+            (1.0, 0.1),
+            (1.5, 2.9),
+            (2.0, f64::NAN),
+        ]
+    }
+
+    fn round_trip(data: &PlotScalarData) -> PlotScalarData {
+        serde_json::from_str(&serde_json::to_string(data).unwrap()).unwrap()
+    }
+
+    fn same(a: &[(f64, f64)], b: &[(f64, f64)]) -> bool {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|((x0, y0), (x1, y1))| x0 == x1 && (y0 == y1 || (y0.is_nan() && y1.is_nan())))
+    }
+
+    #[test]
+    fn f64_data_keeps_its_pairs() {
+        let data = PlotScalarData::new(samples());
+        let json = serde_json::to_value(&data).unwrap();
+        assert!(json.get("data_x").is_some());
+        assert!(json.get("values").is_none());
+        assert!(same(&round_trip(&data).data_x, &samples()));
+    }
+
+    #[test]
+    fn f32_values_read_back_as_rounded() {
+        let data = PlotScalarData::new(samples()).with_precision(Precision::F32);
+        assert_eq!(data.data_x[2].1, 0.1f32 as f64);
+        let json = serde_json::to_value(&data).unwrap();
+        assert!(json.get("data_x").is_none());
+        assert_eq!(json["times"].as_array().unwrap().len(), 5);
+        let read = round_trip(&data);
+        assert_eq!(read.precision, Precision::F32);
+        assert!(same(&read.data_x, &data.data_x));
+    }
+
+    #[test]
+    fn i16_values_stay_within_half_a_step() {
+        let precision = Precision::i16_range(-3.0, 3.0);
+        let Precision::I16 { scale, .. } = precision else {
+            unreachable!()
+        };
+        let samples = samples();
+        let data = PlotScalarData::new(samples.clone()).with_precision(precision);
+        let read = round_trip(&data);
+        assert_eq!(read.precision, precision);
+        assert!(same(&read.data_x, &data.data_x));
+        for ((_, original), (_, stored)) in samples.iter().zip(&read.data_x) {
+            if original.is_nan() {
+                assert!(stored.is_nan());
+            } else {
+                assert!((original - stored).abs() <= scale / 2.0);
+            }
+        }
+    }
+
+    #[test]
+    fn i16_clamps_values_out_of_range() {
+        let precision = Precision::I16 {
+            scale: 1.0,
+            offset: 0.0,
+        };
+        assert_eq!(precision.round(1e9), 32767.0);
+        assert_eq!(precision.round(-1e9), -32767.0);
+    }
+
+    #[test]
+    fn mismatched_times_and_values_are_rejected() {
+        let json = r#"{"times":[0.0,1.0],"values":{"f32":[1.0]}}"#;
+        assert!(serde_json::from_str::<PlotScalarData>(json).is_err());
     }
 }
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+
+use super::non_finite;
+
+/// How sample values are stored in recordings and sent to viewers. Sensor data is
+/// often natively `f32` or `i16`, so `f64` would double or quadruple its size.
+///
+/// For scalar plots this is a wire and on-disk change only: `PlotScalarData` keeps
+/// its values as `f64` in memory whatever the precision, so it does not shrink a
+/// plotter's or the bridge's memory use. `TypedPoint` point clouds do keep their
+/// `TypedBuffer` in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Precision {
+    #[default]
+    F64,
+    F32,
+    /// 16-bit integers `q` standing for `q * scale + offset`. Values outside the
+    /// representable range are clamped.
+    I16 {
+        scale: f64,
+        offset: f64,
+    },
+}
+
+/// Reserved for NaN in `I16` buffers.
+const I16_NAN: i16 = i16::MIN;
+
+impl Precision {
+    /// `I16` with the finest step that still covers `min..=max`.
+    pub fn i16_range(min: f64, max: f64) -> Self {
+        let span = (max - min).abs();
+        Precision::I16 {
+            scale: if span > 0.0 { span / 65534.0 } else { 1.0 },
+            offset: (min + max) / 2.0,
+        }
+    }
+
+    pub fn is_f64(&self) -> bool {
+        *self == Precision::F64
+    }
+
+    /// `value` as it reads back after being stored with this precision.
+    pub fn round(&self, value: f64) -> f64 {
+        match *self {
+            Precision::F64 => value,
+            Precision::F32 => value as f32 as f64,
+            Precision::I16 { scale, offset } => {
+                dequantize(quantize(value, scale, offset), scale, offset)
+            }
+        }
+    }
+
+    pub fn encode(&self, values: impl IntoIterator<Item = f64>) -> TypedBuffer {
+        let values = values.into_iter();
+        match *self {
+            Precision::F64 => TypedBuffer::F64(values.collect()),
+            Precision::F32 => TypedBuffer::F32(values.map(|v| v as f32).collect()),
+            Precision::I16 { scale, offset } => TypedBuffer::I16 {
+                scale,
+                offset,
+                values: values.map(|v| quantize(v, scale, offset)).collect(),
+            },
+        }
+    }
+
+    /// A precision that holds values stored with either `self` or `other` without
+    /// further loss.
+    pub fn common(self, other: Precision) -> Precision {
+        if self == other {
+            self
+        } else {
+            Precision::F64
+        }
+    }
+}
+
+fn quantize(value: f64, scale: f64, offset: f64) -> i16 {
+    if value.is_nan() {
+        return I16_NAN;
+    }
+    ((value - offset) / scale).round().clamp(-32767.0, 32767.0) as i16
+}
+
+fn dequantize(q: i16, scale: f64, offset: f64) -> f64 {
+    if q == I16_NAN {
+        f64::NAN
+    } else {
+        q as f64 * scale + offset
+    }
+}
+
+/// Values stored with a `Precision`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TypedBuffer {
+    F64(#[serde(with = "non_finite::vec")] Vec<f64>),
+    F32(#[serde(with = "non_finite::vec")] Vec<f32>),
+    I16 {
+        scale: f64,
+        offset: f64,
+        values: Vec<i16>,
+    },
+}
+
+impl TypedBuffer {
+    pub fn precision(&self) -> Precision {
+        match *self {
+            TypedBuffer::F64(_) => Precision::F64,
+            TypedBuffer::F32(_) => Precision::F32,
+            TypedBuffer::I16 { scale, offset, .. } => Precision::I16 { scale, offset },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            TypedBuffer::F64(values) => values.len(),
+            TypedBuffer::F32(values) => values.len(),
+            TypedBuffer::I16 { values, .. } => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn decode(&self) -> Vec<f64> {
+        match self {
+            TypedBuffer::F64(values) => values.clone(),
+            TypedBuffer::F32(values) => values.iter().map(|&v| v as f64).collect(),
+            TypedBuffer::I16 {
+                scale,
+                offset,
+                values,
+            } => values
+                .iter()
+                .map(|&q| dequantize(q, *scale, *offset))
+                .collect(),
+        }
+    }
+}
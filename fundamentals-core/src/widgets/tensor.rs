@@ -0,0 +1,385 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+/// Contiguous, row-major element storage. The variant is the dtype.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TensorBuffer {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+    I8(Vec<i8>),
+    I16(Vec<i16>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+/// Applies `$body` to the vec inside any buffer variant, binding it as `$v`, and
+/// wraps the result back into the same variant.
+macro_rules! map_buffer {
+    ($buffer:expr, $v:ident => $body:expr) => {
+        match $buffer {
+            TensorBuffer::U8($v) => TensorBuffer::U8($body),
+            TensorBuffer::U16($v) => TensorBuffer::U16($body),
+            TensorBuffer::U32($v) => TensorBuffer::U32($body),
+            TensorBuffer::U64($v) => TensorBuffer::U64($body),
+            TensorBuffer::I8($v) => TensorBuffer::I8($body),
+            TensorBuffer::I16($v) => TensorBuffer::I16($body),
+            TensorBuffer::I32($v) => TensorBuffer::I32($body),
+            TensorBuffer::I64($v) => TensorBuffer::I64($body),
+            TensorBuffer::F32($v) => TensorBuffer::F32($body),
+            TensorBuffer::F64($v) => TensorBuffer::F64($body),
+        }
+    };
+}
+
+/// Like `map_buffer!` but returns `$body` as is.
+macro_rules! with_buffer {
+    ($buffer:expr, $v:ident => $body:expr) => {
+        match $buffer {
+            TensorBuffer::U8($v) => $body,
+            TensorBuffer::U16($v) => $body,
+            TensorBuffer::U32($v) => $body,
+            TensorBuffer::U64($v) => $body,
+            TensorBuffer::I8($v) => $body,
+            TensorBuffer::I16($v) => $body,
+            TensorBuffer::I32($v) => $body,
+            TensorBuffer::I64($v) => $body,
+            TensorBuffer::F32($v) => $body,
+            TensorBuffer::F64($v) => $body,
+        }
+    };
+}
+
+impl TensorBuffer {
+    pub fn dtype(&self) -> DType {
+        match self {
+            TensorBuffer::U8(_) => DType::U8,
+            TensorBuffer::U16(_) => DType::U16,
+            TensorBuffer::U32(_) => DType::U32,
+            TensorBuffer::U64(_) => DType::U64,
+            TensorBuffer::I8(_) => DType::I8,
+            TensorBuffer::I16(_) => DType::I16,
+            TensorBuffer::I32(_) => DType::I32,
+            TensorBuffer::I64(_) => DType::I64,
+            TensorBuffer::F32(_) => DType::F32,
+            TensorBuffer::F64(_) => DType::F64,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        with_buffer!(self, v => v.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The cast is a no-op for the f64 variant
+    #[allow(clippy::unnecessary_cast)]
+    pub fn get_f64(&self, index: usize) -> Option<f64> {
+        with_buffer!(self, v => v.get(index).map(|x| *x as f64))
+    }
+
+    /// Elements at `indices`, in order, as a buffer of the same dtype.
+    pub fn gather(&self, indices: &[usize]) -> Result<TensorBuffer, anyhow::Error> {
+        if let Some(i) = indices.iter().find(|&&i| i >= self.len()) {
+            return Err(anyhow::anyhow!(
+                "Index {} out of bounds for {} elements",
+                i,
+                self.len()
+            ));
+        }
+        Ok(map_buffer!(self, v => indices.iter().map(|&i| v[i]).collect()))
+    }
+}
+
+/// Element types a `TensorBuffer` can hold.
+pub trait TensorElement: Copy {
+    fn into_buffer(values: Vec<Self>) -> TensorBuffer;
+}
+
+macro_rules! tensor_element {
+    ($($ty:ty => $variant:ident),*) => {
+        $(impl TensorElement for $ty {
+            fn into_buffer(values: Vec<Self>) -> TensorBuffer {
+                TensorBuffer::$variant(values)
+            }
+        })*
+    };
+}
+
+tensor_element!(
+    u8 => U8, u16 => U16, u32 => U32, u64 => U64,
+    i8 => I8, i16 => I16, i32 => I32, i64 => I64,
+    f32 => F32, f64 => F64
+);
+
+/// Selection along one dimension of a tensor.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DimSlice {
+    All,
+    /// A single index; the dimension is dropped from the result.
+    Index(usize),
+    /// `start..end` every `step` elements. `end` is clamped to the dimension size.
+    Range {
+        start: usize,
+        end: usize,
+        #[serde(default = "default_step")]
+        step: usize,
+    },
+}
+
+fn default_step() -> usize {
+    1
+}
+
+impl DimSlice {
+    fn indices(&self, size: usize) -> Result<Vec<usize>, anyhow::Error> {
+        match *self {
+            DimSlice::All => Ok((0..size).collect()),
+            DimSlice::Index(i) if i < size => Ok(vec![i]),
+            DimSlice::Index(i) => Err(anyhow::anyhow!("Index {} out of bounds for {}", i, size)),
+            DimSlice::Range { start, end, step } => {
+                Ok((start..end.min(size)).step_by(step.max(1)).collect())
+            }
+        }
+    }
+}
+
+/// An n-dimensional array: a cost map, occupancy grid, attention matrix, ...
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "TensorParts")]
+pub struct Tensor {
+    pub shape: Vec<usize>,
+    pub buffer: TensorBuffer,
+}
+
+/// Unchecked `Tensor`, so deserialized tensors go through `Tensor::new`.
+#[derive(Deserialize)]
+struct TensorParts {
+    shape: Vec<usize>,
+    buffer: TensorBuffer,
+}
+
+impl TryFrom<TensorParts> for Tensor {
+    type Error = anyhow::Error;
+
+    fn try_from(parts: TensorParts) -> Result<Self, Self::Error> {
+        Tensor::new(parts.shape, parts.buffer)
+    }
+}
+
+impl Tensor {
+    pub fn new(shape: Vec<usize>, buffer: TensorBuffer) -> Result<Self, anyhow::Error> {
+        let expected = shape
+            .iter()
+            .try_fold(1usize, |product, &size| product.checked_mul(size))
+            .ok_or_else(|| anyhow::anyhow!("Shape {:?} is too large", shape))?;
+        if expected != buffer.len() {
+            return Err(anyhow::anyhow!(
+                "Shape {:?} needs {} elements, buffer has {}",
+                shape,
+                expected,
+                buffer.len()
+            ));
+        }
+        Ok(Self { shape, buffer })
+    }
+
+    pub fn from_vec<T: TensorElement>(
+        shape: Vec<usize>,
+        values: Vec<T>,
+    ) -> Result<Self, anyhow::Error> {
+        Self::new(shape, T::into_buffer(values))
+    }
+
+    pub fn dtype(&self) -> DType {
+        self.buffer.dtype()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Row-major strides, in elements.
+    pub fn strides(&self) -> Vec<usize> {
+        let mut strides = vec![1; self.shape.len()];
+        for i in (0..self.shape.len().saturating_sub(1)).rev() {
+            strides[i] = strides[i + 1] * self.shape[i + 1];
+        }
+        strides
+    }
+
+    pub fn get(&self, index: &[usize]) -> Option<f64> {
+        if index.len() != self.shape.len() || index.iter().zip(&self.shape).any(|(i, s)| i >= s) {
+            return None;
+        }
+        let offset = index.iter().zip(self.strides()).map(|(i, s)| i * s).sum();
+        self.buffer.get_f64(offset)
+    }
+
+    /// Sub-tensor selected by one `DimSlice` per dimension. Missing trailing
+    /// dimensions are taken whole.
+    pub fn slice(&self, slices: &[DimSlice]) -> Result<Tensor, anyhow::Error> {
+        if slices.len() > self.shape.len() {
+            return Err(anyhow::anyhow!(
+                "{} slices for a tensor with {} dimensions",
+                slices.len(),
+                self.shape.len()
+            ));
+        }
+        let strides = self.strides();
+        let mut offsets = vec![0];
+        let mut shape = Vec::new();
+        for (dim, &size) in self.shape.iter().enumerate() {
+            let slice = slices.get(dim).copied().unwrap_or(DimSlice::All);
+            let indices = slice.indices(size)?;
+            if !matches!(slice, DimSlice::Index(_)) {
+                shape.push(indices.len());
+            }
+            let stride = strides[dim];
+            offsets = offsets
+                .iter()
+                .flat_map(|offset| indices.iter().map(move |i| offset + i * stride))
+                .collect();
+        }
+        Tensor::new(shape, self.buffer.gather(&offsets)?)
+    }
+
+    /// Every `stride`-th element along each dimension, so that the result holds at
+    /// most about `max_elements`.
+    pub fn downsample(&self, max_elements: usize) -> Tensor {
+        if self.len() <= max_elements || self.shape.is_empty() {
+            return self.clone();
+        }
+        let ratio = self.len() as f64 / max_elements.max(1) as f64;
+        let stride = ratio.powf(1.0 / self.shape.len() as f64).ceil() as usize;
+        let slices: Vec<DimSlice> = self
+            .shape
+            .iter()
+            .map(|&size| DimSlice::Range {
+                start: 0,
+                end: size,
+                step: stride,
+            })
+            .collect();
+        self.slice(&slices).unwrap_or_else(|_| self.clone())
+    }
+}
+
+/// Tensor widget: one tensor per timestamp.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TensorData {
+    pub frames: Vec<(f64, Tensor)>,
+}
+
+impl TensorData {
+    /// Frames with `t0 <= time <= t1`, assuming `frames` is sorted by time.
+    pub fn range(&self, t0: f64, t1: f64) -> &[(f64, Tensor)] {
+        let start = self.frames.partition_point(|(t, _)| *t < t0);
+        let end = self.frames.partition_point(|(t, _)| *t <= t1);
+        &self.frames[start..end.max(start)]
+    }
+
+    /// Copy of the widget restricted to `t0 <= time <= t1`.
+    pub fn slice(&self, t0: f64, t1: f64) -> Self {
+        Self {
+            frames: self.range(t0, t1).to_vec(),
+        }
+    }
+
+    /// Most recent frame with `time <= t`.
+    pub fn latest_at(&self, t: f64) -> Option<&(f64, Tensor)> {
+        let end = self.frames.partition_point(|(time, _)| *time <= t);
+        end.checked_sub(1).map(|i| &self.frames[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix() -> Tensor {
+        Tensor::from_vec(vec![2, 3], vec![0u8, 1, 2, 3, 4, 5]).unwrap()
+    }
+
+    #[test]
+    fn deserializing_checks_the_shape() {
+        let tensor = matrix();
+        let json = serde_json::to_string(&tensor).unwrap();
+        assert_eq!(serde_json::from_str::<Tensor>(&json).unwrap(), tensor);
+
+        let short = r#"{"shape": [2, 3], "buffer": {"u8": [0, 1, 2]}}"#;
+        assert!(serde_json::from_str::<Tensor>(short).is_err());
+        let overflow = r#"{"shape": [18446744073709551615, 2], "buffer": {"u8": []}}"#;
+        assert!(serde_json::from_str::<Tensor>(overflow).is_err());
+    }
+
+    #[test]
+    fn slicing_selects_rows_and_columns() {
+        let tensor = matrix();
+        assert_eq!(tensor.get(&[1, 2]), Some(5.0));
+        assert_eq!(tensor.get(&[2, 0]), None);
+
+        let column = tensor.slice(&[DimSlice::All, DimSlice::Index(1)]).unwrap();
+        assert_eq!(column, Tensor::from_vec(vec![2], vec![1u8, 4]).unwrap());
+        let row = tensor.slice(&[DimSlice::Index(1)]).unwrap();
+        assert_eq!(row, Tensor::from_vec(vec![3], vec![3u8, 4, 5]).unwrap());
+        let strided = tensor
+            .slice(&[
+                DimSlice::All,
+                DimSlice::Range {
+                    start: 0,
+                    end: 10,
+                    step: 2,
+                },
+            ])
+            .unwrap();
+        assert_eq!(
+            strided,
+            Tensor::from_vec(vec![2, 2], vec![0u8, 2, 3, 5]).unwrap()
+        );
+        assert!(tensor.slice(&[DimSlice::Index(2)]).is_err());
+    }
+
+    #[test]
+    fn gather_rejects_out_of_bounds_indices() {
+        let buffer = TensorBuffer::F32(vec![1.0, 2.0]);
+        assert_eq!(
+            buffer.gather(&[1, 0]).unwrap(),
+            TensorBuffer::F32(vec![2.0, 1.0])
+        );
+        assert!(buffer.gather(&[2]).is_err());
+    }
+
+    #[test]
+    fn downsample_bounds_the_element_count() {
+        let tensor = Tensor::from_vec(vec![100, 100], vec![0.0f64; 10_000]).unwrap();
+        let small = tensor.downsample(100);
+        assert_eq!(small.shape, vec![10, 10]);
+        assert_eq!(matrix().downsample(100), matrix());
+    }
+}
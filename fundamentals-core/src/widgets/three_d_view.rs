@@ -1,11 +1,489 @@
+use std::collections::BTreeSet;
+
 use serde::{Deserialize, Serialize};
 
+use super::mesh::{MeshInstance, Pose};
+use super::occupancy::{CellEncoding, GridUpdate, OccupancyGrid, Voxel, VoxelGrid, VoxelUpdate};
+use super::precision::{Precision, TypedBuffer};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ThreeDPrimative {
+    /// A full point cloud; also the keyframe that `PointDelta`s apply to.
     Point(Vec<(f64, f64, f64)>),
+    /// A full point cloud stored with a reduced precision: the x, y and z of every
+    /// point interleaved in one buffer. Equivalent to `Point` otherwise.
+    TypedPoint(TypedBuffer),
+    /// Changed points of the cloud built from the most recent `Point`.
+    PointDelta(PointDelta),
+    OccupancyGrid(OccupancyGrid),
+    /// Changed cells of the most recent `OccupancyGrid`.
+    OccupancyGridUpdate(GridUpdate),
+    VoxelGrid(VoxelGrid),
+    /// Changed voxels of the most recent `VoxelGrid`.
+    VoxelGridUpdate(VoxelUpdate),
+    /// Placements of mesh assets at one time, e.g. every link of a robot.
+    Meshes(Vec<MeshInstance>),
+    /// A static point cloud asset placed at `pose`.
+    PointCloudInstance {
+        cloud: String,
+        pose: Pose,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        color: Option<String>,
+    },
+    /// A texture asset drawn on a `width` x `height` rectangle in the pose's xy plane,
+    /// e.g. a camera image.
+    TexturedQuad {
+        texture: String,
+        pose: Pose,
+        size: (f64, f64),
+    },
+}
+
+/// Difference between two point clouds whose points are matched by index.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PointDelta {
+    /// Number of points after the change. Points past it are dropped; points
+    /// added beyond the previous length are listed in `changed`.
+    pub len: usize,
+    /// Index and new position of every point that moved or was added.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changed: Vec<(u32, (f64, f64, f64))>,
+}
+
+impl PointDelta {
+    /// Points of `next` that differ from `previous` by more than `tolerance` along
+    /// any axis. With a tolerance of zero the delta is lossless.
+    pub fn between(previous: &[(f64, f64, f64)], next: &[(f64, f64, f64)], tolerance: f64) -> Self {
+        let moved = |a: &(f64, f64, f64), b: &(f64, f64, f64)| {
+            (a.0 - b.0).abs() > tolerance
+                || (a.1 - b.1).abs() > tolerance
+                || (a.2 - b.2).abs() > tolerance
+        };
+        let changed = next
+            .iter()
+            .enumerate()
+            .filter(|(i, point)| previous.get(*i).is_none_or(|old| moved(old, point)))
+            .map(|(i, point)| (i as u32, *point))
+            .collect();
+        Self {
+            len: next.len(),
+            changed,
+        }
+    }
+
+    pub fn apply(&self, points: &mut Vec<(f64, f64, f64)>) {
+        points.resize(self.len, (0.0, 0.0, 0.0));
+        for &(i, point) in self.changed.iter() {
+            if let Some(slot) = points.get_mut(i as usize) {
+                *slot = point;
+            }
+        }
+    }
+}
+
+/// Encodes a sequence of point clouds as a `Point` keyframe every
+/// `keyframe_interval` frames and `PointDelta`s in between.
+#[derive(Debug, Clone)]
+pub struct PointDeltaEncoder {
+    keyframe_interval: usize,
+    tolerance: f64,
+    /// The cloud as a decoder will have rebuilt it, so errors within the tolerance
+    /// do not accumulate.
+    decoded: Vec<(f64, f64, f64)>,
+    /// Frames since the last keyframe, `None` before the first one.
+    since_keyframe: Option<usize>,
+}
+
+impl PointDeltaEncoder {
+    pub fn new(keyframe_interval: usize) -> Self {
+        Self {
+            keyframe_interval: keyframe_interval.max(1),
+            tolerance: 0.0,
+            decoded: Vec::new(),
+            since_keyframe: None,
+        }
+    }
+
+    /// Treats points that moved less than `tolerance` along every axis as
+    /// unchanged, trading exactness for smaller deltas.
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance.max(0.0);
+        self
+    }
+
+    pub fn encode(&mut self, points: Vec<(f64, f64, f64)>) -> ThreeDPrimative {
+        if let Some(since) = self
+            .since_keyframe
+            .filter(|n| n + 1 < self.keyframe_interval)
+        {
+            let delta = PointDelta::between(&self.decoded, &points, self.tolerance);
+            // A delta touching most points is no smaller than a keyframe
+            if delta.changed.len() * 2 <= points.len() {
+                delta.apply(&mut self.decoded);
+                self.since_keyframe = Some(since + 1);
+                return ThreeDPrimative::PointDelta(delta);
+            }
+        }
+        self.decoded = points.clone();
+        self.since_keyframe = Some(0);
+        ThreeDPrimative::Point(points)
+    }
+}
+
+/// Points of a cloud, as `Point` stores them.
+type Cloud = Vec<(f64, f64, f64)>;
+
+/// Sequences of primitives in which updates build on the last full state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Track {
+    Points,
+    Occupancy,
+    Voxels,
+}
+
+impl Track {
+    const ALL: [Track; 3] = [Track::Points, Track::Occupancy, Track::Voxels];
+
+    /// The track of a primitive, and whether it is an update rather than a full
+    /// state.
+    fn of(primative: &ThreeDPrimative) -> Option<(Track, bool)> {
+        match primative {
+            ThreeDPrimative::Point(_) | ThreeDPrimative::TypedPoint(_) => {
+                Some((Track::Points, false))
+            }
+            ThreeDPrimative::PointDelta(_) => Some((Track::Points, true)),
+            ThreeDPrimative::OccupancyGrid(_) => Some((Track::Occupancy, false)),
+            ThreeDPrimative::OccupancyGridUpdate(_) => Some((Track::Occupancy, true)),
+            ThreeDPrimative::VoxelGrid(_) => Some((Track::Voxels, false)),
+            ThreeDPrimative::VoxelGridUpdate(_) => Some((Track::Voxels, true)),
+            _ => None,
+        }
+    }
+}
+
+impl ThreeDPrimative {
+    /// A full point cloud stored with `precision`: a `Point` for `F64`, otherwise a
+    /// `TypedPoint`.
+    pub fn points(points: &[(f64, f64, f64)], precision: Precision) -> Self {
+        if precision.is_f64() {
+            return ThreeDPrimative::Point(points.to_vec());
+        }
+        ThreeDPrimative::TypedPoint(
+            precision.encode(points.iter().flat_map(|&(x, y, z)| [x, y, z])),
+        )
+    }
+
+    /// Ids of the recording assets this primitive refers to.
+    pub fn asset_ids(&self) -> Vec<&str> {
+        match self {
+            ThreeDPrimative::Meshes(instances) => {
+                instances.iter().map(|i| i.mesh.as_str()).collect()
+            }
+            ThreeDPrimative::PointCloudInstance { cloud, .. } => vec![cloud.as_str()],
+            ThreeDPrimative::TexturedQuad { texture, .. } => vec![texture.as_str()],
+            _ => Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThreeDViewData {
     pub primatives: Vec<(f64, ThreeDPrimative)>,
 }
+
+impl ThreeDViewData {
+    /// Primitives with `t0 <= time <= t1`, assuming `primatives` is sorted by time.
+    pub fn range(&self, t0: f64, t1: f64) -> &[(f64, ThreeDPrimative)] {
+        let start = self.primatives.partition_point(|(t, _)| *t < t0);
+        let end = self.primatives.partition_point(|(t, _)| *t <= t1);
+        &self.primatives[start..end.max(start)]
+    }
+
+    /// Copy of the view restricted to `t0 <= time <= t1`. If the range starts with
+    /// point deltas or grid updates, the state as it was at `t0` is prepended so the
+    /// updates still have something to apply to.
+    pub fn slice(&self, t0: f64, t1: f64) -> Self {
+        let range = self.range(t0, t1);
+        let mut primatives = Vec::with_capacity(range.len() + Track::ALL.len());
+        let mut rebuilt = Vec::new();
+        for track in Track::ALL {
+            let first = range
+                .iter()
+                .find_map(|(_, primative)| Track::of(primative).filter(|(t, _)| *t == track));
+            if first.is_some_and(|(_, is_update)| is_update) {
+                if let Some(state) = self.state_at(track, t0) {
+                    primatives.push((t0, state));
+                    rebuilt.push(track);
+                }
+            }
+        }
+        // Updates at exactly t0 are already part of the prepended state
+        primatives.extend(
+            range
+                .iter()
+                .filter(|(t, primative)| match Track::of(primative) {
+                    Some((track, true)) => *t > t0 || !rebuilt.contains(&track),
+                    _ => true,
+                })
+                .cloned(),
+        );
+        Self { primatives }
+    }
+
+    /// Replaces the first primitive of each track in `retained` by the full state it
+    /// produces if it is an update, so `retained` can be read on its own once the
+    /// `evicted` primitives before it are dropped.
+    pub fn rebase(evicted: &[(f64, ThreeDPrimative)], retained: &mut [(f64, ThreeDPrimative)]) {
+        for track in Track::ALL {
+            let on_track =
+                |primative: &ThreeDPrimative| Track::of(primative).is_some_and(|(t, _)| t == track);
+            let Some(i) = retained
+                .iter()
+                .position(|(_, primative)| on_track(primative))
+            else {
+                continue;
+            };
+            if Track::of(&retained[i].1) != Some((track, true)) {
+                continue;
+            }
+            let history: Vec<_> = evicted
+                .iter()
+                .chain(&retained[..=i])
+                .filter(|(_, primative)| on_track(primative))
+                .collect();
+            let keyframe = history
+                .iter()
+                .rposition(|(_, primative)| Track::of(primative) == Some((track, false)));
+            let Some(keyframe) = keyframe else {
+                continue;
+            };
+            let history = Self {
+                primatives: history[keyframe..].iter().map(|&p| p.clone()).collect(),
+            };
+            if let Some(state) = history.state_at(track, retained[i].0) {
+                retained[i].1 = state;
+            }
+        }
+    }
+
+    /// Most recent primitive with `time <= t`.
+    pub fn latest_at(&self, t: f64) -> Option<&(f64, ThreeDPrimative)> {
+        let end = self.primatives.partition_point(|(time, _)| *time <= t);
+        end.checked_sub(1).map(|i| &self.primatives[i])
+    }
+
+    /// Like `latest_at`, but a delta or update is replaced by the full state it
+    /// produces.
+    pub fn latest_state_at(&self, t: f64) -> Option<(f64, ThreeDPrimative)> {
+        let (time, primative) = self.latest_at(t)?;
+        match Track::of(primative) {
+            Some((track, true)) => Some((*time, self.state_at(track, *time)?)),
+            _ => Some((*time, primative.clone())),
+        }
+    }
+
+    fn state_at(&self, track: Track, t: f64) -> Option<ThreeDPrimative> {
+        match track {
+            Track::Points => self
+                .typed_points_at(t)
+                .map(|(points, precision)| ThreeDPrimative::points(&points, precision)),
+            Track::Occupancy => self.occupancy_at(t).map(ThreeDPrimative::OccupancyGrid),
+            Track::Voxels => self.voxel_grid_at(t).map(ThreeDPrimative::VoxelGrid),
+        }
+    }
+
+    /// Primitives of `track` from its last full state at or before `t` up to `t`.
+    /// Only the primitives since that keyframe are visited.
+    fn since_full_state(&self, track: Track, t: f64) -> &[(f64, ThreeDPrimative)] {
+        let end = self.primatives.partition_point(|(time, _)| *time <= t);
+        let start = self.primatives[..end]
+            .iter()
+            .rposition(|(_, primative)| Track::of(primative) == Some((track, false)))
+            .unwrap_or(end);
+        &self.primatives[start..end]
+    }
+
+    /// The point cloud at time `t`: the last `Point` keyframe at or before `t` with
+    /// every later delta up to `t` applied.
+    pub fn points_at(&self, t: f64) -> Option<Vec<(f64, f64, f64)>> {
+        self.typed_points_at(t).map(|(points, _)| points)
+    }
+
+    /// Like `points_at`, along with the precision of the keyframe.
+    fn typed_points_at(&self, t: f64) -> Option<(Cloud, Precision)> {
+        let mut points: Option<(Cloud, Precision)> = None;
+        for (_, primative) in self.since_full_state(Track::Points, t) {
+            match primative {
+                ThreeDPrimative::Point(full) => points = Some((full.clone(), Precision::F64)),
+                ThreeDPrimative::TypedPoint(buffer) => {
+                    let coords = buffer.decode();
+                    let full = coords.chunks_exact(3).map(|c| (c[0], c[1], c[2])).collect();
+                    points = Some((full, buffer.precision()));
+                }
+                ThreeDPrimative::PointDelta(delta) => {
+                    if let Some((points, _)) = points.as_mut() {
+                        delta.apply(points);
+                    }
+                }
+                _ => {}
+            }
+        }
+        points
+    }
+
+    /// The occupancy grid at time `t`, built like `points_at`.
+    pub fn occupancy_at(&self, t: f64) -> Option<OccupancyGrid> {
+        let mut grid: Option<(&OccupancyGrid, Vec<i8>)> = None;
+        for (_, primative) in self.since_full_state(Track::Occupancy, t) {
+            match primative {
+                ThreeDPrimative::OccupancyGrid(full) => grid = Some((full, full.cells.decode())),
+                ThreeDPrimative::OccupancyGridUpdate(update) => {
+                    if let Some((full, cells)) = grid.as_mut() {
+                        update.write_into(cells, full.width, full.height);
+                    }
+                }
+                _ => {}
+            }
+        }
+        grid.map(|(full, cells)| OccupancyGrid {
+            cells: CellEncoding::compact(&cells),
+            ..full.clone()
+        })
+    }
+
+    /// The voxel map at time `t`, built like `points_at`.
+    pub fn voxel_grid_at(&self, t: f64) -> Option<VoxelGrid> {
+        let mut grid: Option<(VoxelGrid, BTreeSet<Voxel>)> = None;
+        for (_, primative) in self.since_full_state(Track::Voxels, t) {
+            match primative {
+                ThreeDPrimative::VoxelGrid(full) => {
+                    grid = Some((full.clone(), full.voxels.decode()));
+                }
+                ThreeDPrimative::VoxelGridUpdate(update) => {
+                    if let Some((_, voxels)) = grid.as_mut() {
+                        for voxel in update.cleared.iter() {
+                            voxels.remove(voxel);
+                        }
+                        voxels.extend(update.set.iter().copied());
+                    }
+                }
+                _ => {}
+            }
+        }
+        grid.map(|(grid, voxels)| VoxelGrid::new(grid.resolution, grid.origin, &voxels))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::occupancy::GridOrigin;
+
+    /// Five clouds at t = 0..4, each moving one more point, as one keyframe and deltas.
+    fn moving_cloud(precision: Precision) -> ThreeDViewData {
+        let mut encoder = PointDeltaEncoder::new(10);
+        let mut cloud = vec![(0.0, 0.0, 0.0); 4];
+        let primatives = (0..5)
+            .map(|i| {
+                cloud[i % 4].2 += 1.0;
+                let primative = match encoder.encode(cloud.clone()) {
+                    ThreeDPrimative::Point(points) => ThreeDPrimative::points(&points, precision),
+                    delta => delta,
+                };
+                (i as f64, primative)
+            })
+            .collect();
+        ThreeDViewData { primatives }
+    }
+
+    fn kinds(view: &ThreeDViewData) -> Vec<(f64, Option<(Track, bool)>)> {
+        view.primatives
+            .iter()
+            .map(|(t, primative)| (*t, Track::of(primative)))
+            .collect()
+    }
+
+    #[test]
+    fn slice_starting_mid_delta_rebuilds_the_keyframe() {
+        let view = moving_cloud(Precision::F64);
+        let updates = Some((Track::Points, true));
+        assert_eq!(kinds(&view)[0], (0.0, Some((Track::Points, false))));
+        assert!(kinds(&view)[1..].iter().all(|(_, kind)| *kind == updates));
+
+        // The delta at t0 is folded into the rebuilt keyframe instead of repeated
+        let sliced = view.slice(2.0, 4.0);
+        let keyframe = Some((Track::Points, false));
+        assert_eq!(
+            kinds(&sliced),
+            [(2.0, keyframe), (3.0, updates), (4.0, updates)]
+        );
+        for t in [2.0, 3.0, 4.0] {
+            assert_eq!(sliced.points_at(t), view.points_at(t));
+        }
+
+        // Between primitives the keyframe is the state at t0
+        let sliced = view.slice(2.5, 4.0);
+        assert_eq!(
+            kinds(&sliced),
+            [(2.5, keyframe), (3.0, updates), (4.0, updates)]
+        );
+        assert_eq!(sliced.points_at(2.5), view.points_at(2.0));
+        assert_eq!(sliced.points_at(4.0), view.points_at(4.0));
+
+        // A slice starting at a keyframe is left as it is
+        assert_eq!(kinds(&view.slice(0.0, 1.0)), kinds(&view)[..2]);
+    }
+
+    #[test]
+    fn rebuilt_keyframes_keep_their_precision() {
+        let view = moving_cloud(Precision::F32);
+        let sliced = view.slice(1.0, 2.0);
+        assert!(matches!(
+            sliced.primatives[0].1,
+            ThreeDPrimative::TypedPoint(_)
+        ));
+        assert_eq!(sliced.points_at(2.0), view.points_at(2.0));
+
+        let (time, state) = view.latest_state_at(3.5).unwrap();
+        assert_eq!(time, 3.0);
+        let ThreeDPrimative::TypedPoint(buffer) = state else {
+            panic!("expected a typed keyframe, got {:?}", state);
+        };
+        assert_eq!(buffer.precision(), Precision::F32);
+        let points: Vec<_> = buffer
+            .decode()
+            .chunks_exact(3)
+            .map(|c| (c[0], c[1], c[2]))
+            .collect();
+        assert_eq!(Some(points), view.points_at(3.0));
+        assert!(view.latest_state_at(-1.0).is_none());
+    }
+
+    #[test]
+    fn slice_rebuilds_grids_from_their_updates() {
+        let grid =
+            |cells: &[i8]| OccupancyGrid::new(1.0, 2, 2, GridOrigin::default(), cells).unwrap();
+        let grids = [
+            grid(&[0, 0, 0, 0]),
+            grid(&[100, 0, 0, 0]),
+            grid(&[100, 0, 0, 100]),
+        ];
+        let mut primatives = vec![(0.0, ThreeDPrimative::OccupancyGrid(grids[0].clone()))];
+        for (i, pair) in grids.windows(2).enumerate() {
+            let update = pair[0].diff(&pair[1]).unwrap();
+            primatives.push(((i + 1) as f64, ThreeDPrimative::OccupancyGridUpdate(update)));
+        }
+        let view = ThreeDViewData { primatives };
+
+        let sliced = view.slice(1.5, 2.0);
+        let ThreeDPrimative::OccupancyGrid(first) = &sliced.primatives[0].1 else {
+            panic!("expected a full grid, got {:?}", sliced.primatives[0].1);
+        };
+        assert_eq!(first.cells.decode(), grids[1].cells.decode());
+        assert_eq!(
+            sliced.occupancy_at(2.0).unwrap().cells.decode(),
+            grids[2].cells.decode()
+        );
+        assert!(view.slice(-2.0, -1.0).primatives.is_empty());
+    }
+}
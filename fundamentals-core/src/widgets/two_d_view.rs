@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TwoDPrimative {
+    /// Scatter points. `colors` and `sizes` are per point; when empty the viewer
+    /// uses its defaults, when they hold a single entry it applies to every point.
+    Points {
+        positions: Vec<(f64, f64)>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        colors: Vec<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        sizes: Vec<f64>,
+    },
+    LineStrip {
+        positions: Vec<(f64, f64)>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        color: Option<String>,
+    },
+    /// Closed polygon; the last vertex connects back to the first.
+    Polygon {
+        vertices: Vec<(f64, f64)>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        color: Option<String>,
+        #[serde(default)]
+        filled: bool,
+    },
+    /// Rectangle of `size` (width, height) centred on `center`, rotated by
+    /// `rotation` radians counter-clockwise.
+    Box2D {
+        center: (f64, f64),
+        size: (f64, f64),
+        #[serde(default)]
+        rotation: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        color: Option<String>,
+    },
+}
+
+impl TwoDPrimative {
+    /// Every vertex of the primitive; box corners for `Box2D`.
+    pub fn positions(&self) -> Vec<(f64, f64)> {
+        match self {
+            TwoDPrimative::Points { positions, .. }
+            | TwoDPrimative::LineStrip { positions, .. } => positions.clone(),
+            TwoDPrimative::Polygon { vertices, .. } => vertices.clone(),
+            TwoDPrimative::Box2D {
+                center,
+                size,
+                rotation,
+                ..
+            } => {
+                let (sin, cos) = rotation.sin_cos();
+                [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                    .iter()
+                    .map(|(sx, sy)| {
+                        let (dx, dy) = (sx * size.0 / 2.0, sy * size.1 / 2.0);
+                        (
+                            center.0 + dx * cos - dy * sin,
+                            center.1 + dx * sin + dy * cos,
+                        )
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Top-down view: trajectories, scatter plots, polygons and boxes, each tagged with
+/// the time it was logged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TwoDViewData {
+    pub primatives: Vec<(f64, TwoDPrimative)>,
+}
+
+impl TwoDViewData {
+    /// Primitives with `t0 <= time <= t1`, assuming `primatives` is sorted by time.
+    pub fn range(&self, t0: f64, t1: f64) -> &[(f64, TwoDPrimative)] {
+        let start = self.primatives.partition_point(|(t, _)| *t < t0);
+        let end = self.primatives.partition_point(|(t, _)| *t <= t1);
+        &self.primatives[start..end.max(start)]
+    }
+
+    /// Copy of the view restricted to `t0 <= time <= t1`.
+    pub fn slice(&self, t0: f64, t1: f64) -> Self {
+        Self {
+            primatives: self.range(t0, t1).to_vec(),
+        }
+    }
+
+    /// Every primitive logged up to and including `t`, e.g. the trail of a
+    /// trajectory at the current time.
+    pub fn trail(&self, t: f64) -> &[(f64, TwoDPrimative)] {
+        self.range(f64::NEG_INFINITY, t)
+    }
+
+    /// Most recent primitive with `time <= t`.
+    pub fn latest_at(&self, t: f64) -> Option<&(f64, TwoDPrimative)> {
+        self.trail(t).last()
+    }
+
+    /// Bounding box `((min_x, min_y), (max_x, max_y))` of every primitive.
+    pub fn bounds(&self) -> Option<((f64, f64), (f64, f64))> {
+        self.primatives
+            .iter()
+            .flat_map(|(_, primative)| primative.positions())
+            .fold(None, |acc, (x, y)| match acc {
+                Some(((x0, y0), (x1, y1))) => {
+                    Some(((x.min(x0), y.min(y0)), (x.max(x1), y.max(y1))))
+                }
+                None => Some(((x, y), (x, y))),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view() -> TwoDViewData {
+        TwoDViewData {
+            primatives: vec![
+                (
+                    0.0,
+                    TwoDPrimative::Points {
+                        positions: vec![(0.0, 0.0)],
+                        colors: Vec::new(),
+                        sizes: vec![2.0],
+                    },
+                ),
+                (
+                    1.0,
+                    TwoDPrimative::Polygon {
+                        vertices: vec![(0.0, 0.0), (2.0, 0.0), (0.0, 3.0)],
+                        color: Some("red".to_string()),
+                        filled: true,
+                    },
+                ),
+                (
+                    2.0,
+                    TwoDPrimative::Box2D {
+                        center: (5.0, 5.0),
+                        size: (2.0, 4.0),
+                        rotation: std::f64::consts::FRAC_PI_2,
+                        color: None,
+                    },
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn primitives_serialize_tagged_without_defaults() {
+        let json = serde_json::to_value(&view().primatives[0].1).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "Points": { "positions": [[0.0, 0.0]], "sizes": [2.0] } })
+        );
+        let line: TwoDPrimative =
+            serde_json::from_str(r#"{"LineStrip": {"positions": [[1, 2], [3, 4]]}}"#).unwrap();
+        assert_eq!(
+            line,
+            TwoDPrimative::LineStrip {
+                positions: vec![(1.0, 2.0), (3.0, 4.0)],
+                color: None
+            }
+        );
+
+        let view = view();
+        let read: TwoDViewData =
+            serde_json::from_str(&serde_json::to_string(&view).unwrap()).unwrap();
+        assert_eq!(read.primatives, view.primatives);
+    }
+
+    #[test]
+    fn slices_and_trails_follow_time() {
+        let view = view();
+        let times = |primatives: &[(f64, TwoDPrimative)]| -> Vec<f64> {
+            primatives.iter().map(|(t, _)| *t).collect()
+        };
+        assert_eq!(times(&view.slice(0.5, 2.0).primatives), vec![1.0, 2.0]);
+        assert!(view.slice(2.5, 3.0).primatives.is_empty());
+        assert_eq!(times(view.trail(1.5)), vec![0.0, 1.0]);
+        assert_eq!(view.latest_at(1.5).map(|(t, _)| *t), Some(1.0));
+        assert!(view.latest_at(-1.0).is_none());
+    }
+
+    #[test]
+    fn boxes_are_rotated_around_their_center() {
+        let view = view();
+        let corners = view.primatives[2].1.positions();
+        let rounded: Vec<(f64, f64)> = corners
+            .iter()
+            .map(|(x, y)| (x.round(), y.round()))
+            .collect();
+        // A 2 x 4 box turned a quarter is 4 wide and 2 high
+        assert_eq!(
+            rounded,
+            vec![(7.0, 4.0), (7.0, 6.0), (3.0, 6.0), (3.0, 4.0)]
+        );
+        let ((x0, y0), (x1, y1)) = view.bounds().unwrap();
+        assert_eq!((x0, y0), (0.0, 0.0));
+        assert_eq!((x1.round(), y1.round()), (7.0, 6.0));
+        assert!(TwoDViewData::default().bounds().is_none());
+    }
+}
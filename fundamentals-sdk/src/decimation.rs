@@ -0,0 +1,241 @@
+use fundamentals_core::viz::Viz;
+
+pub const MODE_KEY: &str = "decimation.mode";
+pub const ORIGINAL_SAMPLES_KEY: &str = "decimation.original_samples";
+pub const ORIGINAL_RATE_KEY: &str = "decimation.original_rate_hz";
+
+/// Log-time reduction applied to incoming `(x, y)` samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decimation {
+    /// Keep every sample (the default).
+    None,
+    /// Drop samples closer than `interval` to the last kept sample.
+    MinInterval(f64),
+    /// Keep one sample out of every `n`.
+    KeepEveryNth(usize),
+    /// Collect buckets of `n` samples and keep only their minimum and maximum,
+    /// so spikes survive decimation.
+    MinMax(usize),
+}
+
+impl Decimation {
+    pub fn label(&self) -> String {
+        match self {
+            Decimation::None => "none".to_string(),
+            Decimation::MinInterval(interval) => format!("min_interval({})", interval),
+            Decimation::KeepEveryNth(n) => format!("keep_every_nth({})", n),
+            Decimation::MinMax(n) => format!("min_max({})", n),
+        }
+    }
+}
+
+/// Applies a `Decimation` to a stream of samples and tracks the input rate.
+#[derive(Debug, Clone)]
+pub struct Decimator {
+    mode: Decimation,
+    received: u64,
+    first_x: Option<f64>,
+    last_x: Option<f64>,
+    last_kept_x: Option<f64>,
+    bucket: Vec<(f64, f64)>,
+}
+
+impl Decimator {
+    pub fn new(mode: Decimation) -> Self {
+        Self {
+            mode,
+            received: 0,
+            first_x: None,
+            last_x: None,
+            last_kept_x: None,
+            bucket: Vec::new(),
+        }
+    }
+
+    pub fn mode(&self) -> Decimation {
+        self.mode
+    }
+
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    /// Average input rate in samples per unit of x, if at least two samples were seen.
+    pub fn original_rate(&self) -> Option<f64> {
+        let span = self.last_x? - self.first_x?;
+        if self.received < 2 || span <= 0.0 {
+            return None;
+        }
+        Some((self.received - 1) as f64 / span)
+    }
+
+    /// Feeds one sample and returns the samples that should be kept.
+    pub fn push(&mut self, x: f64, y: f64) -> Vec<(f64, f64)> {
+        let index = self.received;
+        self.received += 1;
+        self.first_x.get_or_insert(x);
+        self.last_x = Some(x);
+
+        match self.mode {
+            Decimation::None => vec![(x, y)],
+            Decimation::MinInterval(interval) => {
+                if self
+                    .last_kept_x
+                    .is_some_and(|last| (x - last).abs() < interval)
+                {
+                    return Vec::new();
+                }
+                self.last_kept_x = Some(x);
+                vec![(x, y)]
+            }
+            Decimation::KeepEveryNth(n) => {
+                if index.is_multiple_of(n.max(1) as u64) {
+                    vec![(x, y)]
+                } else {
+                    Vec::new()
+                }
+            }
+            Decimation::MinMax(n) => {
+                self.bucket.push((x, y));
+                if self.bucket.len() < n.max(1) {
+                    return Vec::new();
+                }
+                let kept = Self::min_max(&self.bucket);
+                self.bucket.clear();
+                kept
+            }
+        }
+    }
+
+    /// Samples from a partially filled min/max bucket that have not been emitted yet.
+    pub fn pending(&self) -> Vec<(f64, f64)> {
+        Self::min_max(&self.bucket)
+    }
+
+    /// Records the decimation mode and original input rate in the viz metadata.
+    pub fn annotate(&self, viz: &mut Viz) {
+        if self.mode == Decimation::None {
+            return;
+        }
+        viz.set_metadata(MODE_KEY, self.mode.label());
+        viz.set_metadata(ORIGINAL_SAMPLES_KEY, self.received);
+        if let Some(rate) = self.original_rate() {
+            viz.set_metadata(ORIGINAL_RATE_KEY, rate);
+        }
+    }
+
+    fn min_max(bucket: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        let min = bucket
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1 .1.total_cmp(&b.1 .1));
+        let max = bucket
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1 .1.total_cmp(&b.1 .1));
+        match (min, max) {
+            (Some((i, min)), Some((j, max))) if i < j => vec![*min, *max],
+            (Some((i, min)), Some((j, max))) if i > j => vec![*max, *min],
+            (Some((_, min)), _) => vec![*min],
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plotter::Plotter;
+    use fundamentals_core::widgets::Widget;
+
+    fn feed(mode: Decimation, samples: &[(f64, f64)]) -> (Decimator, Vec<(f64, f64)>) {
+        let mut decimator = Decimator::new(mode);
+        let kept = samples
+            .iter()
+            .flat_map(|&(x, y)| decimator.push(x, y))
+            .collect();
+        (decimator, kept)
+    }
+
+    fn ramp(n: usize) -> Vec<(f64, f64)> {
+        (0..n).map(|i| (i as f64 / 2.0, i as f64)).collect()
+    }
+
+    #[test]
+    fn min_interval_measures_from_the_last_kept_sample() {
+        let samples = [
+            (0.0, 0.0),
+            (0.4, 1.0),
+            (0.8, 2.0),
+            (1.0, 3.0),
+            (1.1, 4.0),
+            (2.5, 5.0),
+        ];
+        let (decimator, kept) = feed(Decimation::MinInterval(1.0), &samples);
+        assert_eq!(kept, vec![(0.0, 0.0), (1.0, 3.0), (2.5, 5.0)]);
+        assert_eq!(decimator.received(), 6);
+        assert_eq!(decimator.pending(), Vec::new());
+    }
+
+    #[test]
+    fn keep_every_nth_keeps_the_first_of_each_group() {
+        let (_, kept) = feed(Decimation::KeepEveryNth(3), &ramp(8));
+        let ys: Vec<f64> = kept.iter().map(|(_, y)| *y).collect();
+        assert_eq!(ys, vec![0.0, 3.0, 6.0]);
+        // Zero keeps everything instead of dividing by zero
+        assert_eq!(feed(Decimation::KeepEveryNth(0), &ramp(4)).1, ramp(4));
+    }
+
+    #[test]
+    fn min_max_keeps_spikes_in_time_order() {
+        let samples = [
+            (0.0, 1.0),
+            (1.0, 9.0),
+            (2.0, -3.0),
+            (3.0, 2.0),
+            (4.0, -8.0),
+            (5.0, 0.0),
+            (6.0, 4.0),
+            (7.0, 4.0),
+        ];
+        let (decimator, kept) = feed(Decimation::MinMax(3), &samples);
+        assert_eq!(kept, vec![(1.0, 9.0), (2.0, -3.0), (3.0, 2.0), (4.0, -8.0)]);
+        // The last two samples wait for a full bucket
+        assert_eq!(decimator.pending(), vec![(6.0, 4.0), (7.0, 4.0)]);
+    }
+
+    #[test]
+    fn plotters_flush_the_pending_bucket() {
+        let mut plotter = Plotter::new("speed").with_min_max_buckets(4);
+        for (x, y) in ramp(6) {
+            plotter.add_point(x, y);
+        }
+        assert_eq!(plotter.len(), 2);
+        let data = plotter.as_scalar_data();
+        assert_eq!(
+            data.data_x,
+            vec![(0.0, 0.0), (1.5, 3.0), (2.0, 4.0), (2.5, 5.0)]
+        );
+
+        let viz = plotter.into_viz();
+        let Widget::PlotScalar(owned) = &viz.widgets[0] else {
+            panic!("expected a scalar plot");
+        };
+        assert_eq!(owned.data_x, data.data_x);
+        assert_eq!(viz.get_metadata(MODE_KEY), Some(&"min_max(4)".into()));
+        assert_eq!(viz.get_metadata(ORIGINAL_SAMPLES_KEY), Some(&6.into()));
+        let rate = viz
+            .get_metadata(ORIGINAL_RATE_KEY)
+            .unwrap()
+            .as_f64()
+            .unwrap();
+        assert_eq!(rate, 2.0);
+    }
+
+    #[test]
+    fn undecimated_plots_have_no_metadata() {
+        let mut plotter = Plotter::new("speed");
+        plotter.add_point(0.0, 1.0);
+        assert!(plotter.as_viz().get_metadata(MODE_KEY).is_none());
+    }
+}
@@ -0,0 +1,106 @@
+use fundamentals_core::{
+    recording::Recording,
+    viz::Viz,
+    widgets::{
+        histogram::{BinSpec, HistogramBins, HistogramData, HistogramFrame},
+        Widget,
+    },
+};
+
+use crate::retention::{RetentionBuffer, RetentionPolicy, RetentionStats};
+
+/// Logs distributions. Samples added with `add_sample` accumulate until `snapshot`
+/// turns them into a histogram at a given time; without snapshots the viz is a
+/// single static histogram. Raw samples are binned by the bridge.
+pub struct HistogramLogger {
+    pub name: String,
+    bins: BinSpec,
+    frames: RetentionBuffer<HistogramFrame>,
+    pending: Vec<f64>,
+}
+
+impl HistogramLogger {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            bins: BinSpec::default(),
+            frames: RetentionBuffer::new(name, RetentionPolicy::Unbounded),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn with_bins(mut self, bins: BinSpec) -> Self {
+        self.bins = bins;
+        self
+    }
+
+    pub fn with_bin_count(self, bins: usize) -> Self {
+        self.with_bins(BinSpec::Count(bins))
+    }
+
+    pub fn with_uniform_bins(self, min: f64, max: f64, bins: usize) -> Self {
+        self.with_bins(BinSpec::Uniform { min, max, bins })
+    }
+
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.frames.set_policy(policy);
+        self
+    }
+
+    pub fn add_sample(&mut self, value: f64) {
+        self.pending.push(value);
+    }
+
+    pub fn add_samples(&mut self, values: impl IntoIterator<Item = f64>) {
+        self.pending.extend(values);
+    }
+
+    /// Records the samples added since the last snapshot as the histogram at `time`.
+    pub fn snapshot(&mut self, time: f64) {
+        let samples = std::mem::take(&mut self.pending);
+        self.frames.push(time, HistogramFrame::Samples(samples));
+    }
+
+    /// Records a full set of samples as the histogram at `time`.
+    pub fn log_samples(&mut self, time: f64, samples: Vec<f64>) {
+        self.frames.push(time, HistogramFrame::Samples(samples));
+    }
+
+    /// Records a histogram binned by the caller.
+    pub fn log_binned(
+        &mut self,
+        time: f64,
+        edges: Vec<f64>,
+        counts: Vec<f64>,
+    ) -> Result<(), anyhow::Error> {
+        let bins = HistogramBins::new(edges, counts)?;
+        self.frames.push(time, HistogramFrame::Binned(bins));
+        Ok(())
+    }
+
+    pub fn retention_stats(&self) -> RetentionStats {
+        self.frames.stats()
+    }
+
+    pub fn log(&self, recording: &mut Recording) {
+        recording.add_viz(self.as_viz());
+    }
+
+    /// Recorded frames, plus any samples not yet snapshotted as a last frame at the
+    /// time of the previous one (or 0).
+    pub fn as_histogram_data(&self) -> HistogramData {
+        let mut frames = self.frames.to_vec();
+        if !self.pending.is_empty() {
+            let time = frames.last().map(|(t, _)| *t).unwrap_or_default();
+            frames.push((time, HistogramFrame::Samples(self.pending.clone())));
+        }
+        HistogramData::new(frames).with_bins(self.bins.clone())
+    }
+
+    pub fn as_viz(&self) -> Viz {
+        let widget = Widget::Histogram(self.as_histogram_data());
+        let mut viz = Viz::new(self.name.clone()).with_widget(widget);
+        self.frames.stats().annotate(&mut viz);
+        viz
+    }
+}
@@ -1,3 +1,14 @@
+pub mod decimation;
+pub mod histogram;
 pub mod logger;
+#[cfg(feature = "meshes")]
+pub mod mesh;
 pub mod plotter;
+pub mod retention;
+pub mod tensor;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod threed;
+pub mod twod;
+#[cfg(feature = "urdf")]
+pub mod urdf;
@@ -1,37 +1,227 @@
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use fundamentals_core::recording::Recording;
-use log::info;
+use fundamentals_core::{
+    annotation::Annotation,
+    compression::Compression,
+    recording::{Recording, RecordingMetadata},
+};
+use log::{error, info, warn};
+
+use crate::retention::RetentionStats;
+
+/// What to do when the resolved output path already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Replace the existing file.
+    Overwrite,
+    /// Return an error and leave the existing file untouched.
+    Fail,
+    /// Append `_1`, `_2`, ... to the file stem until a free path is found.
+    Increment,
+}
+
+/// Controls where and when a `Logger` writes its recording.
+///
+/// `output_path` is a template: `{name}`, `{session_id}` and `{timestamp}`
+/// (unix seconds at logger creation) are substituted when the path is resolved.
+#[derive(Debug, Clone)]
+pub struct LoggerOptions {
+    pub output_path: String,
+    pub save_on_drop: bool,
+    pub overwrite: OverwritePolicy,
+}
+
+impl Default for LoggerOptions {
+    fn default() -> Self {
+        Self {
+            output_path: "recording.json".to_string(),
+            save_on_drop: true,
+            overwrite: OverwritePolicy::Overwrite,
+        }
+    }
+}
+
+impl LoggerOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_output_path(mut self, template: impl Into<String>) -> Self {
+        self.output_path = template.into();
+        self
+    }
+
+    pub fn with_save_on_drop(mut self, save_on_drop: bool) -> Self {
+        self.save_on_drop = save_on_drop;
+        self
+    }
+
+    pub fn with_overwrite(mut self, overwrite: OverwritePolicy) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+}
 
 pub struct Logger {
     pub name: String,
     pub session_id: String,
     pub recording: Recording,
+    pub options: LoggerOptions,
+    timestamp: u64,
+    /// Where `finish` wrote the recording, reused by later saves. Behind a lock so
+    /// the launch methods can finish a shared logger.
+    saved_path: Mutex<Option<PathBuf>>,
 }
 
 impl Logger {
     pub fn new(name: String) -> Self {
+        Self::with_options(name, LoggerOptions::default())
+    }
+
+    pub fn with_options(name: String, options: LoggerOptions) -> Self {
         info!("Creating logger for {}", name);
         let session_id = uuid::Uuid::new_v4().to_string();
+        let start_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut recording = Recording::new(name.clone(), session_id.clone());
+        recording.metadata = RecordingMetadata {
+            git_commit: git_commit(),
+            host: Some(gethostname::gethostname().to_string_lossy().to_string()),
+            start_time: Some(start_time.as_secs_f64()),
+            command_line: std::env::args().collect(),
+            ..Default::default()
+        };
         Self {
-            name: name.clone(),
-            session_id: session_id.clone(),
-            recording: Recording::new(name, session_id),
+            name,
+            session_id,
+            recording,
+            options,
+            timestamp: start_time.as_secs(),
+            saved_path: Mutex::new(None),
         }
     }
 
+    /// Attaches a user-defined key/value tag to the recording metadata.
+    pub fn tag(&mut self, key: &str, value: impl ToString) {
+        self.recording.set_tag(key, &value.to_string());
+    }
+
+    /// Expands the output path template without applying the overwrite policy.
+    pub fn output_path(&self) -> PathBuf {
+        PathBuf::from(
+            self.options
+                .output_path
+                .replace("{name}", &self.name)
+                .replace("{session_id}", &self.session_id)
+                .replace("{timestamp}", &self.timestamp.to_string()),
+        )
+    }
+
+    fn resolve_output_path(&self) -> Result<PathBuf, anyhow::Error> {
+        let path = self.output_path();
+        if !path.exists() {
+            return Ok(path);
+        }
+
+        match self.options.overwrite {
+            OverwritePolicy::Overwrite => Ok(path),
+            OverwritePolicy::Fail => Err(anyhow::anyhow!(
+                "Recording {} already exists",
+                path.display()
+            )),
+            OverwritePolicy::Increment => {
+                // Keep `.json.zst` together rather than numbering `x.json_1.zst`
+                let stem = Compression::strip_extension(&path)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let extension = name[stem.len().min(name.len())..].to_string();
+                (1..)
+                    .map(|i| path.with_file_name(format!("{}_{}{}", stem, i, extension)))
+                    .find(|candidate| !candidate.exists())
+                    .ok_or_else(|| anyhow::anyhow!("No free path for {}", path.display()))
+            }
+        }
+    }
+
+    /// Marks an instant event, e.g. `logger.mark(4.2, "collision")`.
+    pub fn mark(&mut self, time: f64, label: &str) {
+        self.recording
+            .add_annotation(Annotation::instant(time, label));
+    }
+
+    /// Marks a span of time, e.g. a test phase.
+    pub fn mark_interval(&mut self, start: f64, end: f64, label: &str) {
+        self.recording
+            .add_annotation(Annotation::interval(start, end, label));
+    }
+
+    /// Adds a fully specified annotation (color, viz scope).
+    pub fn annotate(&mut self, annotation: Annotation) {
+        self.recording.add_annotation(annotation);
+    }
+
+    /// Per-viz dropped/spilled counts for every viz that lost samples to a retention policy.
+    pub fn retention_report(&self) -> Vec<(String, RetentionStats)> {
+        self.recording
+            .get_vizs()
+            .iter()
+            .map(|viz| (viz.name.clone(), RetentionStats::from_viz(viz)))
+            .filter(|(_, stats)| !stats.is_empty())
+            .collect()
+    }
+
+    /// Writes the recording to the configured output path and returns where it went.
+    /// Later calls rewrite the same file rather than resolving the template again.
+    ///
+    /// After a successful call the recording is no longer saved on drop.
+    pub fn finish(&self) -> Result<PathBuf, anyhow::Error> {
+        for (name, stats) in self.retention_report() {
+            warn!(
+                "{}: {} samples dropped, {} spilled to disk by retention policy",
+                name, stats.dropped, stats.spilled
+            );
+        }
+
+        let mut saved_path = self.saved_path.lock().unwrap_or_else(|e| e.into_inner());
+        let path = match saved_path.as_ref() {
+            Some(path) => path.clone(),
+            None => self.resolve_output_path()?,
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        self.save(&path)?;
+        *saved_path = Some(path.clone());
+        Ok(path)
+    }
+
+    /// Where `finish` wrote the recording, if it has been called.
+    pub fn saved_path(&self) -> Option<PathBuf> {
+        self.saved_path
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
     pub fn save(&self, path: &PathBuf) -> Result<(), anyhow::Error> {
         info!("Saving recording to {}", path.display());
         self.recording.save_to_file(path)
     }
 
     pub async fn launch_bridge(&self, open_browser: bool) -> Result<(), anyhow::Error> {
-        // First save the recording to a file
-        let path = PathBuf::from("recording.json");
-        self.save(&path)?;
-
-        // Then launch the bridge
-        let recording_path = path.clone();
+        // First save the recording as configured by the logger options
+        let recording_path = self.finish()?;
         let port = 3031;
 
         // Spawn a new thread to run the async bridge server
@@ -53,40 +243,28 @@ impl Logger {
     }
 
     pub fn launch_tauri(&self) -> Result<(), anyhow::Error> {
-        // First save the recording to the default path expected by Tauri
-        let path = PathBuf::from("recording.json");
-        self.save(&path)?;
-
-        // Change the working directory to the repository root to ensure proper asset resolution
-        let current_dir = std::env::current_dir()?;
-        let repo_root = current_dir
-            .ancestors()
-            .find(|p| p.join("fundamentals-web").exists())
-            .ok_or_else(|| anyhow::anyhow!("Could not find repository root"))?;
-
-        std::env::set_current_dir(repo_root)?;
-        info!(
-            "Changed working directory to repository root: {}",
-            repo_root.display()
-        );
-
-        fundamentals_tauri_lib::run();
-
-        info!("Tauri app launched with default recording at recording.json");
-        Ok(())
+        self.launch_tauri_with_recording(None, None)
     }
 
+    /// Saves the recording to `recording_path`, or as configured by the logger
+    /// options, and launches the Tauri app.
     pub fn launch_tauri_with_recording(
         &self,
         recording_path: Option<PathBuf>,
         port: Option<u16>,
     ) -> Result<(), anyhow::Error> {
-        // Use provided path or default
-        let path = recording_path.unwrap_or_else(|| PathBuf::from("recording.json"));
         let port = port.unwrap_or(3031);
 
         // Save the recording
-        self.save(&path)?;
+        let path = match recording_path {
+            Some(path) => {
+                self.save(&path)?;
+                path
+            }
+            None => self.finish()?,
+        };
+        // Resolve before changing directory, relative paths would point elsewhere
+        let abs_path = std::fs::canonicalize(&path)?;
 
         // Change the working directory to the repository root to ensure proper asset resolution
         let current_dir = std::env::current_dir()?;
@@ -103,17 +281,11 @@ impl Logger {
 
         info!("Saved recording to {} for Tauri app", path.display());
 
-        // Save the absolute path for use in the Tauri app
-        let abs_path = std::fs::canonicalize(&path)?;
         let abs_path_str = abs_path.to_string_lossy().to_string();
 
         // If we're using the default path, Tauri will auto-detect it
         // Otherwise we need to manually start the bridge once Tauri launches
-        let use_custom_path = path.to_string_lossy() != "recording.json";
-
-        // Use a clone of the path for the closure
-        let path_for_closure = abs_path_str.clone();
-        let port_for_closure = port;
+        let use_custom_path = abs_path != repo_root.join("recording.json");
 
         fundamentals_tauri_lib::run();
 
@@ -132,9 +304,142 @@ impl Logger {
     }
 }
 
+/// Commit of the git checkout the process runs in, if any.
+fn git_commit() -> Option<String> {
+    git_commit_in(&std::env::current_dir().ok()?)
+}
+
+/// Commit checked out in the work tree containing `dir`. `None` outside a work
+/// tree, including inside a `.git` directory or a bare repository, where
+/// `rev-parse HEAD` would still answer.
+fn git_commit_in(dir: &Path) -> Option<String> {
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    if git(&["rev-parse", "--is-inside-work-tree"])? != "true" {
+        return None;
+    }
+    git(&["rev-parse", "HEAD"])
+}
+
 impl Drop for Logger {
     fn drop(&mut self) {
-        let path = PathBuf::from("recording.json");
-        self.save(&path).unwrap();
+        if self.saved_path().is_some() || !self.options.save_on_drop {
+            return;
+        }
+        if let Err(e) = self.finish() {
+            error!("Failed to save recording for {} on drop: {}", self.name, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logger_in(dir: &std::path::Path, template: &str, overwrite: OverwritePolicy) -> Logger {
+        let options = LoggerOptions::new()
+            .with_output_path(dir.join(template).to_string_lossy())
+            .with_save_on_drop(false)
+            .with_overwrite(overwrite);
+        Logger::with_options("drive".to_string(), options)
+    }
+
+    #[test]
+    fn output_path_expands_the_template() {
+        let logger = Logger::with_options(
+            "drive".to_string(),
+            LoggerOptions::new()
+                .with_output_path("runs/{name}/{session_id}-{timestamp}.json")
+                .with_save_on_drop(false),
+        );
+        assert_eq!(
+            logger.output_path(),
+            PathBuf::from(format!(
+                "runs/drive/{}-{}.json",
+                logger.session_id, logger.timestamp
+            ))
+        );
+    }
+
+    #[test]
+    fn overwrite_policies_decide_what_happens_to_existing_files() {
+        let dir = std::env::temp_dir().join(format!("fundamentals-logger-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let existing = dir.join("drive.json.zst");
+        std::fs::write(&existing, b"old").unwrap();
+
+        let logger = logger_in(&dir, "{name}.json.zst", OverwritePolicy::Fail);
+        assert!(logger.finish().is_err());
+        assert_eq!(std::fs::read(&existing).unwrap(), b"old");
+        assert!(logger.saved_path().is_none());
+
+        // Numbered before the format and compression extensions
+        let logger = logger_in(&dir, "{name}.json.zst", OverwritePolicy::Increment);
+        assert_eq!(logger.finish().unwrap(), dir.join("drive_1.json.zst"));
+        let logger = logger_in(&dir, "{name}.json.zst", OverwritePolicy::Increment);
+        assert_eq!(logger.finish().unwrap(), dir.join("drive_2.json.zst"));
+        assert_eq!(std::fs::read(&existing).unwrap(), b"old");
+
+        let logger = logger_in(&dir, "{name}.json.zst", OverwritePolicy::Overwrite);
+        assert_eq!(logger.finish().unwrap(), existing);
+        assert!(Recording::load_from_file(&existing).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn finish_rewrites_the_file_it_saved_first() {
+        let dir =
+            std::env::temp_dir().join(format!("fundamentals-logger-finish-{}", std::process::id()));
+        let mut logger = logger_in(&dir, "out/{name}.json", OverwritePolicy::Increment);
+        let path = logger.finish().unwrap();
+        assert_eq!(path, dir.join("out/drive.json"));
+
+        // The file now exists, but a second finish must not move to drive_1.json
+        logger.tag("car", "blue");
+        assert_eq!(logger.finish().unwrap(), path);
+        assert_eq!(logger.saved_path(), Some(path.clone()));
+        assert!(!dir.join("out/drive_1.json").exists());
+        let recording = Recording::load_from_file(&path).unwrap();
+        assert_eq!(
+            recording.metadata.tags.get("car").map(String::as_str),
+            Some("blue")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn git_commit_needs_a_work_tree() {
+        let dir = std::env::temp_dir().join(format!("fundamentals-git-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(git_commit_in(&dir), None);
+
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(&dir)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&["init", "-q"]);
+        // No commit yet
+        assert_eq!(git_commit_in(&dir), None);
+        git(&["commit", "-q", "--allow-empty", "-m", "first"]);
+        let commit = git_commit_in(&dir).unwrap();
+        assert_eq!(commit.len(), 40);
+        // HEAD resolves inside .git too, but that is not a checkout
+        assert_eq!(git_commit_in(&dir.join(".git")), None);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
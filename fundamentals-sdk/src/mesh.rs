@@ -0,0 +1,331 @@
+use std::path::Path;
+
+use base64::Engine;
+use fundamentals_core::widgets::mesh::{Mesh, Pose};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshFormat {
+    Obj,
+    Stl,
+    /// `.gltf` with external or embedded buffers, or binary `.glb`.
+    Gltf,
+}
+
+impl MeshFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "obj" => Some(MeshFormat::Obj),
+            "stl" => Some(MeshFormat::Stl),
+            "gltf" | "glb" => Some(MeshFormat::Gltf),
+            _ => None,
+        }
+    }
+}
+
+/// Loads a mesh file, picking the format from the extension. All objects in the
+/// file are merged into one mesh.
+pub fn load_mesh(path: &Path) -> Result<Mesh, anyhow::Error> {
+    let format = MeshFormat::from_path(path)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported mesh format: {}", path.display()))?;
+    load_mesh_as(path, format)
+}
+
+pub fn load_mesh_as(path: &Path, format: MeshFormat) -> Result<Mesh, anyhow::Error> {
+    let mesh = match format {
+        MeshFormat::Obj => load_obj(path),
+        MeshFormat::Stl => load_stl(path),
+        MeshFormat::Gltf => load_gltf(path),
+    };
+    mesh.map_err(|e| anyhow::anyhow!("Failed to load mesh {}: {}", path.display(), e))
+}
+
+/// CSS color for RGBA components in `0..=1`.
+pub(crate) fn css_color(rgba: [f64; 4]) -> String {
+    let channel = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "rgba({}, {}, {}, {})",
+        channel(rgba[0]),
+        channel(rgba[1]),
+        channel(rgba[2]),
+        rgba[3].clamp(0.0, 1.0)
+    )
+}
+
+fn load_obj(path: &Path) -> Result<Mesh, anyhow::Error> {
+    let options = tobj::LoadOptions {
+        triangulate: true,
+        single_index: true,
+        ..Default::default()
+    };
+    let (models, materials) = tobj::load_obj(path, &options)?;
+    // A missing .mtl file only costs us the color
+    let materials = materials.unwrap_or_default();
+
+    let mut mesh = Mesh::default();
+    for model in models {
+        let part = Mesh {
+            vertices: model
+                .mesh
+                .positions
+                .chunks_exact(3)
+                .map(|p| (p[0] as f64, p[1] as f64, p[2] as f64))
+                .collect(),
+            triangles: model
+                .mesh
+                .indices
+                .chunks_exact(3)
+                .map(|t| (t[0], t[1], t[2]))
+                .collect(),
+            color: model
+                .mesh
+                .material_id
+                .and_then(|id| materials.get(id)?.diffuse)
+                .map(|[r, g, b]| css_color([r as f64, g as f64, b as f64, 1.0])),
+        };
+        mesh.append(&part, &Pose::identity());
+    }
+    Ok(mesh)
+}
+
+fn load_stl(path: &Path) -> Result<Mesh, anyhow::Error> {
+    let mut file = std::fs::File::open(path)?;
+    let stl = stl_io::read_stl(&mut file)?;
+    Ok(Mesh {
+        vertices: stl
+            .vertices
+            .iter()
+            .map(|v| (v[0] as f64, v[1] as f64, v[2] as f64))
+            .collect(),
+        triangles: stl
+            .faces
+            .iter()
+            .map(|f| {
+                (
+                    f.vertices[0] as u32,
+                    f.vertices[1] as u32,
+                    f.vertices[2] as u32,
+                )
+            })
+            .collect(),
+        color: None,
+    })
+}
+
+fn load_gltf(path: &Path) -> Result<Mesh, anyhow::Error> {
+    let gltf = gltf::Gltf::from_slice(&std::fs::read(path)?)?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let buffers = gltf
+        .buffers()
+        .map(|buffer| match buffer.source() {
+            gltf::buffer::Source::Bin => gltf
+                .blob
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("Missing binary chunk")),
+            gltf::buffer::Source::Uri(uri) => match uri.strip_prefix("data:") {
+                Some(data) => {
+                    let (_, encoded) = data
+                        .split_once(";base64,")
+                        .ok_or_else(|| anyhow::anyhow!("Unsupported data URI"))?;
+                    Ok(base64::engine::general_purpose::STANDARD.decode(encoded)?)
+                }
+                None => Ok(std::fs::read(dir.join(uri))?),
+            },
+        })
+        .collect::<Result<Vec<_>, anyhow::Error>>()?;
+
+    let scene = gltf
+        .default_scene()
+        .or_else(|| gltf.scenes().next())
+        .ok_or_else(|| anyhow::anyhow!("No scene"))?;
+    let mut mesh = Mesh::default();
+    let mut nodes: Vec<_> = scene.nodes().map(|node| (node, IDENTITY)).collect();
+    while let Some((node, parent)) = nodes.pop() {
+        let transform = mul(&parent, &node.transform().matrix());
+        for child in node.children() {
+            nodes.push((child, transform));
+        }
+        let Some(node_mesh) = node.mesh() else {
+            continue;
+        };
+        for primitive in node_mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+            let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|b| &b[..]));
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+            let vertices: Vec<_> = positions.map(|p| apply(&transform, p)).collect();
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..vertices.len() as u32).collect(),
+            };
+            let [r, g, b, a] = primitive
+                .material()
+                .pbr_metallic_roughness()
+                .base_color_factor();
+            let part = Mesh {
+                vertices,
+                triangles: indices
+                    .chunks_exact(3)
+                    .map(|t| (t[0], t[1], t[2]))
+                    .collect(),
+                color: Some(css_color([r as f64, g as f64, b as f64, a as f64])),
+            };
+            mesh.append(&part, &Pose::identity());
+        }
+    }
+    Ok(mesh)
+}
+
+/// Column-major 4x4 matrices, as glTF stores node transforms.
+type Matrix = [[f32; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+fn mul(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = [[0.0; 4]; 4];
+    for (col, out_col) in out.iter_mut().enumerate() {
+        for (row, value) in out_col.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[col][k]).sum();
+        }
+    }
+    out
+}
+
+fn apply(m: &Matrix, p: [f32; 3]) -> (f64, f64, f64) {
+    let coord =
+        |row: usize| (m[0][row] * p[0] + m[1][row] * p[1] + m[2][row] * p[2] + m[3][row]) as f64;
+    (coord(0), coord(1), coord(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fundamentals-mesh-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn obj_faces_are_triangulated() {
+        let obj = b"v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n";
+        let mesh = load_mesh(&temp_file("quad.obj", obj)).unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.triangles.len(), 2);
+        assert_eq!(mesh.vertices[2], (1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn stl_vertices_are_shared() {
+        let stl = b"solid t
+facet normal 0 0 1
+outer loop
+vertex 0 0 0
+vertex 1 0 0
+vertex 0 1 0
+endloop
+endfacet
+facet normal 0 0 1
+outer loop
+vertex 1 0 0
+vertex 1 1 0
+vertex 0 1 0
+endloop
+endfacet
+endsolid t
+";
+        let mesh = load_mesh(&temp_file("two.stl", stl)).unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.triangles.len(), 2);
+        let (a, b, c) = mesh.triangles[1];
+        assert_eq!(
+            [a, b, c].map(|i| mesh.vertices[i as usize]),
+            [(1.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn gltf_node_transforms_are_applied() {
+        let positions: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(&positions)
+        );
+        // The child node is scaled by 2, then moved along x by its parent
+        let gltf = serde_json::json!({
+            "asset": {"version": "2.0"},
+            "scene": 0,
+            "scenes": [{"nodes": [0]}],
+            "nodes": [
+                {"translation": [5.0, 0.0, 0.0], "children": [1]},
+                {"mesh": 0, "scale": [2.0, 2.0, 2.0]}
+            ],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0}}]}],
+            "buffers": [{"byteLength": 36, "uri": uri}],
+            "bufferViews": [{"buffer": 0, "byteLength": 36}],
+            "accessors": [{
+                "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+            }]
+        });
+        let path = temp_file("triangle.gltf", gltf.to_string().as_bytes());
+        let mesh = load_mesh(&path).unwrap();
+        assert_eq!(
+            mesh.vertices,
+            vec![(5.0, 0.0, 0.0), (7.0, 0.0, 0.0), (5.0, 2.0, 0.0)]
+        );
+        assert_eq!(mesh.triangles, vec![(0, 1, 2)]);
+        assert_eq!(mesh.color.as_deref(), Some("rgba(255, 255, 255, 1)"));
+    }
+
+    #[test]
+    fn matrices_compose_column_major() {
+        let mut translate = IDENTITY;
+        translate[3] = [1.0, 2.0, 3.0, 1.0];
+        let mut scale = IDENTITY;
+        (scale[0][0], scale[1][1], scale[2][2]) = (2.0, 2.0, 2.0);
+        assert_eq!(
+            apply(&mul(&translate, &scale), [1.0, 1.0, 1.0]),
+            (3.0, 4.0, 5.0)
+        );
+        assert_eq!(
+            apply(&mul(&scale, &translate), [1.0, 1.0, 1.0]),
+            (4.0, 6.0, 8.0)
+        );
+        assert_eq!(mul(&IDENTITY, &translate), translate);
+    }
+
+    #[test]
+    fn formats_come_from_the_extension() {
+        assert_eq!(
+            MeshFormat::from_path(Path::new("a/b.STL")),
+            Some(MeshFormat::Stl)
+        );
+        assert_eq!(
+            MeshFormat::from_path(Path::new("b.glb")),
+            Some(MeshFormat::Gltf)
+        );
+        assert_eq!(MeshFormat::from_path(Path::new("b.dae")), None);
+        assert!(load_mesh(Path::new("missing.dae")).is_err());
+        assert_eq!(
+            css_color([1.0, 0.5, 2.0, 0.25]),
+            "rgba(255, 128, 255, 0.25)"
+        );
+    }
+}
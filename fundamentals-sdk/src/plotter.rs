@@ -1,45 +1,126 @@
 use fundamentals_core::{
     recording::Recording,
     viz::Viz,
-    widgets::{plot_scalar::PlotScalarData, Widget},
+    widgets::{plot_scalar::PlotScalarData, precision::Precision, Widget},
+};
+
+use crate::{
+    decimation::{Decimation, Decimator},
+    retention::{RetentionBuffer, RetentionPolicy, RetentionStats},
 };
 
 pub struct Plotter {
     pub name: String,
-    pub points_x: Vec<f64>,
-    pub points_y: Vec<f64>,
+    points: RetentionBuffer<f64>,
+    decimator: Decimator,
+    precision: Precision,
 }
 
 impl Plotter {
     pub fn new(name: &str) -> Self {
         Self {
             name: name.to_string(),
-            points_x: Vec::new(),
-            points_y: Vec::new(),
+            points: RetentionBuffer::new(name, RetentionPolicy::Unbounded),
+            decimator: Decimator::new(Decimation::None),
+            precision: Precision::F64,
         }
     }
 
+    pub fn with_decimation(mut self, decimation: Decimation) -> Self {
+        self.decimator = Decimator::new(decimation);
+        self
+    }
+
+    pub fn with_min_interval(self, interval: f64) -> Self {
+        self.with_decimation(Decimation::MinInterval(interval))
+    }
+
+    pub fn with_keep_every_nth(self, n: usize) -> Self {
+        self.with_decimation(Decimation::KeepEveryNth(n))
+    }
+
+    pub fn with_min_max_buckets(self, bucket_size: usize) -> Self {
+        self.with_decimation(Decimation::MinMax(bucket_size))
+    }
+
+    /// Saves and sends values as `f32` or quantized `i16` instead of `f64`. Points
+    /// are still added, and kept in memory, as `f64`.
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.points.set_policy(policy);
+        self
+    }
+
     pub fn add_point(&mut self, x: f64, y: f64) {
-        self.points_x.push(x);
-        self.points_y.push(y);
+        for (x, y) in self.decimator.push(x, y) {
+            self.points.push(x, y);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    #[deprecated(note = "points are kept in a retention buffer, use `as_scalar_data`")]
+    pub fn points_x(&self) -> Vec<f64> {
+        self.as_scalar_data()
+            .data_x
+            .iter()
+            .map(|(x, _)| *x)
+            .collect()
+    }
+
+    #[deprecated(note = "points are kept in a retention buffer, use `as_scalar_data`")]
+    pub fn points_y(&self) -> Vec<f64> {
+        self.as_scalar_data()
+            .data_x
+            .iter()
+            .map(|(_, y)| *y)
+            .collect()
+    }
+
+    pub fn retention_stats(&self) -> RetentionStats {
+        self.points.stats()
     }
 
     pub fn log(&self, recording: &mut Recording) {
         recording.add_viz(self.as_viz());
     }
 
+    /// Like `log`, but moves the points into the recording instead of copying them.
+    pub fn log_owned(self, recording: &mut Recording) {
+        recording.add_viz(self.into_viz());
+    }
+
     pub fn as_scalar_data(&self) -> PlotScalarData {
-        PlotScalarData::new(
-            self.points_x
-                .iter()
-                .zip(self.points_y.iter())
-                .map(|(x, y)| (*x, *y))
-                .collect(),
-        )
+        let mut data = self.points.to_vec();
+        data.extend(self.decimator.pending());
+        PlotScalarData::new(data).with_precision(self.precision)
     }
     pub fn as_viz(&self) -> Viz {
         let plot_scalar_data = self.as_scalar_data();
         let widget = Widget::PlotScalar(plot_scalar_data);
-        Viz::new(self.name.clone()).with_widget(widget)
+        let mut viz = Viz::new(self.name.clone()).with_widget(widget);
+        self.points.stats().annotate(&mut viz);
+        self.decimator.annotate(&mut viz);
+        viz
+    }
+
+    pub fn into_viz(self) -> Viz {
+        let mut viz = Viz::new(self.name);
+        self.points.stats().annotate(&mut viz);
+        self.decimator.annotate(&mut viz);
+        let mut data = self.points.into_vec();
+        data.extend(self.decimator.pending());
+        let data = PlotScalarData::new(data).with_precision(self.precision);
+        viz.with_widget(Widget::PlotScalar(data))
     }
 }
@@ -0,0 +1,303 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use fundamentals_core::viz::Viz;
+use log::error;
+use serde::Serialize;
+
+pub const DROPPED_KEY: &str = "retention.dropped";
+pub const SPILLED_KEY: &str = "retention.spilled";
+
+/// How many samples a logging helper keeps in memory.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RetentionPolicy {
+    /// Keep everything (the default).
+    Unbounded,
+    /// Keep only the most recent `n` samples.
+    KeepLast(usize),
+    /// Keep only samples within `seconds` of the newest one.
+    KeepDuration(f64),
+    /// Keep the most recent `keep_last` samples in memory and write older
+    /// chunks of `keep_last` samples into `dir`, as JSON files named
+    /// `{name}_{n}.spill` that never replace an existing file.
+    SpillToDisk { keep_last: usize, dir: PathBuf },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionStats {
+    pub dropped: u64,
+    pub spilled: u64,
+}
+
+impl RetentionStats {
+    pub fn is_empty(&self) -> bool {
+        self.dropped == 0 && self.spilled == 0
+    }
+
+    /// Records the counts in the viz metadata so they survive into the recording.
+    pub fn annotate(&self, viz: &mut Viz) {
+        if self.is_empty() {
+            return;
+        }
+        viz.set_metadata(DROPPED_KEY, self.dropped);
+        viz.set_metadata(SPILLED_KEY, self.spilled);
+    }
+
+    pub fn from_viz(viz: &Viz) -> Self {
+        let count = |key| {
+            viz.get_metadata(key)
+                .and_then(|v| v.as_u64())
+                .unwrap_or_default()
+        };
+        Self {
+            dropped: count(DROPPED_KEY),
+            spilled: count(SPILLED_KEY),
+        }
+    }
+}
+
+/// Time-ordered ring buffer that enforces a `RetentionPolicy` on push.
+#[derive(Debug, Clone)]
+pub struct RetentionBuffer<T> {
+    name: String,
+    policy: RetentionPolicy,
+    samples: VecDeque<(f64, T)>,
+    stats: RetentionStats,
+    spill_chunks: usize,
+    rebase: Option<Rebase<T>>,
+}
+
+/// Rewrites the retained samples after the evicted ones before them are removed,
+/// e.g. `ThreeDViewData::rebase` to turn a leading delta back into a keyframe.
+pub type Rebase<T> = fn(&[(f64, T)], &mut [(f64, T)]);
+
+impl<T: Clone + Serialize> RetentionBuffer<T> {
+    pub fn new(name: &str, policy: RetentionPolicy) -> Self {
+        Self {
+            name: name.to_string(),
+            policy,
+            samples: VecDeque::new(),
+            stats: RetentionStats::default(),
+            spill_chunks: 0,
+            rebase: None,
+        }
+    }
+
+    /// Lets samples depend on earlier ones: `rebase` is called with the evicted
+    /// samples whenever some are dropped or spilled.
+    pub fn with_rebase(mut self, rebase: Rebase<T>) -> Self {
+        self.rebase = Some(rebase);
+        self
+    }
+
+    pub fn set_policy(&mut self, policy: RetentionPolicy) {
+        self.policy = policy;
+        self.enforce();
+    }
+
+    pub fn policy(&self) -> &RetentionPolicy {
+        &self.policy
+    }
+
+    pub fn stats(&self) -> RetentionStats {
+        self.stats
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(f64, T)> {
+        self.samples.iter()
+    }
+
+    pub fn last(&self) -> Option<&(f64, T)> {
+        self.samples.back()
+    }
+
+    pub fn to_vec(&self) -> Vec<(f64, T)> {
+        self.samples.iter().cloned().collect()
+    }
+
+    /// The retained samples, moved out without cloning them.
+    pub fn into_vec(self) -> Vec<(f64, T)> {
+        self.samples.into()
+    }
+
+    pub fn push(&mut self, time: f64, value: T) {
+        self.samples.push_back((time, value));
+        self.enforce();
+    }
+
+    fn enforce(&mut self) {
+        match self.policy.clone() {
+            RetentionPolicy::Unbounded => {}
+            RetentionPolicy::KeepLast(n) => {
+                let excess = self.samples.len().saturating_sub(n);
+                self.stats.dropped += self.evict(excess).len() as u64;
+            }
+            RetentionPolicy::KeepDuration(seconds) => {
+                let Some(newest) = self.samples.back().map(|(t, _)| *t) else {
+                    return;
+                };
+                let expired = self
+                    .samples
+                    .iter()
+                    .position(|(t, _)| newest - *t <= seconds)
+                    .unwrap_or(self.samples.len());
+                self.stats.dropped += self.evict(expired).len() as u64;
+            }
+            RetentionPolicy::SpillToDisk { keep_last, dir } => {
+                // Spill in chunks of `keep_last` so we don't write a file per sample.
+                let chunk_size = keep_last.max(1);
+                while self.samples.len() >= keep_last + chunk_size {
+                    let chunk = self.evict(chunk_size);
+                    let count = chunk.len() as u64;
+                    match self.spill(&dir, &chunk) {
+                        Ok(_) => self.stats.spilled += count,
+                        Err(e) => {
+                            error!("Failed to spill {} samples of {}: {}", count, self.name, e);
+                            self.stats.dropped += count;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes the `count` oldest samples, rebasing the ones that are left.
+    fn evict(&mut self, count: usize) -> Vec<(f64, T)> {
+        let evicted: Vec<(f64, T)> = self.samples.drain(..count).collect();
+        if let Some(rebase) = self.rebase.filter(|_| !evicted.is_empty()) {
+            rebase(&evicted, self.samples.make_contiguous());
+        }
+        evicted
+    }
+
+    fn spill(&mut self, dir: &Path, chunk: &[(f64, T)]) -> Result<PathBuf, anyhow::Error> {
+        std::fs::create_dir_all(dir)?;
+        let stem: String = self
+            .name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        // Chunks of earlier runs, or of vizs with a similar name, are left alone.
+        // The extension keeps them out of directory scans for recordings.
+        let (path, file) = loop {
+            let path = dir.join(format!("{}_{:06}.spill", stem, self.spill_chunks));
+            self.spill_chunks += 1;
+            match File::create_new(&path) {
+                Ok(file) => break (path, file),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        serde_json::to_writer(BufWriter::new(file), chunk)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plotter::Plotter;
+
+    fn plotter(policy: RetentionPolicy, times: impl IntoIterator<Item = f64>) -> Plotter {
+        let mut plotter = Plotter::new("speed/front").with_retention(policy);
+        for t in times {
+            plotter.add_point(t, t * 10.0);
+        }
+        plotter
+    }
+
+    fn times(plotter: &Plotter) -> Vec<f64> {
+        plotter
+            .as_scalar_data()
+            .data_x
+            .iter()
+            .map(|(t, _)| *t)
+            .collect()
+    }
+
+    #[test]
+    fn keep_last_drops_the_oldest_samples() {
+        let plotter = plotter(RetentionPolicy::KeepLast(3), (0..5).map(f64::from));
+        assert_eq!(times(&plotter), vec![2.0, 3.0, 4.0]);
+        assert_eq!(
+            plotter.retention_stats(),
+            RetentionStats {
+                dropped: 2,
+                spilled: 0
+            }
+        );
+
+        let viz = plotter.as_viz();
+        assert_eq!(viz.get_metadata(DROPPED_KEY), Some(&2.into()));
+        assert_eq!(RetentionStats::from_viz(&viz), plotter.retention_stats());
+    }
+
+    #[test]
+    fn keep_duration_keeps_samples_within_the_window() {
+        let mut plotter = plotter(RetentionPolicy::KeepDuration(1.0), [0.0, 0.5, 1.0, 1.5]);
+        assert_eq!(times(&plotter), vec![0.5, 1.0, 1.5]);
+        plotter.add_point(4.0, 0.0);
+        assert_eq!(times(&plotter), vec![4.0]);
+        assert_eq!(plotter.retention_stats().dropped, 4);
+    }
+
+    #[test]
+    fn tightening_the_policy_evicts_at_once() {
+        let mut buffer = RetentionBuffer::new("speed", RetentionPolicy::Unbounded);
+        for t in 0..4 {
+            buffer.push(t as f64, t);
+        }
+        buffer.set_policy(RetentionPolicy::KeepLast(1));
+        assert_eq!(buffer.to_vec(), vec![(3.0, 3)]);
+        assert!(Plotter::new("speed").retention_stats().is_empty());
+    }
+
+    #[test]
+    fn spill_to_disk_writes_chunks_in_order() {
+        let dir = std::env::temp_dir().join(format!("fundamentals-spill-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // A chunk of an earlier run is left alone
+        std::fs::write(dir.join("speed_front_000000.spill"), "earlier").unwrap();
+
+        let policy = RetentionPolicy::SpillToDisk {
+            keep_last: 2,
+            dir: dir.clone(),
+        };
+        let plotter = plotter(policy, (0..7).map(f64::from));
+        assert_eq!(times(&plotter), vec![4.0, 5.0, 6.0]);
+        assert_eq!(
+            plotter.retention_stats(),
+            RetentionStats {
+                dropped: 0,
+                spilled: 4
+            }
+        );
+
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("speed_front_000000.spill"), "earlier");
+        let chunk = |name: &str| serde_json::from_str::<Vec<(f64, f64)>>(&read(name)).unwrap();
+        assert_eq!(
+            chunk("speed_front_000001.spill"),
+            vec![(0.0, 0.0), (1.0, 10.0)]
+        );
+        assert_eq!(
+            chunk("speed_front_000002.spill"),
+            vec![(2.0, 20.0), (3.0, 30.0)]
+        );
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,90 @@
+use fundamentals_core::{
+    recording::Recording,
+    viz::Viz,
+    widgets::{
+        tensor::{Tensor, TensorData, TensorElement},
+        Widget,
+    },
+};
+
+use crate::retention::{RetentionBuffer, RetentionPolicy, RetentionStats};
+
+/// Logs n-dimensional arrays (cost maps, grids, attention matrices), one per time.
+pub struct TensorLogger {
+    pub name: String,
+    frames: RetentionBuffer<Tensor>,
+}
+
+impl TensorLogger {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            frames: RetentionBuffer::new(name, RetentionPolicy::Unbounded),
+        }
+    }
+
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.frames.set_policy(policy);
+        self
+    }
+
+    pub fn add_tensor(&mut self, tensor: Tensor, time: f64) {
+        self.frames.push(time, tensor);
+    }
+
+    /// Logs a row-major buffer of the given shape.
+    pub fn add_values<T: TensorElement>(
+        &mut self,
+        shape: Vec<usize>,
+        values: Vec<T>,
+        time: f64,
+    ) -> Result<(), anyhow::Error> {
+        self.add_tensor(Tensor::from_vec(shape, values)?, time);
+        Ok(())
+    }
+
+    #[cfg(feature = "ndarray")]
+    pub fn add_array<T, S, D>(&mut self, array: &ndarray::ArrayBase<S, D>, time: f64)
+    where
+        T: TensorElement,
+        S: ndarray::Data<Elem = T>,
+        D: ndarray::Dimension,
+    {
+        self.add_tensor(tensor_from_array(array), time);
+    }
+
+    pub fn retention_stats(&self) -> RetentionStats {
+        self.frames.stats()
+    }
+
+    pub fn log(&self, recording: &mut Recording) {
+        recording.add_viz(self.as_viz());
+    }
+
+    pub fn as_tensor_data(&self) -> TensorData {
+        TensorData {
+            frames: self.frames.to_vec(),
+        }
+    }
+
+    pub fn as_viz(&self) -> Viz {
+        let widget = Widget::Tensor(self.as_tensor_data());
+        let mut viz = Viz::new(self.name.clone()).with_widget(widget);
+        self.frames.stats().annotate(&mut viz);
+        viz
+    }
+}
+
+/// Copies any `ndarray` array (including `ArrayD` and views) into a row-major tensor.
+#[cfg(feature = "ndarray")]
+pub fn tensor_from_array<T, S, D>(array: &ndarray::ArrayBase<S, D>) -> Tensor
+where
+    T: TensorElement,
+    S: ndarray::Data<Elem = T>,
+    D: ndarray::Dimension,
+{
+    // `iter` walks in logical (row-major) order whatever the memory layout
+    let values: Vec<T> = array.iter().copied().collect();
+    Tensor::from_vec(array.shape().to_vec(), values)
+        .expect("an ndarray's shape always matches its element count")
+}
@@ -0,0 +1,314 @@
+//! Golden-recording assertions for `cargo test`.
+//!
+//! ```ignore
+//! let tolerances = Tolerances::rel(0.02);
+//! assert_recording_matches(&logger.recording, "tests/golden/step_response.json", &tolerances);
+//! ```
+//!
+//! Run with `FUNDAMENTALS_BLESS=1` to write the actual recording over the golden
+//! file instead of comparing.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use fundamentals_core::{
+    compare::Comparison, compression::Compression, recording::Recording, widgets::Widget,
+};
+
+/// Set to `1` or `true` to overwrite golden recordings with the actual ones.
+pub const BLESS_ENV: &str = "FUNDAMENTALS_BLESS";
+
+/// How far a sample may stray from the golden value: `|actual - golden| <= abs + rel * |golden|`.
+#[derive(Debug, Clone, Default)]
+pub struct Tolerances {
+    pub abs: f64,
+    pub rel: f64,
+    /// Overrides for individual vizs, by name.
+    pub per_viz: BTreeMap<String, Tolerances>,
+}
+
+impl Tolerances {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn abs(abs: f64) -> Self {
+        Self::new().with_abs(abs)
+    }
+
+    pub fn rel(rel: f64) -> Self {
+        Self::new().with_rel(rel)
+    }
+
+    pub fn with_abs(mut self, abs: f64) -> Self {
+        self.abs = abs;
+        self
+    }
+
+    pub fn with_rel(mut self, rel: f64) -> Self {
+        self.rel = rel;
+        self
+    }
+
+    pub fn with_viz(mut self, name: &str, tolerances: Tolerances) -> Self {
+        self.per_viz.insert(name.to_string(), tolerances);
+        self
+    }
+
+    pub fn for_viz(&self, name: &str) -> &Tolerances {
+        self.per_viz.get(name).unwrap_or(self)
+    }
+
+    /// NaN is only allowed where the golden value is NaN too.
+    pub fn allows(&self, actual: f64, golden: f64) -> bool {
+        actual == golden
+            || (actual.is_nan() && golden.is_nan())
+            || (actual - golden).abs() <= self.abs + self.rel * golden.abs()
+    }
+}
+
+/// Panics unless `actual` matches the golden recording at `golden_path` within
+/// `tolerances`. See [`check_recording_matches`].
+#[track_caller]
+pub fn assert_recording_matches(
+    actual: &Recording,
+    golden_path: impl AsRef<Path>,
+    tolerances: &Tolerances,
+) {
+    if let Err(e) = check_recording_matches(actual, golden_path, tolerances) {
+        panic!("{:#}", e);
+    }
+}
+
+/// Compares `actual` against the golden recording at `golden_path`. Scalar series
+/// are aligned by viz name and time, interpolating the golden series, and must
+/// have as many samples over the same time range as the golden ones.
+///
+/// On mismatch the actual recording and a diff recording are written next to the
+/// golden file as `<golden>.actual.json` and `<golden>.diff.json`, with the golden
+/// file's extensions, ready to open in the viewer. In bless mode the golden file is overwritten and the check passes.
+pub fn check_recording_matches(
+    actual: &Recording,
+    golden_path: impl AsRef<Path>,
+    tolerances: &Tolerances,
+) -> Result<(), anyhow::Error> {
+    let golden_path = golden_path.as_ref().to_path_buf();
+    if blessing() {
+        save(actual, &golden_path)?;
+        log::info!("Blessed {}", golden_path.display());
+        return Ok(());
+    }
+
+    let actual_path = sibling(&golden_path, "actual");
+    if !golden_path.exists() {
+        save(actual, &actual_path)?;
+        anyhow::bail!(
+            "Golden recording {} does not exist; wrote {}. Run with {}=1 to create it",
+            golden_path.display(),
+            actual_path.display(),
+            BLESS_ENV
+        );
+    }
+    let golden = Recording::load_from_file(&golden_path)
+        .with_context(|| format!("Failed to load {}", golden_path.display()))?;
+
+    let failures = mismatches(&golden, actual, tolerances);
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    let diff_path = sibling(&golden_path, "diff");
+    actual.save_to_file(&actual_path)?;
+    Comparison::new(&golden, actual)
+        .diff_recording()
+        .save_to_file(&diff_path)?;
+    anyhow::bail!(
+        "Recording does not match {}:\n  {}\nWrote {} and {}. Run with {}=1 to accept the new output",
+        golden_path.display(),
+        failures.join("\n  "),
+        actual_path.display(),
+        diff_path.display(),
+        BLESS_ENV
+    )
+}
+
+fn save(recording: &Recording, path: &PathBuf) -> Result<(), anyhow::Error> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    recording.save_to_file(path)
+}
+
+fn blessing() -> bool {
+    std::env::var(BLESS_ENV).is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// `dir/golden.json.zst` -> `dir/golden.<suffix>.json.zst`, keeping the golden
+/// file's format and compression.
+fn sibling(golden_path: &Path, suffix: &str) -> PathBuf {
+    let name = golden_path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let stem = Compression::strip_extension(Path::new(&name))
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extensions = match &name[stem.len()..] {
+        "" => ".json",
+        extensions => extensions,
+    };
+    golden_path.with_file_name(format!("{}.{}{}", stem, suffix, extensions))
+}
+
+/// One line per viz or series that is out of tolerance. The golden recording is
+/// the baseline of the comparison.
+fn mismatches(golden: &Recording, actual: &Recording, tolerances: &Tolerances) -> Vec<String> {
+    let comparison = Comparison::new(golden, actual);
+    // Samples are only checked where the golden series has data, so a run that
+    // stops early or keeps going is caught by the sample count and range here
+    let mut failures = comparison.shape_failures();
+    for viz_diff in comparison.vizs.iter() {
+        let (Some(expected), Some(found)) = (
+            golden.get_viz(&viz_diff.name),
+            actual.get_viz(&viz_diff.name),
+        ) else {
+            continue;
+        };
+        let tolerances = tolerances.for_viz(&expected.name);
+        for (index, pair) in expected.widgets.iter().zip(&found.widgets).enumerate() {
+            let (Widget::PlotScalar(expected_data), Widget::PlotScalar(found_data)) = pair else {
+                continue;
+            };
+            // Values that are NaN in only one run are already reported
+            let outside: Vec<(f64, f64, f64)> = found_data
+                .data_x
+                .iter()
+                .filter_map(|&(t, y)| Some((t, y, expected_data.interpolate(t)?)))
+                .filter(|&(_, y, g)| y.is_nan() == g.is_nan() && !tolerances.allows(y, g))
+                .collect();
+            if let Some(&(t, y, g)) = outside.first() {
+                failures.push(format!(
+                    "{}[{}]: {} samples out of tolerance, first at t={} (actual {}, golden {})",
+                    expected.name,
+                    index,
+                    outside.len(),
+                    t,
+                    y,
+                    g
+                ));
+            }
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fundamentals_core::{viz::Viz, widgets::plot_scalar::PlotScalarData};
+
+    fn recording(data: Vec<(f64, f64)>) -> Recording {
+        let mut recording = Recording::new("run".to_string(), "run".to_string());
+        recording.add_viz(
+            Viz::new("speed".to_string())
+                .with_widget(Widget::PlotScalar(PlotScalarData::new(data))),
+        );
+        recording
+    }
+
+    fn ramp(n: usize) -> Vec<(f64, f64)> {
+        (0..n).map(|i| (i as f64, i as f64)).collect()
+    }
+
+    #[test]
+    fn tolerances_allow_matching_nan_only() {
+        let tolerances = Tolerances::abs(0.1).with_rel(0.01);
+        assert!(tolerances.allows(10.2, 10.0));
+        assert!(!tolerances.allows(10.3, 10.0));
+        assert!(tolerances.allows(f64::NAN, f64::NAN));
+        assert!(!tolerances.allows(f64::NAN, 1.0));
+        assert!(!tolerances.allows(1.0, f64::NAN));
+        assert!(tolerances.allows(f64::INFINITY, f64::INFINITY));
+    }
+
+    #[test]
+    fn matching_run_has_no_mismatches() {
+        let tolerances = Tolerances::abs(0.01);
+        assert!(mismatches(&recording(ramp(10)), &recording(ramp(10)), &tolerances).is_empty());
+    }
+
+    #[test]
+    fn out_of_tolerance_samples_are_reported() {
+        let mut data = ramp(10);
+        data[3].1 += 1.0;
+        let failures = mismatches(
+            &recording(ramp(10)),
+            &recording(data),
+            &Tolerances::abs(0.5),
+        );
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].contains("t=3"), "{}", failures[0]);
+    }
+
+    #[test]
+    fn short_long_and_empty_runs_fail() {
+        let tolerances = Tolerances::abs(0.5);
+        let golden = recording(ramp(10));
+        for actual in [ramp(5), ramp(20), Vec::new()] {
+            let failures = mismatches(&golden, &recording(actual), &tolerances);
+            assert_eq!(failures.len(), 1, "{:?}", failures);
+            assert!(failures[0].contains("baseline has 10"), "{}", failures[0]);
+        }
+    }
+
+    #[test]
+    fn failed_check_writes_actual_and_diff() {
+        let dir = std::env::temp_dir().join(format!("fundamentals-golden-{}", std::process::id()));
+        let golden_path = dir.join("golden.json");
+        let tolerances = Tolerances::abs(0.5);
+
+        assert!(check_recording_matches(&recording(ramp(4)), &golden_path, &tolerances).is_err());
+        assert!(dir.join("golden.actual.json").exists());
+        save(&recording(ramp(4)), &golden_path).unwrap();
+        check_recording_matches(&recording(ramp(4)), &golden_path, &tolerances).unwrap();
+        assert!(check_recording_matches(&recording(ramp(3)), &golden_path, &tolerances).is_err());
+        assert!(dir.join("golden.diff.json").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_golden_with_nan_is_checked() {
+        let dir = std::env::temp_dir().join(format!("fundamentals-nan-{}", std::process::id()));
+        let golden_path = dir.join("nan.json");
+        let mut data = ramp(5);
+        data[2].1 = f64::NAN;
+        save(&recording(data.clone()), &golden_path).unwrap();
+        let tolerances = Tolerances::abs(0.5);
+        check_recording_matches(&recording(data), &golden_path, &tolerances).unwrap();
+
+        let error = check_recording_matches(&recording(ramp(5)), &golden_path, &tolerances)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("NaN in only one run"), "{}", error);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn siblings_keep_format_and_compression() {
+        let sibling = |name: &str| sibling(&Path::new("golden").join(name), "actual");
+        assert_eq!(sibling("run.json"), Path::new("golden/run.actual.json"));
+        assert_eq!(
+            sibling("run.json.zst"),
+            Path::new("golden/run.actual.json.zst")
+        );
+        assert_eq!(
+            sibling("run.msgpack"),
+            Path::new("golden/run.actual.msgpack")
+        );
+        assert_eq!(sibling("run"), Path::new("golden/run.actual.json"));
+    }
+}
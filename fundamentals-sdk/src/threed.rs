@@ -1,44 +1,377 @@
 use core::time;
+use std::collections::BTreeSet;
 
 use fundamentals_core::{
+    assets::{Asset, AssetTable},
     recording::Recording,
     viz::Viz,
     widgets::{
-        three_d_view::{ThreeDPrimative, ThreeDViewData},
+        mesh::{Mesh, MeshInstance, Pose},
+        occupancy::{OccupancyGrid, Voxel, VoxelGrid, VoxelUpdate},
+        precision::Precision,
+        three_d_view::{PointDeltaEncoder, ThreeDPrimative, ThreeDViewData},
         Widget,
     },
 };
 
+use crate::retention::{RetentionBuffer, RetentionPolicy, RetentionStats};
+
+/// Resolution, origin and voxels of the last `add_voxels` call.
+type VoxelState = (f64, (f64, f64, f64), BTreeSet<Voxel>);
+
 pub struct ThreeDView {
     name: String,
-    primatives: Vec<(f64, ThreeDPrimative)>,
+    primatives: RetentionBuffer<ThreeDPrimative>,
+    point_deltas: Option<PointDeltaEncoder>,
+    precision: Precision,
+    last_grid: Option<OccupancyGrid>,
+    last_voxels: Option<VoxelState>,
+    /// Assets referenced by the primitives, added to the recording on `log`.
+    assets: AssetTable,
 }
 
 impl ThreeDView {
     pub fn new(name: &str) -> Self {
         Self {
             name: name.to_string(),
-            primatives: Vec::new(),
+            primatives: RetentionBuffer::new(name, RetentionPolicy::Unbounded)
+                .with_rebase(ThreeDViewData::rebase),
+            point_deltas: None,
+            precision: Precision::F64,
+            last_grid: None,
+            last_voxels: None,
+            assets: AssetTable::new(),
+        }
+    }
+
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.primatives.set_policy(policy);
+        self
+    }
+
+    /// Stores point clouds as keyframes and deltas, e.g.
+    /// `PointDeltaEncoder::new(10)` for a full cloud every 10 frames.
+    pub fn with_point_deltas(mut self, encoder: PointDeltaEncoder) -> Self {
+        self.point_deltas = Some(encoder);
+        self
+    }
+
+    /// Stores point coordinates as `f32` or quantized `i16` instead of `f64`, e.g.
+    /// `Precision::I16 { scale: 0.001, offset: 0.0 }` for millimeters within 32 m.
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    pub fn add_points(&mut self, mut points: Vec<(f64, f64, f64)>, time: f64) {
+        let precision = self.precision;
+        if !precision.is_f64() {
+            // Round first so deltas are taken against what a reader will decode
+            for (x, y, z) in points.iter_mut() {
+                (*x, *y, *z) = (
+                    precision.round(*x),
+                    precision.round(*y),
+                    precision.round(*z),
+                );
+            }
+        }
+        let primative = match self.point_deltas.as_mut() {
+            Some(encoder) => encoder.encode(points),
+            None => ThreeDPrimative::Point(points),
+        };
+        let primative = match primative {
+            ThreeDPrimative::Point(points) => ThreeDPrimative::points(&points, precision),
+            primative => primative,
+        };
+        self.primatives.push(time, primative);
+    }
+
+    /// Logs the current state of an occupancy grid. Only the cells that changed
+    /// since the previous call are stored, unless the grid was resized or moved.
+    pub fn add_occupancy_grid(&mut self, grid: OccupancyGrid, time: f64) {
+        match self.last_grid.as_ref() {
+            Some(last) if last.same_layout(&grid) => {
+                if let Some(update) = last.diff(&grid) {
+                    self.primatives
+                        .push(time, ThreeDPrimative::OccupancyGridUpdate(update));
+                }
+            }
+            _ => self
+                .primatives
+                .push(time, ThreeDPrimative::OccupancyGrid(grid.clone())),
+        }
+        self.last_grid = Some(grid);
+    }
+
+    /// Logs the set of occupied voxels. Only voxels that were set or cleared since
+    /// the previous call are stored, unless the resolution or origin changed.
+    pub fn add_voxels(
+        &mut self,
+        resolution: f64,
+        origin: (f64, f64, f64),
+        voxels: BTreeSet<Voxel>,
+        time: f64,
+    ) {
+        match self.last_voxels.as_ref() {
+            Some((last_resolution, last_origin, previous))
+                if *last_resolution == resolution && *last_origin == origin =>
+            {
+                let update = VoxelUpdate::between(previous, &voxels);
+                if !update.is_empty() {
+                    self.primatives
+                        .push(time, ThreeDPrimative::VoxelGridUpdate(update));
+                }
+            }
+            _ => self.primatives.push(
+                time,
+                ThreeDPrimative::VoxelGrid(VoxelGrid::new(resolution, origin, &voxels)),
+            ),
+        }
+        self.last_voxels = Some((resolution, origin, voxels));
+    }
+
+    /// Stores an asset once, however often it is added, and returns the id
+    /// primitives use to refer to it.
+    pub fn add_asset(&mut self, asset: Asset) -> String {
+        self.assets.insert(asset)
+    }
+
+    pub fn add_mesh(&mut self, mesh: Mesh) -> String {
+        self.add_asset(Asset::Mesh(mesh))
+    }
+
+    pub fn add_mesh_instances(&mut self, instances: Vec<MeshInstance>, time: f64) {
+        self.primatives
+            .push(time, ThreeDPrimative::Meshes(instances));
+    }
+
+    /// Places a point cloud added with `add_asset`, e.g. a prior map, at `pose`.
+    pub fn add_point_cloud_instance(&mut self, cloud: &str, pose: Pose, time: f64) {
+        let primative = ThreeDPrimative::PointCloudInstance {
+            cloud: cloud.to_string(),
+            pose,
+            color: None,
+        };
+        self.primatives.push(time, primative);
+    }
+
+    pub fn add_textured_quad(&mut self, texture: &str, pose: Pose, size: (f64, f64), time: f64) {
+        let primative = ThreeDPrimative::TexturedQuad {
+            texture: texture.to_string(),
+            pose,
+            size,
+        };
+        self.primatives.push(time, primative);
+    }
+
+    /// Logs the robot posed by `positions` (joint name to radians or meters), with
+    /// its root link at `base`.
+    #[cfg(feature = "urdf")]
+    pub fn add_robot(
+        &mut self,
+        robot: &crate::urdf::RobotModel,
+        base: &Pose,
+        positions: &std::collections::HashMap<String, f64>,
+        time: f64,
+    ) {
+        if robot
+            .assets()
+            .iter()
+            .any(|(id, _)| !self.assets.contains(id))
+        {
+            self.assets.extend(robot.assets().clone());
         }
+        self.add_mesh_instances(robot.instances(base, positions), time);
     }
 
-    pub fn add_points(&mut self, points: Vec<(f64, f64, f64)>, time: f64) {
-        self.primatives.push((time, ThreeDPrimative::Point(points)));
+    pub fn retention_stats(&self) -> RetentionStats {
+        self.primatives.stats()
     }
 
+    /// Adds a copy of the view to `recording`, e.g. to log it periodically.
     pub fn log(&self, recording: &mut Recording) {
+        recording.assets.extend(self.assets.clone());
         recording.add_viz(self.as_viz());
     }
 
+    /// Like `log`, but moves the primitives into the recording instead of copying
+    /// them.
+    pub fn log_owned(mut self, recording: &mut Recording) {
+        recording.assets.extend(std::mem::take(&mut self.assets));
+        recording.add_viz(self.into_viz());
+    }
+
+    pub fn assets(&self) -> &AssetTable {
+        &self.assets
+    }
+
     pub fn as_view_data(&self) -> ThreeDViewData {
         ThreeDViewData {
-            primatives: self.primatives.clone(),
+            primatives: self.primatives.to_vec(),
         }
     }
 
+    pub fn into_viz(self) -> Viz {
+        let mut viz = Viz::new(self.name);
+        self.primatives.stats().annotate(&mut viz);
+        let data = ThreeDViewData {
+            primatives: self.primatives.into_vec(),
+        };
+        viz.with_widget(Widget::ThreeDView(data))
+    }
+
     pub fn as_viz(&self) -> Viz {
         let three_d_view_data = self.as_view_data();
         let widget = Widget::ThreeDView(three_d_view_data);
-        Viz::new(self.name.clone()).with_widget(widget)
+        let mut viz = Viz::new(self.name.clone()).with_widget(widget);
+        self.primatives.stats().annotate(&mut viz);
+        viz
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fundamentals_core::widgets::occupancy::GridOrigin;
+
+    use super::*;
+
+    fn cloud(i: usize) -> Vec<(f64, f64, f64)> {
+        (0..4).map(|j| (j as f64, (i * j) as f64, 0.0)).collect()
+    }
+
+    fn grid(i: usize) -> OccupancyGrid {
+        let mut cells = vec![0; 9];
+        cells[i % 9] = 100;
+        OccupancyGrid::new(0.5, 3, 3, GridOrigin::default(), &cells).unwrap()
+    }
+
+    fn voxels(i: usize) -> BTreeSet<Voxel> {
+        (0..=i as i32).map(|v| (v, 0, 0)).collect()
+    }
+
+    fn is_update(primative: &ThreeDPrimative) -> bool {
+        matches!(
+            primative,
+            ThreeDPrimative::PointDelta(_)
+                | ThreeDPrimative::OccupancyGridUpdate(_)
+                | ThreeDPrimative::VoxelGridUpdate(_)
+        )
+    }
+
+    /// Whether the first primitive of every track is a full state.
+    fn starts_with_keyframes(primatives: &[(f64, ThreeDPrimative)]) -> bool {
+        let first = |kind: fn(&ThreeDPrimative) -> bool| {
+            primatives.iter().map(|(_, p)| p).find(|p| kind(p))
+        };
+        [
+            first(|p| {
+                matches!(
+                    p,
+                    ThreeDPrimative::Point(_)
+                        | ThreeDPrimative::TypedPoint(_)
+                        | ThreeDPrimative::PointDelta(_)
+                )
+            }),
+            first(|p| {
+                matches!(
+                    p,
+                    ThreeDPrimative::OccupancyGrid(_) | ThreeDPrimative::OccupancyGridUpdate(_)
+                )
+            }),
+            first(|p| {
+                matches!(
+                    p,
+                    ThreeDPrimative::VoxelGrid(_) | ThreeDPrimative::VoxelGridUpdate(_)
+                )
+            }),
+        ]
+        .into_iter()
+        .flatten()
+        .all(|p| !is_update(p))
+    }
+
+    #[test]
+    fn keep_last_turns_leading_deltas_into_keyframes() {
+        let mut view = ThreeDView::new("points")
+            .with_point_deltas(PointDeltaEncoder::new(5))
+            .with_retention(RetentionPolicy::KeepLast(3));
+        for i in 0..12 {
+            view.add_points(cloud(i), i as f64);
+        }
+        let data = view.as_view_data();
+        assert_eq!(data.primatives.len(), 3);
+        assert!(starts_with_keyframes(&data.primatives));
+        for (t, _) in data.primatives.iter() {
+            assert_eq!(data.points_at(*t), Some(cloud(*t as usize)));
+        }
+        assert_eq!(view.retention_stats().dropped, 9);
+    }
+
+    #[test]
+    fn keyframes_keep_their_precision() {
+        let precision = Precision::I16 {
+            scale: 0.5,
+            offset: 0.0,
+        };
+        let mut view = ThreeDView::new("points")
+            .with_point_deltas(PointDeltaEncoder::new(10))
+            .with_precision(precision)
+            .with_retention(RetentionPolicy::KeepLast(2));
+        for i in 0..4 {
+            view.add_points(cloud(i), i as f64);
+        }
+        let data = view.as_view_data();
+        assert!(
+            matches!(&data.primatives[0].1, ThreeDPrimative::TypedPoint(b) if b.precision() == precision)
+        );
+        assert_eq!(data.points_at(3.0), Some(cloud(3)));
+    }
+
+    #[test]
+    fn keep_duration_turns_leading_grid_updates_into_grids() {
+        let mut view = ThreeDView::new("maps").with_retention(RetentionPolicy::KeepDuration(2.0));
+        for i in 0..8 {
+            view.add_occupancy_grid(grid(i), i as f64);
+            view.add_voxels(0.1, (0.0, 0.0, 0.0), voxels(i), i as f64);
+        }
+        let data = view.as_view_data();
+        assert_eq!(data.primatives.first().map(|(t, _)| *t), Some(5.0));
+        assert!(starts_with_keyframes(&data.primatives));
+        for t in [5.0, 6.0, 7.0] {
+            assert_eq!(data.occupancy_at(t), Some(grid(t as usize)));
+            assert_eq!(
+                data.voxel_grid_at(t),
+                Some(VoxelGrid::new(0.1, (0.0, 0.0, 0.0), &voxels(t as usize)))
+            );
+        }
+    }
+
+    #[test]
+    fn spilled_chunks_can_be_read_on_their_own() {
+        let dir = std::env::temp_dir().join(format!("fundamentals-threed-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut view = ThreeDView::new("spilled")
+            .with_point_deltas(PointDeltaEncoder::new(100))
+            .with_retention(RetentionPolicy::SpillToDisk {
+                keep_last: 3,
+                dir: dir.clone(),
+            });
+        for i in 0..10 {
+            view.add_points(cloud(i), i as f64);
+        }
+        let mut chunks = 0;
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let file = std::fs::File::open(entry.unwrap().path()).unwrap();
+            let primatives: Vec<(f64, ThreeDPrimative)> = serde_json::from_reader(file).unwrap();
+            assert!(starts_with_keyframes(&primatives));
+            let data = ThreeDViewData { primatives };
+            for (t, _) in data.primatives.iter() {
+                assert_eq!(data.points_at(*t), Some(cloud(*t as usize)));
+            }
+            chunks += 1;
+        }
+        assert_eq!(chunks, 2);
+        assert!(starts_with_keyframes(&view.as_view_data().primatives));
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,163 @@
+use fundamentals_core::{
+    recording::Recording,
+    viz::Viz,
+    widgets::{
+        two_d_view::{TwoDPrimative, TwoDViewData},
+        Widget,
+    },
+};
+
+use crate::retention::{RetentionBuffer, RetentionPolicy, RetentionStats};
+
+pub struct TwoDView {
+    name: String,
+    primatives: RetentionBuffer<TwoDPrimative>,
+}
+
+impl TwoDView {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            primatives: RetentionBuffer::new(name, RetentionPolicy::Unbounded),
+        }
+    }
+
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.primatives.set_policy(policy);
+        self
+    }
+
+    /// Logs one pose of a trajectory; the viewer draws the trail up to the current time.
+    pub fn add_position(&mut self, x: f64, y: f64, time: f64) {
+        self.add_points(vec![(x, y)], time);
+    }
+
+    pub fn add_points(&mut self, positions: Vec<(f64, f64)>, time: f64) {
+        self.add_scatter(positions, Vec::new(), Vec::new(), time);
+    }
+
+    /// Scatter points with per-point colors and sizes. Either may be empty, or hold a
+    /// single value for every point.
+    pub fn add_scatter(
+        &mut self,
+        positions: Vec<(f64, f64)>,
+        colors: Vec<String>,
+        sizes: Vec<f64>,
+        time: f64,
+    ) {
+        self.primatives.push(
+            time,
+            TwoDPrimative::Points {
+                positions,
+                colors,
+                sizes,
+            },
+        );
+    }
+
+    pub fn add_line_strip(&mut self, positions: Vec<(f64, f64)>, color: Option<&str>, time: f64) {
+        self.primatives.push(
+            time,
+            TwoDPrimative::LineStrip {
+                positions,
+                color: color.map(str::to_string),
+            },
+        );
+    }
+
+    pub fn add_polygon(
+        &mut self,
+        vertices: Vec<(f64, f64)>,
+        color: Option<&str>,
+        filled: bool,
+        time: f64,
+    ) {
+        self.primatives.push(
+            time,
+            TwoDPrimative::Polygon {
+                vertices,
+                color: color.map(str::to_string),
+                filled,
+            },
+        );
+    }
+
+    /// Rectangle of `size` (width, height) centred on `center`, rotated by `rotation`
+    /// radians.
+    pub fn add_box(
+        &mut self,
+        center: (f64, f64),
+        size: (f64, f64),
+        rotation: f64,
+        color: Option<&str>,
+        time: f64,
+    ) {
+        self.primatives.push(
+            time,
+            TwoDPrimative::Box2D {
+                center,
+                size,
+                rotation,
+                color: color.map(str::to_string),
+            },
+        );
+    }
+
+    pub fn retention_stats(&self) -> RetentionStats {
+        self.primatives.stats()
+    }
+
+    pub fn log(&self, recording: &mut Recording) {
+        recording.add_viz(self.as_viz());
+    }
+
+    pub fn as_view_data(&self) -> TwoDViewData {
+        TwoDViewData {
+            primatives: self.primatives.to_vec(),
+        }
+    }
+
+    pub fn as_viz(&self) -> Viz {
+        let widget = Widget::TwoDView(self.as_view_data());
+        let mut viz = Viz::new(self.name.clone()).with_widget(widget);
+        self.primatives.stats().annotate(&mut viz);
+        viz
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_primitives_in_time_order_under_retention() {
+        let mut view = TwoDView::new("map").with_retention(RetentionPolicy::KeepLast(2));
+        view.add_position(1.0, 2.0, 0.0);
+        view.add_line_strip(vec![(0.0, 0.0), (1.0, 1.0)], Some("blue"), 1.0);
+        view.add_box((0.0, 0.0), (1.0, 1.0), 0.0, None, 2.0);
+
+        let mut recording = Recording::new("drive".to_string(), "run-1".to_string());
+        view.log(&mut recording);
+        let viz = recording.get_viz("map").unwrap();
+        let Widget::TwoDView(data) = &viz.widgets[0] else {
+            panic!("expected a 2D view");
+        };
+        assert_eq!(data.primatives.len(), 2);
+        assert_eq!(
+            data.primatives[0],
+            (
+                1.0,
+                TwoDPrimative::LineStrip {
+                    positions: vec![(0.0, 0.0), (1.0, 1.0)],
+                    color: Some("blue".to_string()),
+                }
+            )
+        );
+        assert!(matches!(data.primatives[1].1, TwoDPrimative::Box2D { .. }));
+        assert_eq!(RetentionStats::from_viz(viz).dropped, 1);
+        assert_eq!(
+            serde_json::to_value(viz).unwrap()["widgets"][0]["2d_view"]["primatives"][1][0],
+            2.0
+        );
+    }
+}
@@ -0,0 +1,604 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use fundamentals_core::{
+    assets::{Asset, AssetTable},
+    widgets::mesh::{Mesh, MeshInstance, Pose},
+};
+
+use crate::mesh::{css_color, load_mesh};
+
+/// Sides used to tessellate URDF cylinders and spheres.
+const SEGMENTS: u32 = 24;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Geometry {
+    Box {
+        size: (f64, f64, f64),
+    },
+    /// Along the link's z axis.
+    Cylinder {
+        radius: f64,
+        length: f64,
+    },
+    Sphere {
+        radius: f64,
+    },
+    /// `filename` as written in the URDF, e.g. `package://robot/meshes/base.stl`.
+    Mesh {
+        filename: String,
+        scale: (f64, f64, f64),
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visual {
+    /// Pose of the geometry in the link frame.
+    pub origin: Pose,
+    pub geometry: Geometry,
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub name: String,
+    pub visuals: Vec<Visual>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JointKind {
+    Revolute,
+    Continuous,
+    Prismatic,
+    Fixed,
+    /// Floating and planar joints; logged joint states do not move them.
+    Other,
+}
+
+/// A joint whose position follows another joint: `multiplier * q + offset`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mimic {
+    pub joint: String,
+    pub multiplier: f64,
+    pub offset: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Joint {
+    pub name: String,
+    pub kind: JointKind,
+    pub parent: String,
+    pub child: String,
+    /// Pose of the child frame in the parent frame at zero position.
+    pub origin: Pose,
+    pub axis: (f64, f64, f64),
+    pub mimic: Option<Mimic>,
+}
+
+impl Joint {
+    /// Motion of the child frame for the given joint position.
+    pub fn motion(&self, position: f64) -> Pose {
+        match self.kind {
+            JointKind::Revolute | JointKind::Continuous => {
+                Pose::from_axis_angle(self.axis, position)
+            }
+            JointKind::Prismatic => Pose::from_position((
+                self.axis.0 * position,
+                self.axis.1 * position,
+                self.axis.2 * position,
+            )),
+            JointKind::Fixed | JointKind::Other => Pose::identity(),
+        }
+    }
+}
+
+/// Kinematic tree and visual geometry of a URDF robot description.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Urdf {
+    pub name: String,
+    pub links: Vec<Link>,
+    pub joints: Vec<Joint>,
+}
+
+impl Urdf {
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let xml = std::fs::read_to_string(path)?;
+        Self::parse(&xml).map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    pub fn parse(xml: &str) -> Result<Self, anyhow::Error> {
+        let document = roxmltree::Document::parse(xml)?;
+        let robot = document.root_element();
+        if !robot.has_tag_name("robot") {
+            return Err(anyhow::anyhow!("Root element is not <robot>"));
+        }
+
+        // Materials can be defined at the top level and referenced by name
+        let mut materials = HashMap::new();
+        for material in robot.children().filter(|n| n.has_tag_name("material")) {
+            if let (Some(name), Some(color)) =
+                (material.attribute("name"), material_color(material)?)
+            {
+                materials.insert(name.to_string(), color);
+            }
+        }
+
+        let mut links = Vec::new();
+        for link in robot.children().filter(|n| n.has_tag_name("link")) {
+            let mut visuals = Vec::new();
+            for visual in link.children().filter(|n| n.has_tag_name("visual")) {
+                let Some(geometry) =
+                    child(visual, "geometry").and_then(|g| g.children().find(|n| n.is_element()))
+                else {
+                    return Err(anyhow::anyhow!("Visual without geometry"));
+                };
+                let color = match child(visual, "material") {
+                    Some(material) => match material_color(material)? {
+                        Some(color) => Some(color),
+                        None => material
+                            .attribute("name")
+                            .and_then(|name| materials.get(name).cloned()),
+                    },
+                    None => None,
+                };
+                visuals.push(Visual {
+                    origin: origin(visual)?,
+                    geometry: parse_geometry(geometry)?,
+                    color,
+                });
+            }
+            links.push(Link {
+                name: required(link, "name")?.to_string(),
+                visuals,
+            });
+        }
+
+        let mut joints = Vec::new();
+        for joint in robot.children().filter(|n| n.has_tag_name("joint")) {
+            let link_of = |tag: &str| -> Result<String, anyhow::Error> {
+                let node =
+                    child(joint, tag).ok_or_else(|| anyhow::anyhow!("Joint without <{}>", tag))?;
+                Ok(required(node, "link")?.to_string())
+            };
+            let kind = match required(joint, "type")? {
+                "revolute" => JointKind::Revolute,
+                "continuous" => JointKind::Continuous,
+                "prismatic" => JointKind::Prismatic,
+                "fixed" => JointKind::Fixed,
+                _ => JointKind::Other,
+            };
+            let axis = match child(joint, "axis").and_then(|a| a.attribute("xyz")) {
+                Some(xyz) => triple(xyz)?,
+                None => (1.0, 0.0, 0.0),
+            };
+            let mimic = match child(joint, "mimic") {
+                Some(mimic) => Some(Mimic {
+                    joint: required(mimic, "joint")?.to_string(),
+                    multiplier: number(mimic, "multiplier", 1.0)?,
+                    offset: number(mimic, "offset", 0.0)?,
+                }),
+                None => None,
+            };
+            joints.push(Joint {
+                name: required(joint, "name")?.to_string(),
+                kind,
+                parent: link_of("parent")?,
+                child: link_of("child")?,
+                origin: origin(joint)?,
+                axis,
+                mimic,
+            });
+        }
+
+        Ok(Self {
+            name: robot.attribute("name").unwrap_or_default().to_string(),
+            links,
+            joints,
+        })
+    }
+
+    /// The link that is not the child of any joint.
+    pub fn root_link(&self) -> Option<&str> {
+        let children: HashSet<&str> = self.joints.iter().map(|j| j.child.as_str()).collect();
+        self.links
+            .iter()
+            .map(|l| l.name.as_str())
+            .find(|name| !children.contains(name))
+    }
+
+    /// Names of the joints that take a position, in document order.
+    pub fn movable_joints(&self) -> impl Iterator<Item = &str> {
+        self.joints
+            .iter()
+            .filter(|j| {
+                j.mimic.is_none()
+                    && matches!(
+                        j.kind,
+                        JointKind::Revolute | JointKind::Continuous | JointKind::Prismatic
+                    )
+            })
+            .map(|j| j.name.as_str())
+    }
+
+    /// Pose of every link reachable from the root, with the root at `base`.
+    /// Joints missing from `positions` are at zero.
+    pub fn link_poses(
+        &self,
+        base: &Pose,
+        positions: &HashMap<String, f64>,
+    ) -> HashMap<String, Pose> {
+        let position = |joint: &Joint| match &joint.mimic {
+            Some(mimic) => {
+                mimic.multiplier * positions.get(&mimic.joint).copied().unwrap_or(0.0)
+                    + mimic.offset
+            }
+            None => positions.get(&joint.name).copied().unwrap_or(0.0),
+        };
+
+        let mut poses = HashMap::new();
+        let Some(root) = self.root_link() else {
+            return poses;
+        };
+        poses.insert(root.to_string(), *base);
+        let mut pending = vec![root];
+        while let Some(parent) = pending.pop() {
+            let parent_pose = poses[parent];
+            for joint in self.joints.iter().filter(|j| j.parent == parent) {
+                // Guards against malformed, cyclic descriptions
+                if poses.contains_key(&joint.child) {
+                    continue;
+                }
+                let pose = parent_pose
+                    .then(&joint.origin)
+                    .then(&joint.motion(position(joint)));
+                poses.insert(joint.child.clone(), pose);
+                pending.push(&joint.child);
+            }
+        }
+        poses
+    }
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    tag: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(tag))
+}
+
+fn required<'a>(node: roxmltree::Node<'a, '_>, attribute: &str) -> Result<&'a str, anyhow::Error> {
+    node.attribute(attribute).ok_or_else(|| {
+        anyhow::anyhow!(
+            "<{}> without '{}' attribute",
+            node.tag_name().name(),
+            attribute
+        )
+    })
+}
+
+fn number(node: roxmltree::Node, attribute: &str, default: f64) -> Result<f64, anyhow::Error> {
+    match node.attribute(attribute) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid {} '{}'", attribute, value)),
+        None => Ok(default),
+    }
+}
+
+fn numbers(text: &str) -> Result<Vec<f64>, anyhow::Error> {
+    text.split_whitespace()
+        .map(|v| {
+            v.parse()
+                .map_err(|_| anyhow::anyhow!("Invalid number '{}'", v))
+        })
+        .collect()
+}
+
+fn triple(text: &str) -> Result<(f64, f64, f64), anyhow::Error> {
+    match numbers(text)?[..] {
+        [x, y, z] => Ok((x, y, z)),
+        _ => Err(anyhow::anyhow!("Expected 3 numbers, got '{}'", text)),
+    }
+}
+
+fn origin(node: roxmltree::Node) -> Result<Pose, anyhow::Error> {
+    let Some(origin) = child(node, "origin") else {
+        return Ok(Pose::identity());
+    };
+    let xyz = origin.attribute("xyz").map(triple).transpose()?;
+    let rpy = origin.attribute("rpy").map(triple).transpose()?;
+    Ok(Pose::from_xyz_rpy(
+        xyz.unwrap_or_default(),
+        rpy.unwrap_or_default(),
+    ))
+}
+
+fn material_color(material: roxmltree::Node) -> Result<Option<String>, anyhow::Error> {
+    let Some(rgba) = child(material, "color").and_then(|c| c.attribute("rgba")) else {
+        return Ok(None);
+    };
+    match numbers(rgba)?[..] {
+        [r, g, b, a] => Ok(Some(css_color([r, g, b, a]))),
+        _ => Err(anyhow::anyhow!("Invalid rgba '{}'", rgba)),
+    }
+}
+
+fn parse_geometry(node: roxmltree::Node) -> Result<Geometry, anyhow::Error> {
+    match node.tag_name().name() {
+        "box" => Ok(Geometry::Box {
+            size: triple(required(node, "size")?)?,
+        }),
+        "cylinder" => Ok(Geometry::Cylinder {
+            radius: number(node, "radius", 0.0)?,
+            length: number(node, "length", 0.0)?,
+        }),
+        "sphere" => Ok(Geometry::Sphere {
+            radius: number(node, "radius", 0.0)?,
+        }),
+        "mesh" => Ok(Geometry::Mesh {
+            filename: required(node, "filename")?.to_string(),
+            scale: match node.attribute("scale") {
+                Some(scale) => triple(scale)?,
+                None => (1.0, 1.0, 1.0),
+            },
+        }),
+        other => Err(anyhow::anyhow!("Unsupported geometry <{}>", other)),
+    }
+}
+
+/// Finds the file a URDF mesh `filename` refers to. `package://name/...` is
+/// looked up in the ancestors of `base_dir` and in `ROS_PACKAGE_PATH`; relative
+/// paths are relative to `base_dir`.
+pub fn resolve_mesh_path(filename: &str, base_dir: &Path) -> Result<PathBuf, anyhow::Error> {
+    if let Some(path) = filename.strip_prefix("file://") {
+        return Ok(PathBuf::from(path));
+    }
+    let Some(reference) = filename.strip_prefix("package://") else {
+        return Ok(base_dir.join(filename));
+    };
+    let (package, rest) = reference
+        .split_once('/')
+        .ok_or_else(|| anyhow::anyhow!("Invalid package URI '{}'", filename))?;
+
+    let search_paths = std::env::var_os("ROS_PACKAGE_PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+    base_dir
+        .ancestors()
+        .flat_map(|dir| {
+            let named = (dir.file_name() == Some(package.as_ref())).then(|| dir.join(rest));
+            named.into_iter().chain([dir.join(package).join(rest)])
+        })
+        .chain(search_paths.iter().map(|dir| dir.join(package).join(rest)))
+        .find(|path| path.is_file())
+        .ok_or_else(|| anyhow::anyhow!("Could not find '{}'", filename))
+}
+
+/// A visual of a link, referring to one of the model's mesh assets by id.
+#[derive(Debug, Clone)]
+struct LinkVisual {
+    link: String,
+    mesh: String,
+    origin: Pose,
+    scale: (f64, f64, f64),
+    color: Option<String>,
+}
+
+/// A URDF robot with its meshes loaded, ready to be posed from joint states.
+#[derive(Debug, Clone)]
+pub struct RobotModel {
+    pub urdf: Urdf,
+    assets: AssetTable,
+    visuals: Vec<LinkVisual>,
+}
+
+impl RobotModel {
+    /// Loads a URDF file and every mesh it references.
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        Self::from_urdf(Urdf::load(path)?, base_dir)
+    }
+
+    /// Loads the meshes of `urdf`, resolving relative file names from `base_dir`.
+    pub fn from_urdf(urdf: Urdf, base_dir: &Path) -> Result<Self, anyhow::Error> {
+        let mut assets = AssetTable::new();
+        // Asset id per geometry, so each mesh file is read once
+        let mut loaded: HashMap<String, String> = HashMap::new();
+        let mut visuals = Vec::new();
+        for link in urdf.links.iter() {
+            for visual in link.visuals.iter() {
+                let (key, scale) = match &visual.geometry {
+                    Geometry::Box { size } => (format!("box {:?}", size), (1.0, 1.0, 1.0)),
+                    Geometry::Cylinder { radius, length } => {
+                        (format!("cylinder {} {}", radius, length), (1.0, 1.0, 1.0))
+                    }
+                    Geometry::Sphere { radius } => (format!("sphere {}", radius), (1.0, 1.0, 1.0)),
+                    Geometry::Mesh { filename, scale } => (filename.clone(), *scale),
+                };
+                let id = match loaded.get(&key) {
+                    Some(id) => id.clone(),
+                    None => {
+                        let mesh = match &visual.geometry {
+                            Geometry::Box { size } => Mesh::cuboid(*size),
+                            Geometry::Cylinder { radius, length } => {
+                                Mesh::cylinder(*radius, *length, SEGMENTS)
+                            }
+                            Geometry::Sphere { radius } => Mesh::sphere(*radius, SEGMENTS),
+                            Geometry::Mesh { filename, .. } => {
+                                load_mesh(&resolve_mesh_path(filename, base_dir)?)?
+                            }
+                        };
+                        let id = assets.insert(Asset::Mesh(mesh));
+                        loaded.insert(key, id.clone());
+                        id
+                    }
+                };
+                visuals.push(LinkVisual {
+                    link: link.name.clone(),
+                    mesh: id,
+                    origin: visual.origin,
+                    scale,
+                    color: visual.color.clone(),
+                });
+            }
+        }
+        Ok(Self {
+            urdf,
+            assets,
+            visuals,
+        })
+    }
+
+    /// Every mesh the robot uses, keyed by the asset ids its instances refer to.
+    pub fn assets(&self) -> &AssetTable {
+        &self.assets
+    }
+
+    /// The robot's visuals posed for the given joint positions, with the root link
+    /// at `base`.
+    pub fn instances(&self, base: &Pose, positions: &HashMap<String, f64>) -> Vec<MeshInstance> {
+        let poses = self.urdf.link_poses(base, positions);
+        self.visuals
+            .iter()
+            .filter_map(|visual| {
+                let link_pose = poses.get(&visual.link)?;
+                Some(
+                    MeshInstance::new(&visual.mesh, link_pose.then(&visual.origin))
+                        .with_scale(visual.scale)
+                        .with_color(visual.color.clone()),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+
+    const ARM: &str = r#"
+<robot name="arm">
+  <material name="blue"><color rgba="0 0 1 1"/></material>
+  <link name="base">
+    <visual><geometry><box size="1 1 0.2"/></geometry><material name="blue"/></visual>
+  </link>
+  <link name="upper">
+    <visual>
+      <origin xyz="0.5 0 0"/>
+      <geometry><cylinder radius="0.05" length="1"/></geometry>
+    </visual>
+  </link>
+  <link name="lower">
+    <visual><geometry><box size="1 1 0.2"/></geometry></visual>
+  </link>
+  <link name="finger"/>
+  <joint name="shoulder" type="revolute">
+    <parent link="base"/><child link="upper"/>
+    <origin xyz="0 0 0.1"/>
+    <axis xyz="0 0 1"/>
+  </joint>
+  <joint name="elbow" type="continuous">
+    <parent link="upper"/><child link="lower"/>
+    <origin xyz="1 0 0"/>
+    <axis xyz="0 0 1"/>
+  </joint>
+  <joint name="slide" type="prismatic">
+    <parent link="lower"/><child link="finger"/>
+    <axis xyz="1 0 0"/>
+    <mimic joint="shoulder" multiplier="2" offset="0.1"/>
+  </joint>
+</robot>"#;
+
+    fn assert_near(a: (f64, f64, f64), b: (f64, f64, f64)) {
+        let error = (a.0 - b.0).abs() + (a.1 - b.1).abs() + (a.2 - b.2).abs();
+        assert!(error < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn parses_links_joints_and_materials() {
+        let urdf = Urdf::parse(ARM).unwrap();
+        assert_eq!(urdf.name, "arm");
+        assert_eq!(urdf.root_link(), Some("base"));
+        assert_eq!(
+            urdf.movable_joints().collect::<Vec<_>>(),
+            vec!["shoulder", "elbow"]
+        );
+        assert_eq!(
+            urdf.links[0].visuals[0].color.as_deref(),
+            Some("rgba(0, 0, 255, 1)")
+        );
+        assert_eq!(
+            urdf.links[1].visuals[0].geometry,
+            Geometry::Cylinder {
+                radius: 0.05,
+                length: 1.0
+            }
+        );
+        assert!(Urdf::parse("<robot><link/></robot>").is_err());
+        assert!(Urdf::parse("<model/>").is_err());
+    }
+
+    #[test]
+    fn link_poses_follow_joint_positions() {
+        let urdf = Urdf::parse(ARM).unwrap();
+        let base = Pose::from_position((0.0, 0.0, 1.0));
+        let positions = HashMap::from([
+            ("shoulder".to_string(), FRAC_PI_2),
+            ("elbow".to_string(), -FRAC_PI_2),
+        ]);
+        let poses = urdf.link_poses(&base, &positions);
+        assert_near(poses["upper"].position, (0.0, 0.0, 1.1));
+        // The elbow sits 1 m along the upper arm, which points along y
+        assert_near(poses["lower"].position, (0.0, 1.0, 1.1));
+        assert_near(poses["lower"].rotate((1.0, 0.0, 0.0)), (1.0, 0.0, 0.0));
+        // The mimic joint slides by 2 * shoulder + 0.1 along the lower link's x
+        assert_near(poses["finger"].position, (2.0 * FRAC_PI_2 + 0.1, 1.0, 1.1));
+
+        let zero = urdf.link_poses(&Pose::identity(), &HashMap::new());
+        assert_near(zero["finger"].position, (1.1, 0.0, 0.1));
+    }
+
+    #[test]
+    fn model_shares_meshes_between_equal_geometries() {
+        let model = RobotModel::from_urdf(Urdf::parse(ARM).unwrap(), Path::new(".")).unwrap();
+        // The base and lower link boxes are the same asset
+        assert_eq!(model.assets().len(), 2);
+        let instances = model.instances(&Pose::identity(), &HashMap::new());
+        assert_eq!(instances.len(), 3);
+        assert_eq!(instances[0].mesh, instances[2].mesh);
+        assert_near(instances[1].pose.position, (0.5, 0.0, 0.1));
+        assert!(instances
+            .iter()
+            .all(|i| model.assets().get(&i.mesh).is_some()));
+    }
+
+    #[test]
+    fn mesh_paths_resolve_from_packages() {
+        let root = std::env::temp_dir().join(format!("fundamentals-urdf-{}", std::process::id()));
+        let package = root.join("robot");
+        std::fs::create_dir_all(package.join("meshes")).unwrap();
+        std::fs::create_dir_all(package.join("urdf")).unwrap();
+        std::fs::write(package.join("meshes/base.stl"), b"").unwrap();
+
+        let base_dir = package.join("urdf");
+        assert_eq!(
+            resolve_mesh_path("package://robot/meshes/base.stl", &base_dir).unwrap(),
+            package.join("meshes/base.stl")
+        );
+        assert_eq!(
+            resolve_mesh_path("../meshes/base.stl", &base_dir).unwrap(),
+            base_dir.join("../meshes/base.stl")
+        );
+        assert_eq!(
+            resolve_mesh_path("file:///tmp/x.stl", &base_dir).unwrap(),
+            PathBuf::from("/tmp/x.stl")
+        );
+        assert!(resolve_mesh_path("package://other/base.stl", &base_dir).is_err());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
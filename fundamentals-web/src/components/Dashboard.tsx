@@ -1,7 +1,10 @@
-import { useWebSocket } from '../context/WebSocketContext';
+import { useWebSocket, vizSession } from '../context/WebSocketContext';
 import { useNavigate } from 'react-router-dom';
 import { PlotViz } from './PlotViz';
 import { ThreeDViz } from './ThreeDViz';
+import { HistogramViz } from './HistogramViz';
+import { TwoDViz } from './TwoDViz';
+import { TensorViz } from './TensorViz';
 import {
   Title,
   Text,
@@ -83,6 +86,8 @@ export default function Dashboard() {
                       <PlotViz 
                         data={widget.plot_scalar} 
                         name={viz.name} 
+                        sessionId={vizSession(viz)}
+                        metadata={viz.metadata}
                         onFullscreen={() => viewFullScreen(index, 'plot_scalar')}
                       />
                     ) : widget['3d_view'] ? (
@@ -91,6 +96,26 @@ export default function Dashboard() {
                         name={viz.name} 
                         onFullscreen={() => viewFullScreen(index, '3d_view')}
                       />
+                    ) : widget.histogram ? (
+                      <HistogramViz 
+                        data={widget.histogram} 
+                        name={viz.name} 
+                        onFullscreen={() => viewFullScreen(index, 'histogram')}
+                      />
+                    ) : widget['2d_view'] ? (
+                      <TwoDViz 
+                        data={widget['2d_view']} 
+                        name={viz.name} 
+                        onFullscreen={() => viewFullScreen(index, '2d_view')}
+                      />
+                    ) : widget.tensor ? (
+                      <TensorViz 
+                        data={widget.tensor} 
+                        name={viz.name} 
+                        sessionId={vizSession(viz)}
+                        metadata={viz.metadata}
+                        onFullscreen={() => viewFullScreen(index, 'tensor')}
+                      />
                     ) : (
                       <Card.Section p="md">
                         <Stack>
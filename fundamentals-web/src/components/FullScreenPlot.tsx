@@ -1,8 +1,11 @@
 import { useEffect, useState, useRef } from 'react';
 import { useParams, useNavigate } from 'react-router-dom';
-import { useWebSocket, Viz } from '../context/WebSocketContext';
+import { useWebSocket, Viz, vizSession } from '../context/WebSocketContext';
 import { PlotViz } from './PlotViz';
 import { ThreeDViz } from './ThreeDViz';
+import { HistogramViz } from './HistogramViz';
+import { TwoDViz } from './TwoDViz';
+import { TensorViz } from './TensorViz';
 import {
   Box,
   ActionIcon,
@@ -194,6 +197,8 @@ function renderFullScreenPlot(viz: Viz) {
         <PlotViz 
           data={widget.plot_scalar} 
           name={viz.name} 
+          sessionId={vizSession(viz)}
+          metadata={viz.metadata}
           fullScreen={true}
         />
       </Box>
@@ -212,6 +217,44 @@ function renderFullScreenPlot(viz: Viz) {
     );
   }
   
+  if (widget.histogram) {
+    return (
+      <Box style={{ height: '100%' }}>
+        <HistogramViz 
+          data={widget.histogram} 
+          name={viz.name} 
+          fullScreen={true}
+        />
+      </Box>
+    );
+  }
+  
+  if (widget['2d_view']) {
+    return (
+      <Box style={{ height: '100%' }}>
+        <TwoDViz 
+          data={widget['2d_view']} 
+          name={viz.name} 
+          fullScreen={true}
+        />
+      </Box>
+    );
+  }
+  
+  if (widget.tensor) {
+    return (
+      <Box style={{ height: '100%' }}>
+        <TensorViz 
+          data={widget.tensor} 
+          name={viz.name} 
+          sessionId={vizSession(viz)}
+          metadata={viz.metadata}
+          fullScreen={true}
+        />
+      </Box>
+    );
+  }
+  
   // Fallback
   return (
     <Center style={{ height: '100%' }}>
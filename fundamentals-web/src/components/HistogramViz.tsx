@@ -0,0 +1,147 @@
+import { useState, useMemo } from 'react';
+import { HistogramData, HistogramBins } from '../context/WebSocketContext';
+import {
+  Card,
+  Text,
+  Title,
+  Stack,
+  Group,
+  Badge,
+  Button,
+  Box,
+  Slider,
+  Tooltip,
+  useMantineTheme,
+  useComputedColorScheme
+} from '@mantine/core';
+import Plot from 'react-plotly.js';
+import * as Plotly from 'plotly.js';
+import { IconMaximize } from '@tabler/icons-react';
+
+interface HistogramVizProps {
+  data: HistogramData;
+  name: string;
+  fullScreen?: boolean;
+  onFullscreen?: () => void;
+}
+
+const EMPTY: HistogramBins = { edges: [], counts: [] };
+
+// Bins of a frame; frames of raw samples are binned by the bridge before they are
+// sent, so any left are drawn empty
+function frameBins(data: HistogramData, index: number): HistogramBins {
+  const frame = data.frames[index]?.[1];
+  return frame && 'binned' in frame ? frame.binned : EMPTY;
+}
+
+export function HistogramViz({ data, name, fullScreen = false, onFullscreen }: HistogramVizProps) {
+  const theme = useMantineTheme();
+  const computedColorScheme = useComputedColorScheme('dark');
+  const isDark = computedColorScheme === 'dark';
+
+  // Time-varying histograms show one frame at a time, the latest by default
+  const lastFrame = Math.max(data.frames.length - 1, 0);
+  const [selected, setSelected] = useState<number | null>(null);
+  const frameIndex = Math.min(selected ?? lastFrame, lastFrame);
+  const frameTime = data.frames[frameIndex]?.[0];
+
+  const bins = useMemo(() => frameBins(data, frameIndex), [data, frameIndex]);
+  const total = bins.counts.reduce((sum, count) => sum + count, 0);
+
+  const plotData: Plotly.Data[] = [
+    {
+      x: bins.counts.map((_, i) => (bins.edges[i] + bins.edges[i + 1]) / 2),
+      y: bins.counts,
+      width: bins.counts.map((_, i) => bins.edges[i + 1] - bins.edges[i]),
+      type: 'bar',
+      marker: {
+        color: theme.colors[theme.primaryColor][isDark ? 4 : 6],
+        line: { width: 1, color: isDark ? theme.colors.dark[7] : theme.white }
+      },
+      name: name
+    }
+  ];
+
+  const gridcolor = isDark ? 'rgba(255,255,255,0.1)' : 'rgba(0,0,0,0.1)';
+  const plotLayout: Partial<Plotly.Layout> = {
+    autosize: true,
+    title: fullScreen ? name : '',
+    paper_bgcolor: 'transparent',
+    plot_bgcolor: 'transparent',
+    font: {
+      color: isDark ? theme.colors.gray[4] : theme.colors.gray[7]
+    },
+    bargap: 0,
+    margin: {
+      l: 50,
+      r: 20,
+      t: fullScreen ? 30 : 10,
+      b: 50
+    },
+    xaxis: { title: 'Value', gridcolor },
+    yaxis: { title: 'Count', gridcolor }
+  };
+
+  const plotConfig: Partial<Plotly.Config> = {
+    responsive: true,
+    displayModeBar: fullScreen
+  };
+
+  const frameSlider = data.frames.length > 1 && (
+    <Stack gap={4} px={fullScreen ? 'xl' : 0} pb={fullScreen ? 'md' : 0}>
+      <Text size="sm">Time: {frameTime?.toFixed(2)}</Text>
+      <Slider
+        value={frameIndex}
+        onChange={setSelected}
+        min={0}
+        max={lastFrame}
+        step={1}
+        label={(i) => data.frames[i]?.[0].toFixed(2)}
+      />
+    </Stack>
+  );
+
+  const plotContent = (
+    <div style={{ width: '100%', height: fullScreen ? '100%' : 300 }}>
+      <Plot
+        data={plotData}
+        layout={plotLayout}
+        config={plotConfig}
+        style={{ width: '100%', height: '100%' }}
+      />
+    </div>
+  );
+
+  if (fullScreen) {
+    return (
+      <Box style={{ height: '100%', width: '100%', display: 'flex', flexDirection: 'column' }}>
+        <Box style={{ flex: 1 }}>{plotContent}</Box>
+        {frameSlider}
+      </Box>
+    );
+  }
+
+  return (
+    <Card shadow="sm" p="lg" withBorder>
+      <Card.Section withBorder inheritPadding py="xs">
+        <Group justify="space-between">
+          <Title order={4}>{name}</Title>
+          <Group gap="xs">
+            <Badge>{total} samples</Badge>
+            {onFullscreen && (
+              <Tooltip label="View Fullscreen">
+                <Button onClick={onFullscreen} variant="subtle">
+                  <IconMaximize size={16} />
+                </Button>
+              </Tooltip>
+            )}
+          </Group>
+        </Group>
+      </Card.Section>
+
+      {plotContent}
+
+      {frameSlider}
+    </Card>
+  );
+}
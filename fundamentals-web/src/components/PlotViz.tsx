@@ -1,5 +1,5 @@
 import { useState, useMemo, useEffect, useRef } from 'react';
-import { PlotScalarData } from '../context/WebSocketContext';
+import { PlotScalarData, annotationSpan, plotRangeKey, useWebSocket } from '../context/WebSocketContext';
 import { 
   Card, 
   Text, 
@@ -31,6 +31,10 @@ import {
 interface PlotVizProps {
   data: PlotScalarData;
   name: string;
+  // Recording the plot belongs to, see vizSession
+  sessionId?: string;
+  // Viz metadata, e.g. how the logger decimated the series
+  metadata?: Record<string, unknown>;
   fullScreen?: boolean;
   onFullscreen?: () => void;
 }
@@ -79,10 +83,27 @@ function StatsSummary({ data }: { data: { x: number; y: number }[] }) {
   );
 }
 
-export function PlotViz({ data, name, fullScreen = false, onFullscreen }: PlotVizProps) {
+// Log-time decimation recorded by the SDK (see fundamentals-sdk/src/decimation.rs)
+function decimationLabel(metadata?: Record<string, unknown>): { mode: string; details: string } | null {
+  const mode = metadata?.['decimation.mode'];
+  if (typeof mode !== 'string') return null;
+  const samples = metadata?.['decimation.original_samples'];
+  const rate = metadata?.['decimation.original_rate_hz'];
+  const details = [
+    typeof samples === 'number' ? `${samples} samples logged` : null,
+    typeof rate === 'number' ? `at ${rate.toPrecision(4)} Hz` : null
+  ].filter(Boolean).join(' ');
+  return { mode, details };
+}
+
+export function PlotViz({ data, name, sessionId, metadata, fullScreen = false, onFullscreen }: PlotVizProps) {
   const theme = useMantineTheme();
   const computedColorScheme = useComputedColorScheme('dark');
   const isDark = computedColorScheme === 'dark';
+  const { plotRanges, annotations, sendRequest, clearPlotRange } = useWebSocket();
+  const rangeKey = plotRangeKey(sessionId, name);
+  const decimation = decimationLabel(metadata);
+  const zoomedRange = plotRanges[rangeKey];
   
   // Plot configuration state
   const [plotType, setPlotType] = useState<string>('scatter');
@@ -127,14 +148,65 @@ export function PlotViz({ data, name, fullScreen = false, onFullscreen }: PlotVi
     }
   };
   
-  // Format data for Plotly
+  // Format data for Plotly, preferring the higher resolution range the bridge
+  // sent back after a zoom
   const chartData = useMemo(() => {
-    return data.data_x.map(point => ({
+    const source = zoomedRange ? zoomedRange.data : data;
+    return source.data_x.map(point => ({
       x: point[0],
       y: point[1]
     }));
-  }, [data]);
+  }, [data, zoomedRange]);
+
+  // Ask the bridge for the visible x range at roughly one point per pixel
+  const handleRelayout = (event: Readonly<Plotly.PlotRelayoutEvent>) => {
+    if (event['xaxis.autorange']) {
+      clearPlotRange(rangeKey);
+      return;
+    }
+    const t0 = event['xaxis.range[0]'];
+    const t1 = event['xaxis.range[1]'];
+    if (typeof t0 === 'number' && typeof t1 === 'number') {
+      sendRequest({
+        PlotRange: {
+          session_id: sessionId,
+          viz: name,
+          t0,
+          t1,
+          points: Math.round(window.innerWidth),
+          algorithm: 'min_max'
+        }
+      });
+    }
+  };
   
+  // Annotations scoped to this plot: instants as dashed lines, intervals as bands
+  const markers = useMemo(() => {
+    const scoped = Object.values(annotations)
+      .flat()
+      .filter((a) => !a.vizs || a.vizs.length === 0 || a.vizs.includes(name));
+    const fallback = theme.colors.orange[isDark ? 4 : 6];
+    const shapes: Partial<Plotly.Shape>[] = scoped.map((a) => {
+      const [t0, t1] = annotationSpan(a);
+      const color = a.color ?? fallback;
+      return t0 === t1
+        ? { type: 'line', xref: 'x', yref: 'paper', x0: t0, x1: t0, y0: 0, y1: 1, line: { color, width: 1, dash: 'dash' } }
+        : { type: 'rect', xref: 'x', yref: 'paper', x0: t0, x1: t1, y0: 0, y1: 1, fillcolor: color, opacity: 0.15, line: { width: 0 } };
+    });
+    const labels: Partial<Plotly.Annotations>[] = scoped.map((a) => ({
+      x: annotationSpan(a)[0],
+      xref: 'x',
+      y: 1,
+      yref: 'paper',
+      text: a.label,
+      showarrow: false,
+      xanchor: 'left',
+      yanchor: 'top',
+      font: { size: 10, color: a.color ?? fallback }
+    }));
+    return { shapes, labels };
+  }, [annotations, name, theme, isDark]);
+
   // Configure plot data
   const plotData: Plotly.Data[] = [
     {
@@ -160,12 +232,14 @@ export function PlotViz({ data, name, fullScreen = false, onFullscreen }: PlotVi
   // Configure plot layout
   const plotLayout: Partial<Plotly.Layout> = {
     autosize: true,
-    title: fullScreen ? name : '',
+    title: fullScreen ? (decimation ? `${name} (${decimation.mode})` : name) : '',
     paper_bgcolor: 'transparent',
     plot_bgcolor: 'transparent',
     font: {
       color: isDark ? theme.colors.gray[4] : theme.colors.gray[7]
     },
+    shapes: markers.shapes,
+    annotations: markers.labels,
     margin: {
       l: 50,
       r: 20,
@@ -284,6 +358,7 @@ export function PlotViz({ data, name, fullScreen = false, onFullscreen }: PlotVi
         data={plotData}
         layout={plotLayout}
         config={plotConfig}
+        onRelayout={handleRelayout}
         style={{ width: '100%', height: '100%' }}
       />
     </div>
@@ -379,6 +454,11 @@ export function PlotViz({ data, name, fullScreen = false, onFullscreen }: PlotVi
           <Title order={4}>{name}</Title>
           <Group gap="xs">
             <Badge>{chartData.length} points</Badge>
+            {decimation && (
+              <Tooltip label={decimation.details || 'Decimated when logged'} disabled={!decimation.details}>
+                <Badge color="gray" variant="light">{decimation.mode}</Badge>
+              </Tooltip>
+            )}
             {onFullscreen && (
               <Tooltip label="View Fullscreen">
                 <Button
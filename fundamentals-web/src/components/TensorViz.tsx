@@ -0,0 +1,243 @@
+import { useState, useMemo, useEffect } from 'react';
+import {
+  TensorData,
+  Tensor,
+  DimSlice,
+  plotRangeKey,
+  tensorValues,
+  useWebSocket
+} from '../context/WebSocketContext';
+import {
+  Card,
+  Text,
+  Title,
+  Stack,
+  Group,
+  Badge,
+  Button,
+  Box,
+  Slider,
+  Tooltip,
+  useMantineTheme,
+  useComputedColorScheme
+} from '@mantine/core';
+import Plot from 'react-plotly.js';
+import * as Plotly from 'plotly.js';
+import { IconMaximize } from '@tabler/icons-react';
+
+interface TensorVizProps {
+  data: TensorData;
+  name: string;
+  sessionId?: string;
+  metadata?: Record<string, unknown>;
+  fullScreen?: boolean;
+  onFullscreen?: () => void;
+}
+
+// Rows of the heatmap: the last two dimensions, a single row for vectors
+function heatmapRows(tensor: Tensor): number[][] {
+  const values = tensorValues(tensor);
+  const columns = tensor.shape[tensor.shape.length - 1] ?? 1;
+  const rows: number[][] = [];
+  for (let start = 0; start < values.length; start += columns) {
+    rows.push(values.slice(start, start + columns));
+  }
+  return rows;
+}
+
+// Matrix at `leading` indices of a (possibly strided down) local frame. Indices refer
+// to the full shape and are scaled to the local one.
+function localMatrix(tensor: Tensor, fullShape: number[], leading: number[]): Tensor {
+  const shape = tensor.shape;
+  const matrixShape = shape.slice(leading.length);
+  const matrixSize = matrixShape.reduce((size, dim) => size * dim, 1);
+  let offset = 0;
+  leading.forEach((index, dim) => {
+    const local = Math.min(Math.floor((index * shape[dim]) / (fullShape[dim] || 1)), shape[dim] - 1);
+    offset = offset * shape[dim] + Math.max(local, 0);
+  });
+  const values = tensorValues(tensor).slice(offset * matrixSize, (offset + 1) * matrixSize);
+  return { shape: matrixShape, buffer: { f64: values } };
+}
+
+export function TensorViz({ data, name, sessionId, metadata, fullScreen = false, onFullscreen }: TensorVizProps) {
+  const theme = useMantineTheme();
+  const computedColorScheme = useComputedColorScheme('dark');
+  const isDark = computedColorScheme === 'dark';
+  const { tensorSlices, sendRequest } = useWebSocket();
+
+  // Time-varying tensors show one frame at a time, the latest by default
+  const lastFrame = Math.max(data.frames.length - 1, 0);
+  const [selected, setSelected] = useState<number | null>(null);
+  const frameIndex = Math.min(selected ?? lastFrame, lastFrame);
+  const frame = data.frames[frameIndex];
+  const frameTime = frame?.[0];
+
+  // Frames over the bridge's point limit arrive strided down, with the full shape in
+  // the metadata
+  const downsampledFrom = metadata?.downsampled_from;
+  const localShape = frame?.[1].shape ?? [];
+  const fullShape =
+    Array.isArray(downsampledFrom) && downsampledFrom.length === localShape.length
+      ? (downsampledFrom as number[])
+      : localShape;
+  const downsampled = fullShape !== localShape;
+
+  // One index per dimension before the last two
+  const leadingDims = Math.max(fullShape.length - 2, 0);
+  const [indices, setIndices] = useState<number[]>([]);
+  const leading = fullShape.slice(0, leadingDims).map((size, dim) => Math.min(indices[dim] ?? 0, size - 1));
+
+  const slices: DimSlice[] = useMemo(
+    () => [...leading.map((index) => ({ index })), ...fullShape.slice(leadingDims).map(() => 'all' as const)],
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+    [JSON.stringify(leading), fullShape.length]
+  );
+  const needsSlice = frame !== undefined && (downsampled || leadingDims > 0);
+
+  // Fetch the selected matrix at full resolution. sendRequest changes on every render
+  // of the provider, so it is left out of the dependencies.
+  useEffect(() => {
+    if (!needsSlice) return;
+    sendRequest({ TensorSlice: { session_id: sessionId, viz: name, t: frameTime, slices } });
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [needsSlice, sessionId, name, frameTime, slices]);
+
+  const response = tensorSlices[plotRangeKey(sessionId, name)];
+  const fetched =
+    needsSlice &&
+    response !== undefined &&
+    response.time === frameTime &&
+    JSON.stringify(response.slices) === JSON.stringify(slices);
+
+  // Until the slice arrives, the matching part of the local frame is drawn
+  const matrix = useMemo(() => {
+    if (!frame) return undefined;
+    if (fetched && response) return response.tensor;
+    return leadingDims > 0 ? localMatrix(frame[1], fullShape, leading) : frame[1];
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [frame, fetched, response, slices]);
+  const rows = useMemo(() => (matrix ? heatmapRows(matrix) : []), [matrix]);
+
+  const plotData: Plotly.Data[] = [
+    {
+      z: rows,
+      type: 'heatmap',
+      colorscale: 'Viridis',
+      name: name
+    }
+  ];
+
+  const plotLayout: Partial<Plotly.Layout> = {
+    autosize: true,
+    title: fullScreen ? name : '',
+    paper_bgcolor: 'transparent',
+    plot_bgcolor: 'transparent',
+    font: {
+      color: isDark ? theme.colors.gray[4] : theme.colors.gray[7]
+    },
+    margin: {
+      l: 50,
+      r: 20,
+      t: fullScreen ? 30 : 10,
+      b: 50
+    },
+    xaxis: { title: 'Column' },
+    // Row 0 at the top, like an image
+    yaxis: { title: 'Row', autorange: 'reversed' }
+  };
+
+  const plotConfig: Partial<Plotly.Config> = {
+    responsive: true,
+    displayModeBar: fullScreen
+  };
+
+  const controls = (data.frames.length > 1 || leadingDims > 0) && (
+    <Stack gap={4} px={fullScreen ? 'xl' : 0} pb={fullScreen ? 'md' : 0}>
+      {data.frames.length > 1 && (
+        <>
+          <Text size="sm">Time: {frameTime?.toFixed(2)}</Text>
+          <Slider
+            value={frameIndex}
+            onChange={setSelected}
+            min={0}
+            max={lastFrame}
+            step={1}
+            label={(i) => data.frames[i]?.[0].toFixed(2)}
+          />
+        </>
+      )}
+      {leading.map((index, dim) => (
+        <Stack gap={4} key={dim}>
+          <Text size="sm">
+            Dimension {dim}: {index}
+          </Text>
+          <Slider
+            value={index}
+            onChange={(value) =>
+              setIndices((prev) => {
+                const next = [...prev];
+                next[dim] = value;
+                return next;
+              })
+            }
+            min={0}
+            max={fullShape[dim] - 1}
+            step={1}
+          />
+        </Stack>
+      ))}
+    </Stack>
+  );
+
+  const plotContent = (
+    <div style={{ width: '100%', height: fullScreen ? '100%' : 300 }}>
+      <Plot
+        data={plotData}
+        layout={plotLayout}
+        config={plotConfig}
+        style={{ width: '100%', height: '100%' }}
+      />
+    </div>
+  );
+
+  if (fullScreen) {
+    return (
+      <Box style={{ height: '100%', width: '100%', display: 'flex', flexDirection: 'column' }}>
+        <Box style={{ flex: 1 }}>{plotContent}</Box>
+        {controls}
+      </Box>
+    );
+  }
+
+  return (
+    <Card shadow="sm" p="lg" withBorder>
+      <Card.Section withBorder inheritPadding py="xs">
+        <Group justify="space-between">
+          <Title order={4}>{name}</Title>
+          <Group gap="xs">
+            <Badge>{fullShape.join(' × ') || 'scalar'}</Badge>
+            {downsampled && !fetched && (
+              <Tooltip label="Strided preview, loading full resolution">
+                <Badge color="gray" variant="light">
+                  preview
+                </Badge>
+              </Tooltip>
+            )}
+            {onFullscreen && (
+              <Tooltip label="View Fullscreen">
+                <Button onClick={onFullscreen} variant="subtle">
+                  <IconMaximize size={16} />
+                </Button>
+              </Tooltip>
+            )}
+          </Group>
+        </Group>
+      </Card.Section>
+
+      {plotContent}
+
+      {controls}
+    </Card>
+  );
+}
@@ -1,5 +1,17 @@
-import { useState, useRef, useEffect } from 'react';
-import { ThreeDViewData, ThreeDPrimitive } from '../context/WebSocketContext';
+import { useState, useRef, useEffect, useMemo } from 'react';
+import {
+  ThreeDViewData,
+  ThreeDPrimitive,
+  Asset,
+  Mesh,
+  MeshInstance,
+  Pose,
+  useWebSocket,
+  DecodedOccupancyGrid,
+  DecodedVoxelGrid,
+  occupancyAt,
+  voxelsAt,
+} from '../context/WebSocketContext';
 import { 
   Card, 
   Title, 
@@ -139,9 +151,166 @@ function PointCloud({ points, color, size = 0.05, opacity = 1.0 }: {
   );
 }
 
+// Occupancy grid drawn as a textured plane: free cells white, occupied black and
+// unknown cells faint grey
+function OccupancyGridMesh({ grid, opacity = 0.8 }: { grid: DecodedOccupancyGrid; opacity?: number }) {
+  const texture = useMemo(() => {
+    const pixels = new Uint8Array(grid.width * grid.height * 4);
+    grid.cells.forEach((cell, i) => {
+      const shade = cell < 0 ? 128 : Math.round(255 * (1 - Math.min(cell, 100) / 100));
+      pixels.set([shade, shade, shade, cell < 0 ? 64 : 255], i * 4);
+    });
+    const texture = new THREE.DataTexture(pixels, grid.width, grid.height, THREE.RGBAFormat);
+    texture.magFilter = THREE.NearestFilter;
+    texture.needsUpdate = true;
+    return texture;
+  }, [grid]);
+  useEffect(() => () => texture.dispose(), [texture]);
+
+  const [width, height] = [grid.width * grid.resolution, grid.height * grid.resolution];
+  const yaw = grid.origin.yaw ?? 0;
+  // Cell (0, 0) sits at the origin while the plane geometry is centered
+  const center: [number, number, number] = [
+    grid.origin.x + (width / 2) * Math.cos(yaw) - (height / 2) * Math.sin(yaw),
+    grid.origin.y + (width / 2) * Math.sin(yaw) + (height / 2) * Math.cos(yaw),
+    grid.origin.z ?? 0,
+  ];
+
+  return (
+    <mesh position={center} rotation={[0, 0, yaw]}>
+      <planeGeometry args={[width, height]} />
+      <meshBasicMaterial map={texture} transparent opacity={opacity} side={THREE.DoubleSide} />
+    </mesh>
+  );
+}
+
+// Occupied voxels drawn as one instanced box each
+function VoxelGridMesh({ grid, color }: { grid: DecodedVoxelGrid; color: string }) {
+  const meshRef = useRef<THREE.InstancedMesh>(null);
+
+  useEffect(() => {
+    const mesh = meshRef.current;
+    if (!mesh) return;
+    const matrix = new THREE.Matrix4();
+    grid.voxels.forEach(([x, y, z], i) => {
+      matrix.makeTranslation(
+        grid.origin[0] + (x + 0.5) * grid.resolution,
+        grid.origin[1] + (y + 0.5) * grid.resolution,
+        grid.origin[2] + (z + 0.5) * grid.resolution,
+      );
+      mesh.setMatrixAt(i, matrix);
+    });
+    mesh.instanceMatrix.needsUpdate = true;
+  }, [grid]);
+
+  // The instance count is fixed when the mesh is created, hence the key
+  return (
+    <instancedMesh
+      key={grid.voxels.length}
+      ref={meshRef}
+      args={[undefined, undefined, grid.voxels.length]}
+    >
+      <boxGeometry args={[grid.resolution, grid.resolution, grid.resolution]} />
+      <meshStandardMaterial color={color} />
+    </instancedMesh>
+  );
+}
+
+// Triangle mesh asset as a three.js geometry
+function MeshGeometry({ mesh }: { mesh: Mesh }) {
+  const geometry = useMemo(() => {
+    const geometry = new THREE.BufferGeometry();
+    geometry.setAttribute('position', new THREE.Float32BufferAttribute(mesh.vertices.flat(), 3));
+    geometry.setIndex(mesh.triangles.flat());
+    geometry.computeVertexNormals();
+    return geometry;
+  }, [mesh]);
+  useEffect(() => () => geometry.dispose(), [geometry]);
+  return <primitive object={geometry} attach="geometry" />;
+}
+
+// Placements of mesh assets, e.g. the links of a robot
+function MeshInstances({ instances, assets, color }: {
+  instances: MeshInstance[];
+  assets: Record<string, Asset>;
+  color: string;
+}) {
+  return (
+    <>
+      {instances.map((instance, index) => {
+        const asset = assets[instance.mesh];
+        if (!asset || !('mesh' in asset)) return null;
+        return (
+          <mesh
+            key={index}
+            position={instance.pose.position}
+            quaternion={instance.pose.orientation}
+            scale={instance.scale ?? [1, 1, 1]}
+          >
+            <MeshGeometry mesh={asset.mesh} />
+            <meshStandardMaterial color={instance.color ?? asset.mesh.color ?? color} side={THREE.DoubleSide} />
+          </mesh>
+        );
+      })}
+    </>
+  );
+}
+
+// Image asset drawn on a rectangle in the pose's xy plane
+function TexturedQuad({ texture, pose, size }: {
+  texture: { mime_type: string; data: string };
+  pose: Pose;
+  size: [number, number];
+}) {
+  const map = useMemo(
+    () => new THREE.TextureLoader().load(`data:${texture.mime_type};base64,${texture.data}`),
+    [texture]
+  );
+  useEffect(() => () => map.dispose(), [map]);
+  return (
+    <mesh position={pose.position} quaternion={pose.orientation}>
+      <planeGeometry args={size} />
+      <meshBasicMaterial map={map} side={THREE.DoubleSide} />
+    </mesh>
+  );
+}
+
+// Draws a primitive that refers to assets, or nothing if it refers to none
+function AssetPrimitive({ primitive, assets, color, pointSize }: {
+  primitive: ThreeDPrimitive;
+  assets: Record<string, Asset>;
+  color: string;
+  pointSize: number;
+}) {
+  if ('Meshes' in primitive) {
+    return <MeshInstances instances={primitive.Meshes} assets={assets} color={color} />;
+  }
+  if ('PointCloudInstance' in primitive) {
+    const { cloud, pose, color: cloudColor } = primitive.PointCloudInstance;
+    const asset = assets[cloud];
+    if (!asset || !('point_cloud' in asset)) return null;
+    return (
+      <group position={pose.position} quaternion={pose.orientation}>
+        <PointCloud points={asset.point_cloud} color={cloudColor ?? color} size={pointSize * 0.05} />
+      </group>
+    );
+  }
+  if ('TexturedQuad' in primitive) {
+    const { texture, pose, size } = primitive.TexturedQuad;
+    const asset = assets[texture];
+    if (!asset || !('texture' in asset)) return null;
+    return <TexturedQuad texture={asset.texture} pose={pose} size={size} />;
+  }
+  return null;
+}
+
+// Kinds of primitive that refer to assets. Each shows its most recent placement.
+const ASSET_KINDS = ['Meshes', 'PointCloudInstance', 'TexturedQuad'];
+
 // Scene setup component
-function Scene({ data, color, pointSize, timeIndex, showTrails, trailCount }: { 
+function Scene({ data, assets, color, pointSize, timeIndex, showTrails, trailCount }: { 
   data: ThreeDViewData; 
+  assets: Record<string, Asset>;
   color: string; 
   pointSize: number;
   timeIndex: number;
@@ -174,6 +343,27 @@ function Scene({ data, color, pointSize, timeIndex, showTrails, trailCount }: {
     }
   }
   
+  // Primitives of each asset kind from the last time it was logged. Found with a
+  // loop: spreading the times of 100k+ primitives into Math.max overflows the stack
+  const assetPrimitives = ASSET_KINDS.flatMap((kind) => {
+    let latest = -Infinity;
+    for (const [time, primitive] of data.primatives) {
+      if (kind in primitive && time <= timeIndex && time > latest) latest = time;
+    }
+    return data.primatives.filter(([time, primitive]) => time === latest && kind in primitive);
+  });
+  
+  // Grids are drawn as they stand at the current time, without trails
+  const currentTime = timeValues.filter(t => t <= timeIndex).pop();
+  const occupancy = useMemo(
+    () => (currentTime === undefined ? null : occupancyAt(data.primatives, currentTime)),
+    [data, currentTime]
+  );
+  const voxels = useMemo(
+    () => (currentTime === undefined ? null : voxelsAt(data.primatives, currentTime)),
+    [data, currentTime]
+  );
+  
   return (
     <>
       <GradientBackground />
@@ -212,6 +402,18 @@ function Scene({ data, color, pointSize, timeIndex, showTrails, trailCount }: {
         }
         return null;
       })}
+      
+      {assetPrimitives.map(([time, primitive], index) => (
+        <AssetPrimitive
+          key={`asset-${index}-${time}`}
+          primitive={primitive}
+          assets={assets}
+          color={color}
+          pointSize={pointSize}
+        />
+      ))}
+      {occupancy && <OccupancyGridMesh grid={occupancy} />}
+      {voxels && voxels.voxels.length > 0 && <VoxelGridMesh grid={voxels} color={color} />}
     </>
   );
 }
@@ -220,6 +422,8 @@ export function ThreeDViz({ data, name, fullScreen = false, onFullscreen }: Thre
   const theme = useMantineTheme();
   const computedColorScheme = useComputedColorScheme('dark');
   const isDark = computedColorScheme === 'dark';
+  // The scene cannot read the context itself, it renders in the Canvas' own tree
+  const { assets } = useWebSocket();
   
   // State for controls
   const [pointSize, setPointSize] = useState<number>(3);
@@ -388,6 +592,7 @@ export function ThreeDViz({ data, name, fullScreen = false, onFullscreen }: Thre
     <Canvas style={{ width: '100%', height: '100%', background: 'transparent' }}>
       <Scene 
         data={data} 
+        assets={assets}
         color={pointColor} 
         pointSize={pointSize} 
         timeIndex={timeIndex}
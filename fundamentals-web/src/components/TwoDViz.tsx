@@ -0,0 +1,196 @@
+import { useState, useMemo } from 'react';
+import { TwoDViewData, TwoDPrimitive } from '../context/WebSocketContext';
+import {
+  Card,
+  Text,
+  Title,
+  Stack,
+  Group,
+  Badge,
+  Button,
+  Box,
+  Slider,
+  Switch,
+  Tooltip,
+  useMantineTheme,
+  useComputedColorScheme
+} from '@mantine/core';
+import Plot from 'react-plotly.js';
+import * as Plotly from 'plotly.js';
+import { IconMaximize } from '@tabler/icons-react';
+
+interface TwoDVizProps {
+  data: TwoDViewData;
+  name: string;
+  fullScreen?: boolean;
+  onFullscreen?: () => void;
+}
+
+// Corners of a rotated box, like `TwoDPrimative::positions`
+function boxCorners(center: [number, number], size: [number, number], rotation: number): [number, number][] {
+  const [sin, cos] = [Math.sin(rotation), Math.cos(rotation)];
+  return [[-1, -1], [1, -1], [1, 1], [-1, 1]].map(([sx, sy]) => {
+    const [dx, dy] = [(sx * size[0]) / 2, (sy * size[1]) / 2];
+    return [center[0] + dx * cos - dy * sin, center[1] + dx * sin + dy * cos];
+  });
+}
+
+// One Plotly trace per primitive
+function primitiveTrace(primitive: TwoDPrimitive, color: string): Plotly.Data {
+  const xy = (positions: [number, number][]) => ({
+    x: positions.map(([x]) => x),
+    y: positions.map(([, y]) => y)
+  });
+  const closed = (vertices: [number, number][]) => [...vertices, ...vertices.slice(0, 1)];
+
+  if ('Points' in primitive) {
+    const { positions, colors, sizes } = primitive.Points;
+    return {
+      ...xy(positions),
+      type: 'scatter',
+      mode: 'markers',
+      marker: {
+        // A single entry applies to every point
+        color: colors && colors.length > 0 ? (colors.length === 1 ? colors[0] : colors) : color,
+        size: sizes && sizes.length > 0 ? (sizes.length === 1 ? sizes[0] : sizes) : 6
+      }
+    };
+  }
+  if ('LineStrip' in primitive) {
+    const { positions, color: lineColor } = primitive.LineStrip;
+    return { ...xy(positions), type: 'scatter', mode: 'lines', line: { color: lineColor ?? color } };
+  }
+  if ('Polygon' in primitive) {
+    const { vertices, color: fillColor, filled } = primitive.Polygon;
+    return {
+      ...xy(closed(vertices)),
+      type: 'scatter',
+      mode: 'lines',
+      fill: filled ? 'toself' : 'none',
+      line: { color: fillColor ?? color }
+    };
+  }
+  const { center, size, rotation, color: boxColor } = primitive.Box2D;
+  return {
+    ...xy(closed(boxCorners(center, size, rotation ?? 0))),
+    type: 'scatter',
+    mode: 'lines',
+    line: { color: boxColor ?? color }
+  };
+}
+
+export function TwoDViz({ data, name, fullScreen = false, onFullscreen }: TwoDVizProps) {
+  const theme = useMantineTheme();
+  const computedColorScheme = useComputedColorScheme('dark');
+  const isDark = computedColorScheme === 'dark';
+
+  // Distinct times of the view, assuming primitives are sorted by time
+  const times = useMemo(
+    () => data.primatives.map(([t]) => t).filter((t, i, all) => i === 0 || t !== all[i - 1]),
+    [data]
+  );
+  const lastTime = Math.max(times.length - 1, 0);
+  const [selected, setSelected] = useState<number | null>(null);
+  const timeIndex = Math.min(selected ?? lastTime, lastTime);
+  const currentTime = times[timeIndex] ?? 0;
+  // The trail shows everything up to the current time, otherwise only its primitives
+  const [showTrail, setShowTrail] = useState<boolean>(true);
+
+  const color = theme.colors[theme.primaryColor][isDark ? 4 : 6];
+  const visible = data.primatives.filter(([t]) => (showTrail ? t <= currentTime : t === currentTime));
+  const plotData: Plotly.Data[] = visible.map(([, primitive]) => primitiveTrace(primitive, color));
+
+  const gridcolor = isDark ? 'rgba(255,255,255,0.1)' : 'rgba(0,0,0,0.1)';
+  const plotLayout: Partial<Plotly.Layout> = {
+    autosize: true,
+    title: fullScreen ? name : '',
+    paper_bgcolor: 'transparent',
+    plot_bgcolor: 'transparent',
+    font: {
+      color: isDark ? theme.colors.gray[4] : theme.colors.gray[7]
+    },
+    showlegend: false,
+    margin: {
+      l: 50,
+      r: 20,
+      t: fullScreen ? 30 : 10,
+      b: 50
+    },
+    xaxis: { title: 'x', gridcolor },
+    // Same scale on both axes so shapes keep their proportions
+    yaxis: { title: 'y', gridcolor, scaleanchor: 'x', scaleratio: 1 }
+  };
+
+  const plotConfig: Partial<Plotly.Config> = {
+    responsive: true,
+    displayModeBar: fullScreen,
+    scrollZoom: true
+  };
+
+  const timeControls = (
+    <Stack gap={4} px={fullScreen ? 'xl' : 0} pb={fullScreen ? 'md' : 0}>
+      <Group justify="space-between">
+        <Text size="sm">Time: {currentTime.toFixed(2)}</Text>
+        <Switch
+          label="Show trail"
+          checked={showTrail}
+          onChange={(e) => setShowTrail(e.currentTarget.checked)}
+        />
+      </Group>
+      {times.length > 1 && (
+        <Slider
+          value={timeIndex}
+          onChange={setSelected}
+          min={0}
+          max={lastTime}
+          step={1}
+          label={(i) => times[i]?.toFixed(2)}
+        />
+      )}
+    </Stack>
+  );
+
+  const plotContent = (
+    <div style={{ width: '100%', height: fullScreen ? '100%' : 300 }}>
+      <Plot
+        data={plotData}
+        layout={plotLayout}
+        config={plotConfig}
+        style={{ width: '100%', height: '100%' }}
+      />
+    </div>
+  );
+
+  if (fullScreen) {
+    return (
+      <Box style={{ height: '100%', width: '100%', display: 'flex', flexDirection: 'column' }}>
+        <Box style={{ flex: 1 }}>{plotContent}</Box>
+        {timeControls}
+      </Box>
+    );
+  }
+
+  return (
+    <Card shadow="sm" p="lg" withBorder>
+      <Card.Section withBorder inheritPadding py="xs">
+        <Group justify="space-between">
+          <Title order={4}>{name}</Title>
+          <Group gap="xs">
+            <Badge>{data.primatives.length} primitives</Badge>
+            {onFullscreen && (
+              <Tooltip label="View Fullscreen">
+                <Button onClick={onFullscreen} variant="subtle">
+                  <IconMaximize size={16} />
+                </Button>
+              </Tooltip>
+            )}
+          </Group>
+        </Group>
+      </Card.Section>
+
+      {plotContent}
+
+      {timeControls}
+    </Card>
+  );
+}
@@ -15,13 +15,341 @@ export interface ThreeDViewData {
   primatives: [number, ThreeDPrimitive][];
 }
 
-export type ThreeDPrimitive = {
-  Point: [number, number, number][];
+export type ThreeDPrimitive =
+  | { Point: [number, number, number][] }
+  | { OccupancyGrid: OccupancyGrid }
+  | { OccupancyGridUpdate: GridUpdate }
+  | { VoxelGrid: VoxelGrid }
+  | { VoxelGridUpdate: VoxelUpdate }
+  | { Meshes: MeshInstance[] }
+  | { PointCloudInstance: { cloud: string; pose: Pose; color?: string } }
+  | { TexturedQuad: { texture: string; pose: Pose; size: [number, number] } };
+
+// Top-down views (see fundamentals-core/src/widgets/two_d_view.rs)
+export type TwoDPrimitive =
+  | { Points: { positions: [number, number][]; colors?: string[]; sizes?: number[] } }
+  | { LineStrip: { positions: [number, number][]; color?: string } }
+  | { Polygon: { vertices: [number, number][]; color?: string; filled?: boolean } }
+  | { Box2D: { center: [number, number]; size: [number, number]; rotation?: number; color?: string } };
+
+export interface TwoDViewData {
+  primatives: [number, TwoDPrimitive][];
+}
+
+// N-dimensional arrays, one per frame (see fundamentals-core/src/widgets/tensor.rs).
+// Row-major; the buffer key is the dtype. NaNs arrive as null.
+export type TensorDType = 'u8' | 'u16' | 'u32' | 'u64' | 'i8' | 'i16' | 'i32' | 'i64' | 'f32' | 'f64';
+
+export interface Tensor {
+  shape: number[];
+  buffer: Partial<Record<TensorDType, (number | null)[]>>;
+}
+
+export interface TensorData {
+  frames: [number, Tensor][];
+}
+
+export type DimSlice = 'all' | { index: number } | { range: { start: number; end: number; step?: number } };
+
+export function tensorValues(tensor: Tensor): number[] {
+  const values = Object.values(tensor.buffer)[0] ?? [];
+  return values.map((v) => (v === null ? NaN : v));
+}
+
+// Distributions (see fundamentals-core/src/widgets/histogram.rs). The bridge bins
+// raw samples before sending them, so frames arrive binned.
+export interface HistogramBins {
+  edges: number[];
+  counts: number[];
+}
+
+export type HistogramFrame = { binned: HistogramBins } | { samples: number[] };
+
+export interface HistogramData {
+  frames: [number, HistogramFrame][];
+}
+
+// Meshes and their placements (see fundamentals-core/src/widgets/mesh.rs)
+export interface Pose {
+  position: [number, number, number];
+  // Unit quaternion (x, y, z, w)
+  orientation: [number, number, number, number];
+}
+
+export interface Mesh {
+  vertices: [number, number, number][];
+  triangles: [number, number, number][];
+  color?: string;
+}
+
+export interface MeshInstance {
+  // Id of a mesh asset
+  mesh: string;
+  pose: Pose;
+  scale?: [number, number, number];
+  color?: string;
+}
+
+// Data primitives refer to by id, sent once per connection ahead of the vizs that
+// use it (see fundamentals-core/src/assets.rs)
+export type Asset =
+  | { mesh: Mesh }
+  | { texture: { mime_type: string; data: string } }
+  | { point_cloud: [number, number, number][] };
+
+// Occupancy grids and voxel maps (see fundamentals-core/src/widgets/occupancy.rs)
+export type CellEncoding = { raw: number[] } | { run_length: [number, number][] };
+
+export interface GridOrigin {
+  x: number;
+  y: number;
+  z?: number;
+  yaw?: number;
+}
+
+export interface OccupancyGrid {
+  resolution: number;
+  width: number;
+  height: number;
+  origin: GridOrigin;
+  cells: CellEncoding;
+}
+
+export interface GridUpdate {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+  cells: CellEncoding;
+}
+
+export type Voxel = [number, number, number];
+
+export type VoxelEncoding =
+  | { sparse: Voxel[] }
+  | { bitset: { min: Voxel; dims: [number, number, number]; bits: number[] } };
+
+export interface VoxelGrid {
+  resolution: number;
+  origin: [number, number, number];
+  voxels: VoxelEncoding;
+}
+
+export interface VoxelUpdate {
+  set?: Voxel[];
+  cleared?: Voxel[];
+}
+
+// A grid with its cells decoded, -1 for unknown and 0 to 100 for occupancy
+export interface DecodedOccupancyGrid extends Omit<OccupancyGrid, 'cells'> {
+  cells: Int8Array;
+}
+
+export interface DecodedVoxelGrid extends Omit<VoxelGrid, 'voxels'> {
+  voxels: Voxel[];
+}
+
+// JSON has no NaN or infinities, so the bridge spells them out (see
+// fundamentals-core/src/widgets/non_finite.rs)
+type WireFloat = number | 'NaN' | 'Infinity' | '-Infinity' | null;
+
+function decodeFloat(value: WireFloat): number {
+  return value === null ? NaN : Number(value);
+}
+
+// Values stored with reduced precision (see fundamentals-core/src/widgets/precision.rs)
+export type TypedBuffer =
+  | { f64: WireFloat[] }
+  | { f32: WireFloat[] }
+  | { i16: { scale: number; offset: number; values: number[] } };
+
+// Plot data and 3D primitives as the bridge sends them, before decoding
+interface WirePlotScalarData {
+  data_x?: [WireFloat, WireFloat][];
+  times?: number[];
+  values?: TypedBuffer;
+}
+
+interface PointDelta {
+  len: number;
+  changed?: [number, [number, number, number]][];
+}
+
+type WireThreeDPrimitive =
+  | ThreeDPrimitive
+  | { TypedPoint: TypedBuffer }
+  | { PointDelta: PointDelta };
+
+function decodeBuffer(buffer: TypedBuffer): number[] {
+  if ('f64' in buffer) return buffer.f64.map(decodeFloat);
+  if ('f32' in buffer) return buffer.f32.map(decodeFloat);
+  const { scale, offset, values } = buffer.i16;
+  // i16::MIN is reserved for NaN
+  return values.map((q) => (q === -32768 ? NaN : q * scale + offset));
+}
+
+function decodePlotScalar(data: WirePlotScalarData): PlotScalarData {
+  if (data.data_x || !data.values) {
+    return { data_x: (data.data_x ?? []).map(([t, y]) => [decodeFloat(t), decodeFloat(y)] as [number, number]) };
+  }
+  const values = decodeBuffer(data.values);
+  return { data_x: (data.times ?? []).map((t, i) => [t, values[i]] as [number, number]) };
+}
+
+function decodePrimitive(primitive: Exclude<WireThreeDPrimitive, { PointDelta: PointDelta }>): ThreeDPrimitive {
+  if (!('TypedPoint' in primitive)) return primitive;
+  const coords = decodeBuffer(primitive.TypedPoint);
+  const points: [number, number, number][] = [];
+  for (let i = 0; i + 2 < coords.length; i += 3) {
+    points.push([coords[i], coords[i + 1], coords[i + 2]]);
+  }
+  return { Point: points };
+}
+
+// Replaces every point delta by the cloud it produces from the points before it.
+// Deltas without a cloud to apply to are dropped.
+function decodePrimitives(primatives: [number, WireThreeDPrimitive][]): [number, ThreeDPrimitive][] {
+  let cloud: [number, number, number][] | undefined;
+  const decoded: [number, ThreeDPrimitive][] = [];
+  for (const [t, primitive] of primatives) {
+    if ('PointDelta' in primitive) {
+      if (!cloud) continue;
+      const { len, changed } = primitive.PointDelta;
+      const next = cloud.slice(0, len);
+      while (next.length < len) next.push([0, 0, 0]);
+      for (const [i, point] of changed ?? []) {
+        if (i < len) next[i] = point;
+      }
+      cloud = next;
+      decoded.push([t, { Point: next }]);
+      continue;
+    }
+    const full = decodePrimitive(primitive);
+    if ('Point' in full) cloud = full.Point;
+    decoded.push([t, full]);
+  }
+  return decoded;
+}
+
+function decodeCells(cells: CellEncoding): Int8Array {
+  if ('raw' in cells) return Int8Array.from(cells.raw);
+  const total = cells.run_length.reduce((sum, [count]) => sum + count, 0);
+  const decoded = new Int8Array(total);
+  let i = 0;
+  for (const [count, value] of cells.run_length) {
+    decoded.fill(value, i, i + count);
+    i += count;
+  }
+  return decoded;
+}
+
+function decodeVoxels(voxels: VoxelEncoding): Voxel[] {
+  if ('sparse' in voxels) return voxels.sparse;
+  const { min, dims, bits } = voxels.bitset;
+  const decoded: Voxel[] = [];
+  const volume = dims[0] * dims[1] * dims[2];
+  // Only the set bits of each word, like `VoxelEncoding::decode`
+  bits.forEach((word, w) => {
+    for (let bit = 0; word !== 0 && bit < 32; bit++) {
+      // >>> keeps the words unsigned, they are u32 on the Rust side
+      if (!((word >>> bit) & 1)) continue;
+      word &= ~(1 << bit);
+      const i = w * 32 + bit;
+      if (i >= volume) return;
+      decoded.push([
+        min[0] + (i % dims[0]),
+        min[1] + (Math.floor(i / dims[0]) % dims[1]),
+        min[2] + Math.floor(i / (dims[0] * dims[1])),
+      ]);
+    }
+  });
+  return decoded;
+}
+
+// Primitives from the last one matching `isFull` at or before `t` up to `t`, like
+// `ThreeDViewData::since_full_state`. Assumes `primatives` is sorted by time.
+function sinceFullState(
+  primatives: [number, ThreeDPrimitive][],
+  t: number,
+  isFull: (primitive: ThreeDPrimitive) => boolean,
+): ThreeDPrimitive[] {
+  let end = primatives.findIndex(([time]) => time > t);
+  if (end < 0) end = primatives.length;
+  for (let start = end - 1; start >= 0; start--) {
+    if (isFull(primatives[start][1])) {
+      return primatives.slice(start, end).map(([, primitive]) => primitive);
+    }
+  }
+  return [];
+}
+
+// The occupancy grid at time `t`: the last full grid with every later update applied
+export function occupancyAt(
+  primatives: [number, ThreeDPrimitive][],
+  t: number,
+): DecodedOccupancyGrid | null {
+  const [full, ...rest] = sinceFullState(primatives, t, (p) => 'OccupancyGrid' in p);
+  if (!full || !('OccupancyGrid' in full)) return null;
+  const grid = { ...full.OccupancyGrid, cells: decodeCells(full.OccupancyGrid.cells) };
+  for (const primitive of rest) {
+    if (!('OccupancyGridUpdate' in primitive)) continue;
+    const update = primitive.OccupancyGridUpdate;
+    const patch = decodeCells(update.cells);
+    for (let row = 0; row < update.height; row++) {
+      for (let col = 0; col < update.width; col++) {
+        const [x, y] = [update.x + col, update.y + row];
+        if (x < grid.width && y < grid.height && row * update.width + col < patch.length) {
+          grid.cells[y * grid.width + x] = patch[row * update.width + col];
+        }
+      }
+    }
+  }
+  return grid;
+}
+
+// The voxel map at time `t`, built like `occupancyAt`
+export function voxelsAt(primatives: [number, ThreeDPrimitive][], t: number): DecodedVoxelGrid | null {
+  const [full, ...rest] = sinceFullState(primatives, t, (p) => 'VoxelGrid' in p);
+  if (!full || !('VoxelGrid' in full)) return null;
+  const key = (v: Voxel) => v.join(',');
+  const voxels = new Map(decodeVoxels(full.VoxelGrid.voxels).map((v) => [key(v), v]));
+  for (const primitive of rest) {
+    if (!('VoxelGridUpdate' in primitive)) continue;
+    for (const v of primitive.VoxelGridUpdate.cleared ?? []) voxels.delete(key(v));
+    for (const v of primitive.VoxelGridUpdate.set ?? []) voxels.set(key(v), v);
+  }
+  return { ...full.VoxelGrid, voxels: [...voxels.values()] };
+}
+
+// Expands typed buffers and point deltas into the plain arrays the components draw
+function decodeViz(viz: Viz): Viz {
+  return {
+    ...viz,
+    widgets: viz.widgets.map((widget) => {
+      if (widget.plot_scalar) {
+        return { ...widget, plot_scalar: decodePlotScalar(widget.plot_scalar as WirePlotScalarData) };
+      }
+      if (widget['3d_view']) {
+        const primatives = widget['3d_view'].primatives as [number, WireThreeDPrimitive][];
+        return {
+          ...widget,
+          '3d_view': {
+            ...widget['3d_view'],
+            primatives: decodePrimitives(primatives),
+          },
+        };
+      }
+      return widget;
+    }),
+  };
 }
 
 export interface PlotWidget {
   plot_scalar?: PlotScalarData;
   '3d_view'?: ThreeDViewData;
+  histogram?: HistogramData;
+  '2d_view'?: TwoDViewData;
+  tensor?: TensorData;
 }
 
 // Define the Viz type based on what's coming from the backend
@@ -30,19 +358,104 @@ export interface Viz {
   source: string | null;
   widgets: PlotWidget[];
   range: Record<string, unknown> | null;
+  metadata?: Record<string, unknown>;
 }
 
-// Define the message type from the Rust backend
-export interface WSMessage {
-  VizUpdate: Viz;
+// Recording a viz was sent from, set by the bridge in the viz metadata
+export function vizSession(viz: Viz): string | undefined {
+  const sessionId = viz.metadata?.session_id;
+  return typeof sessionId === 'string' ? sessionId : undefined;
+}
+
+// Zoomed ranges and tensor slices are kept per recording, since open recordings can
+// share viz names
+export function plotRangeKey(sessionId: string | undefined, viz: string): string {
+  return `${sessionId ?? ''}/${viz}`;
+}
+
+// Response to a PlotRange request (see fundamentals-bridge/src/requests.rs)
+export interface PlotRangeResponse {
+  session_id: string;
+  viz: string;
+  t0: number;
+  t1: number;
+  total_points: number;
+  data: PlotScalarData;
+}
+
+// Response to a TensorSlice request: full resolution part of the frame at `time`
+export interface TensorSliceResponse {
+  session_id: string;
+  viz: string;
+  time: number;
+  slices: DimSlice[];
+  tensor: Tensor;
 }
 
+// Event markers (see fundamentals-core/src/annotation.rs)
+export type AnnotationTime = { instant: number } | { interval: [number, number] };
+
+export interface Annotation {
+  label: string;
+  time: AnnotationTime;
+  color?: string;
+  // Names of the vizs the annotation is scoped to, every viz if empty
+  vizs?: string[];
+}
+
+export function annotationSpan(annotation: Annotation): [number, number] {
+  return 'instant' in annotation.time
+    ? [annotation.time.instant, annotation.time.instant]
+    : annotation.time.interval;
+}
+
+// Define the message type from the Rust backend
+export type WSMessage =
+  | { VizUpdate: Viz }
+  | { PlotRange: PlotRangeResponse }
+  | { TensorSlice: TensorSliceResponse }
+  | { Annotations: { session_id: string; annotations: Annotation[] } }
+  | { AnnotationAdded: { session_id: string; annotation: Annotation } }
+  | { Assets: Record<string, Asset> }
+  | { Error: string };
+
+// Requests the viewer can send to the bridge
+export type WSRequest =
+  | {
+      PlotRange: {
+        session_id?: string;
+        viz: string;
+        t0: number;
+        t1: number;
+        points: number;
+        algorithm?: 'lttb' | 'min_max';
+      };
+    }
+  | {
+      TensorSlice: {
+        session_id?: string;
+        viz: string;
+        t?: number;
+        slices: DimSlice[];
+      };
+    };
+
 interface WebSocketContextType {
   isConnected: boolean;
   messages: Viz[];
+  // Zoomed ranges, by plotRangeKey
+  plotRanges: Record<string, PlotRangeResponse>;
+  // Latest tensor slice of each tensor viz, by plotRangeKey
+  tensorSlices: Record<string, TensorSliceResponse>;
+  // Annotations of every open recording, by session id
+  annotations: Record<string, Annotation[]>;
+  // Assets received so far, by id
+  assets: Record<string, Asset>;
   error: string | null;
   connectionUrl: string;
   clearMessages: () => void;
+  sendRequest: (request: WSRequest) => void;
+  clearPlotRange: (key: string) => void;
 }
 
 const WebSocketContext = createContext<WebSocketContextType | undefined>(undefined);
@@ -77,6 +490,10 @@ export function WebSocketProvider({
   const [socket, setSocket] = useState<WebSocket | null>(null);
   const [isConnected, setIsConnected] = useState(false);
   const [messages, setMessages] = useState<Viz[]>([]);
+  const [plotRanges, setPlotRanges] = useState<Record<string, PlotRangeResponse>>({});
+  const [tensorSlices, setTensorSlices] = useState<Record<string, TensorSliceResponse>>({});
+  const [annotations, setAnnotations] = useState<Record<string, Annotation[]>>({});
+  const [assets, setAssets] = useState<Record<string, Asset>>({});
   const [error, setError] = useState<string | null>(null);
   const [connectionUrl, setConnectionUrl] = useState(defaultUrl);
   const reconnectTimer = useRef<number | undefined>(undefined);
@@ -88,6 +505,22 @@ export function WebSocketProvider({
     setMessages([]);
   };
 
+  // Send a request to the bridge if the socket is open
+  const sendRequest = (request: WSRequest) => {
+    if (socket && socket.readyState === WebSocket.OPEN) {
+      socket.send(JSON.stringify(request));
+    }
+  };
+
+  // Drop a zoomed-in range so the plot falls back to the full series
+  const clearPlotRange = (key: string) => {
+    setPlotRanges((prev) => {
+      const next = { ...prev };
+      delete next[key];
+      return next;
+    });
+  };
+
   // Internal function to establish connection
   function connect(url: string) {
     // Close any existing connection
@@ -112,8 +545,37 @@ export function WebSocketProvider({
         try {
           const data = JSON.parse(event.data) as WSMessage;
           if ('VizUpdate' in data) {
-            setMessages((prev) => [...prev, data.VizUpdate]);
+            const viz = decodeViz(data.VizUpdate);
+            setMessages((prev) => [...prev, viz]);
             console.log('Received viz update:', data.VizUpdate);
+          } else if ('PlotRange' in data) {
+            const range = {
+              ...data.PlotRange,
+              data: decodePlotScalar(data.PlotRange.data as WirePlotScalarData),
+            };
+            setPlotRanges((prev) => ({
+              ...prev,
+              [plotRangeKey(range.session_id, range.viz)]: range,
+            }));
+          } else if ('TensorSlice' in data) {
+            const slice = data.TensorSlice;
+            setTensorSlices((prev) => ({
+              ...prev,
+              [plotRangeKey(slice.session_id, slice.viz)]: slice,
+            }));
+          } else if ('Annotations' in data) {
+            const { session_id, annotations } = data.Annotations;
+            setAnnotations((prev) => ({ ...prev, [session_id]: annotations }));
+          } else if ('AnnotationAdded' in data) {
+            const { session_id, annotation } = data.AnnotationAdded;
+            setAnnotations((prev) => ({
+              ...prev,
+              [session_id]: [...(prev[session_id] ?? []), annotation],
+            }));
+          } else if ('Assets' in data) {
+            setAssets((prev) => ({ ...prev, ...data.Assets }));
+          } else if ('Error' in data) {
+            console.error('Bridge error:', data.Error);
           }
         } catch (err) {
           console.error('Error parsing WebSocket message:', err);
@@ -187,9 +649,15 @@ export function WebSocketProvider({
   const value = {
     isConnected,
     messages,
+    plotRanges,
+    tensorSlices,
+    annotations,
+    assets,
     error,
     connectionUrl,
-    clearMessages
+    clearMessages,
+    sendRequest,
+    clearPlotRange
   };
 
   return (
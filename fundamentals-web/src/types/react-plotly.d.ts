@@ -14,6 +14,7 @@ declare module 'react-plotly.js' {
     onRender?: () => void;
     onError?: () => void;
     onPurge?: () => void;
+    onRelayout?: (event: Readonly<Plotly.PlotRelayoutEvent>) => void;
     useResizeHandler?: boolean;
   }
   